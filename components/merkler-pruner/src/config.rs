@@ -0,0 +1,99 @@
+use alloy_primitives::Address;
+use std::str::FromStr;
+
+use crate::bindings::{
+    host::{self, config_var},
+    wavs::types::service::{AggregatorSubmit, Submit},
+};
+
+/// How many expired envelopes to prune per run
+pub enum PrunePolicy {
+    /// Prune up to N expired envelopes, oldest first
+    Count(u64),
+    /// Prune expired envelopes until only the last N remain
+    KeepLast(u64),
+    /// Prune envelopes that expired more than T seconds ago
+    OlderThan(u64),
+}
+
+pub struct PrunerConfig {
+    /// Seconds until the prune payload expires
+    pub expiry_seconds: u64,
+    /// Pruning policy
+    pub policy: PrunePolicy,
+    /// Maximum number of envelopes to prune in a single run
+    pub max_prune: u64,
+    /// MerkleSnapshot contract to inspect for the keep-last and older-than policies
+    pub merkle_snapshot_address: Option<Address>,
+    /// Chain the MerkleSnapshot contract lives on
+    pub chain_name: String,
+}
+
+impl PrunerConfig {
+    pub fn load(trigger_chain: Option<&str>) -> Result<Self, String> {
+        let expiry_seconds = parse_config_var("merkle_expiry_seconds")?.unwrap_or(5 * 60);
+        if expiry_seconds == 0 {
+            return Err("merkle_expiry_seconds must be greater than 0".to_string());
+        }
+
+        let max_prune = parse_config_var("prune_count")?.unwrap_or(100);
+        // A prune count of zero would make MerkleSnapshot treat the payload as a root update,
+        // replacing the active root with an empty one.
+        if max_prune == 0 {
+            return Err("prune_count must be greater than 0".to_string());
+        }
+
+        let policy = match config_var("prune_policy").as_deref().unwrap_or("count") {
+            "count" => PrunePolicy::Count(max_prune),
+            "keep_last" => PrunePolicy::KeepLast(
+                parse_config_var("prune_keep_last")?
+                    .ok_or("prune_policy keep_last requires prune_keep_last")?,
+            ),
+            "older_than" => PrunePolicy::OlderThan(
+                parse_config_var("prune_older_than_seconds")?
+                    .ok_or("prune_policy older_than requires prune_older_than_seconds")?,
+            ),
+            other => {
+                return Err(format!(
+                    "Invalid prune_policy '{other}', expected one of: count, keep_last, older_than"
+                ))
+            }
+        };
+
+        let chain_name = config_var("chain_name")
+            .or_else(|| trigger_chain.map(|chain| chain.to_string()))
+            .unwrap_or_else(|| "local".to_string());
+
+        let merkle_snapshot_address = match config_var("merkle_snapshot_address") {
+            Some(address) => Some(
+                Address::from_str(&address)
+                    .map_err(|e| format!("Failed to parse merkle_snapshot_address: {e}"))?,
+            ),
+            None => submit_contract_address(&chain_name),
+        };
+
+        if !matches!(policy, PrunePolicy::Count(_)) && merkle_snapshot_address.is_none() {
+            return Err("prune_policy requires merkle_snapshot_address".to_string());
+        }
+
+        Ok(Self { expiry_seconds, policy, max_prune, merkle_snapshot_address, chain_name })
+    }
+}
+
+fn parse_config_var(key: &str) -> Result<Option<u64>, String> {
+    config_var(key)
+        .map(|value| value.parse().map_err(|e| format!("Failed to parse {key}: {e}")))
+        .transpose()
+}
+
+/// Find the contract this workflow submits to on the given chain, via the aggregator submit config.
+fn submit_contract_address(chain_name: &str) -> Option<Address> {
+    match host::get_workflow().workflow.submit {
+        Submit::Aggregator(AggregatorSubmit { component, .. }) => component
+            .config
+            .iter()
+            .find(|(key, _)| key == chain_name)
+            .and_then(|(_, address)| Address::from_str(address).ok()),
+        _ => None,
+    }
+}
@@ -1,9 +1,13 @@
 pub mod bindings;
+mod config;
 
 use crate::bindings::{export, Guest, TriggerAction, WasmResponse};
-use alloy_primitives::{FixedBytes, U256};
-use alloy_provider::Provider;
-use alloy_sol_types::SolValue;
+use crate::config::{PrunePolicy, PrunerConfig};
+use alloy_network::Ethereum;
+use alloy_primitives::{Address, FixedBytes, TxKind, U256};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{TransactionInput, TransactionRequest};
+use alloy_sol_types::{SolCall, SolValue};
 use wstd::runtime::block_on;
 
 struct Component;
@@ -11,27 +15,138 @@ export!(Component with_types_in bindings);
 
 impl Guest for Component {
     fn run(action: TriggerAction) -> std::result::Result<Option<WasmResponse>, String> {
-        let current_time = block_on(async move { action.execution_timestamp_seconds().await })?;
-        let payload = solidity::MerklerAvsOutput {
-            // Expire in 5 minutes.
-            expiresAt: U256::from(current_time + 5 * 60),
-            prune: U256::from(100),
-            root: FixedBytes::<32>::ZERO,
-            ipfsHash: FixedBytes::<32>::ZERO,
-            ipfsHashCid: String::new(),
-            totalValue: U256::ZERO,
-        };
-        Ok(Some(WasmResponse { payload: payload.abi_encode().into(), ordering: None }))
+        let config = PrunerConfig::load(action.chain())?;
+
+        block_on(async move {
+            let current_time = action.execution_timestamp_seconds().await?;
+
+            let prune = prune_count(&config, current_time).await?;
+            if prune == 0 {
+                println!("ℹ️  Nothing to prune");
+                return Ok(None);
+            }
+            println!("✂️  Pruning {} expired envelopes", prune);
+
+            let payload = solidity::MerklerAvsOutput {
+                expiresAt: U256::from(current_time + config.expiry_seconds),
+                prune: U256::from(prune),
+                root: FixedBytes::<32>::ZERO,
+                ipfsHash: FixedBytes::<32>::ZERO,
+                ipfsHashCid: String::new(),
+                totalValue: U256::ZERO,
+            };
+            Ok(Some(WasmResponse { payload: payload.abi_encode().into(), ordering: None }))
+        })
     }
 }
 
+/// Get the number of envelopes to prune according to the configured policy.
+///
+/// Pruning only removes the replay protection of expired envelopes, never the active root. Callers
+/// must never submit a count of zero though, since MerkleSnapshot would then treat the payload as a
+/// root update and replace the active root.
+async fn prune_count(config: &PrunerConfig, current_time: u64) -> Result<u64, String> {
+    // The contract stops at the first envelope that has not expired, so no lookups are needed.
+    if let PrunePolicy::Count(count) = config.policy {
+        return Ok(count);
+    }
+
+    let address = config.merkle_snapshot_address.ok_or("merkle_snapshot_address not configured")?;
+    let chain_config = bindings::host::get_evm_chain_config(&config.chain_name)
+        .ok_or_else(|| format!("Failed to get chain config for {}", config.chain_name))?;
+    let provider = wavs_wasi_utils::evm::new_evm_provider::<Ethereum>(
+        chain_config
+            .http_endpoint
+            .ok_or_else(|| format!("Failed to get HTTP endpoint for {}", config.chain_name))?,
+    );
+
+    let start =
+        call(&provider, address, solidity::envelopeExpirationQueueStartCall {}).await?.to::<u64>();
+    let end =
+        call(&provider, address, solidity::envelopeExpirationQueueEndCall {}).await?.to::<u64>();
+    let size = end.saturating_sub(start);
+
+    // The contract stops at the first envelope that hasn't expired, so only the per-run cap applies
+    let prunable = size.min(config.max_prune);
+
+    let count = match config.policy {
+        PrunePolicy::Count(count) => count,
+        PrunePolicy::KeepLast(keep_last) => size.saturating_sub(keep_last),
+        PrunePolicy::OlderThan(older_than_seconds) => {
+            let mut count = 0;
+            while count < prunable {
+                let event_id = call(
+                    &provider,
+                    address,
+                    solidity::envelopeExpirationQueueCall { index: U256::from(start + count) },
+                )
+                .await?;
+                let expires_at =
+                    call(&provider, address, solidity::envelopesSeenCall { eventId: event_id })
+                        .await?;
+                if expires_at + U256::from(older_than_seconds) > U256::from(current_time) {
+                    break;
+                }
+                count += 1;
+            }
+            count
+        }
+    };
+
+    println!("📦 Envelope queue size: {}, prunable: {}", size, prunable);
+
+    Ok(count.min(prunable))
+}
+
+/// Call a view function on a contract.
+async fn call<C: SolCall>(
+    provider: &RootProvider<Ethereum>,
+    address: Address,
+    call: C,
+) -> Result<C::Return, String> {
+    let tx = TransactionRequest {
+        to: Some(TxKind::Call(address)),
+        input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
+        ..Default::default()
+    };
+
+    let result =
+        provider.call(tx).await.map_err(|e| format!("Failed to call {}: {}", C::SIGNATURE, e))?;
+
+    C::abi_decode_returns(&result)
+        .map_err(|e| format!("Failed to decode {} result: {}", C::SIGNATURE, e))
+}
+
 pub mod solidity {
     use alloy_sol_macro::sol;
     pub use IMerkler::*;
+    pub use IUniqueEnvelope::*;
     sol!("../../src/interfaces/merkle/IMerkler.sol");
+
+    sol! {
+        interface IUniqueEnvelope {
+            function envelopeExpirationQueueStart() external view returns (uint256);
+            function envelopeExpirationQueueEnd() external view returns (uint256);
+            function envelopeExpirationQueue(uint256 index) external view returns (bytes20);
+            function envelopesSeen(bytes20 eventId) external view returns (uint256);
+        }
+    }
 }
 
 impl TriggerAction {
+    /// Get the chain this component was triggered on, if the trigger is chain-based.
+    pub fn chain(&self) -> Option<&str> {
+        match &self.data {
+            crate::bindings::wavs::types::events::TriggerData::EvmContractEvent(
+                crate::bindings::wavs::types::events::TriggerDataEvmContractEvent { chain, .. },
+            )
+            | crate::bindings::wavs::types::events::TriggerData::BlockInterval(
+                crate::bindings::wavs::types::events::TriggerDataBlockInterval { chain, .. },
+            ) => Some(chain),
+            _ => None,
+        }
+    }
+
     /// Get the timestamp in seconds since Unix epoch that this component is executing at.
     pub async fn execution_timestamp_seconds(&self) -> Result<u64, String> {
         let (chain, block_number) = match &self.data {
@@ -0,0 +1,31 @@
+use std::{path::Path, process::Command};
+
+/// Record the git commit the component is built from in the reproducibility manifest. CI can set
+/// `TRUST_GRAPH_BUILD_HASH` instead, e.g. when building outside a git checkout.
+fn main() {
+    println!("cargo:rerun-if-env-changed=TRUST_GRAPH_BUILD_HASH");
+    for path in ["../../.git/HEAD", "../../.git/index", "../../.git/refs"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+
+    let build_hash = std::env::var("TRUST_GRAPH_BUILD_HASH")
+        .ok()
+        .or_else(git_commit)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=TRUST_GRAPH_BUILD_HASH={}", build_hash);
+}
+
+/// The current commit, suffixed with `-dirty` if tracked files have uncommitted changes.
+fn git_commit() -> Option<String> {
+    let git = |args: &[&str]| {
+        let output = Command::new("git").args(args).output().ok()?;
+        output.status.success().then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+
+    let commit = git(&["rev-parse", "HEAD"])?;
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .is_some_and(|status| !status.is_empty());
+    Some(if dirty { format!("{}-dirty", commit) } else { commit })
+}
@@ -0,0 +1,151 @@
+use alloy_network::Ethereum;
+use alloy_provider::Provider;
+use alloy_provider::RootProvider;
+use alloy_rpc_types::{BlockId, BlockNumberOrTag};
+use serde::Serialize;
+use wavs_wasi_utils::evm::new_evm_provider;
+
+use crate::{
+    bindings::{
+        wavs::types::events::{
+            EvmEventLog, TriggerData, TriggerDataBlockInterval, TriggerDataEvmContractEvent,
+        },
+        TriggerAction,
+    },
+    config::MerklerConfig,
+};
+
+/// Where the pinned block number came from
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinnedBlockSource {
+    /// The `pinned_block` config var, used to reproduce a previous run
+    Config,
+    /// The block of the trigger, so every operator reads the same state
+    Trigger,
+    /// The last block at or before the trigger time minus `trigger_time_lag_seconds`, for triggers
+    /// without a block on the configured chain
+    TriggerTime,
+    /// The latest block, for dry runs of raw triggers
+    Latest,
+}
+
+/// Block that every chain read of a run is pinned to, so the merkle root is reproducible
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PinnedBlock {
+    pub number: u64,
+    pub source: PinnedBlockSource,
+    /// Number of blocks subtracted from the trigger block or the block at the trigger time
+    pub finality_lag: u64,
+}
+
+impl PinnedBlock {
+    /// Resolve the block to pin reads to: the configured block if set, otherwise the trigger's
+    /// block (or the block at the trigger time) minus the configured finality lag. Raw triggers
+    /// have no time every operator agrees on, so they read at the latest block in dry runs and
+    /// need `pinned_block` to publish.
+    pub async fn resolve(
+        action: &TriggerAction,
+        config: &MerklerConfig,
+        dry_run: bool,
+    ) -> Result<Self, String> {
+        if let Some(number) = config.pinned_block {
+            return Ok(Self { number, source: PinnedBlockSource::Config, finality_lag: 0 });
+        }
+
+        let provider = new_evm_provider::<Ethereum>(config.http_endpoint.clone());
+        let (block_number, source) = match trigger_block_number(action, &config.chain_name) {
+            Some(block_number) => (block_number, PinnedBlockSource::Trigger),
+            None if matches!(action.data, TriggerData::Raw(_)) => {
+                if !dry_run {
+                    return Err("Raw triggers need pinned_block to publish a root, since the \
+                                latest block differs between operators"
+                        .to_string());
+                }
+                let block_number = provider
+                    .get_block_number()
+                    .await
+                    .map_err(|e| format!("Failed to get latest block number: {}", e))?;
+                (block_number, PinnedBlockSource::Latest)
+            }
+            None => {
+                let trigger_time = action.execution_timestamp_seconds().await?;
+                let timestamp = trigger_time.saturating_sub(config.trigger_time_lag_seconds);
+                (block_at_time(&provider, timestamp).await?, PinnedBlockSource::TriggerTime)
+            }
+        };
+
+        Ok(Self {
+            number: block_number.saturating_sub(config.finality_lag),
+            source,
+            finality_lag: config.finality_lag,
+        })
+    }
+
+    pub fn block_id(&self) -> BlockId {
+        BlockId::number(self.number)
+    }
+}
+
+/// Get the block number of the trigger, if it happened on the given chain.
+fn trigger_block_number(action: &TriggerAction, chain_name: &str) -> Option<u64> {
+    match &action.data {
+        TriggerData::EvmContractEvent(TriggerDataEvmContractEvent {
+            chain,
+            log: EvmEventLog { block_number, .. },
+            ..
+        }) if chain == chain_name => Some(*block_number),
+        TriggerData::BlockInterval(TriggerDataBlockInterval { chain, block_height })
+            if chain == chain_name =>
+        {
+            Some(*block_height)
+        }
+        _ => None,
+    }
+}
+
+/// Find the last block with a timestamp at or before `timestamp`. Fails if the latest block isn't
+/// past it yet, since a block at or before it could still be added.
+async fn block_at_time(provider: &RootProvider<Ethereum>, timestamp: u64) -> Result<u64, String> {
+    let (latest, latest_timestamp) = block_timestamp(provider, BlockNumberOrTag::Latest).await?;
+    if latest_timestamp <= timestamp {
+        return Err(format!(
+            "Latest block {} isn't past timestamp {} yet, increase trigger_time_lag_seconds",
+            latest, timestamp
+        ));
+    }
+
+    let (_, genesis_timestamp) = block_timestamp(provider, BlockNumberOrTag::Number(0)).await?;
+    if genesis_timestamp > timestamp {
+        return Err(format!("No block at or before timestamp {}", timestamp));
+    }
+
+    // Block `low` is at or before the timestamp and block `high` after it
+    let (mut low, mut high) = (0, latest);
+    while high - low > 1 {
+        let middle = low + (high - low) / 2;
+        let (_, middle_timestamp) =
+            block_timestamp(provider, BlockNumberOrTag::Number(middle)).await?;
+        if middle_timestamp <= timestamp {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+
+    Ok(low)
+}
+
+/// Get the number and timestamp of a block.
+async fn block_timestamp(
+    provider: &RootProvider<Ethereum>,
+    block: BlockNumberOrTag,
+) -> Result<(u64, u64), String> {
+    let block = provider
+        .get_block(BlockId::Number(block))
+        .await
+        .map_err(|e| format!("Failed to get block {}: {}", block, e))?
+        .ok_or_else(|| format!("Block {} not found", block))?;
+
+    Ok((block.header.number, block.header.timestamp))
+}
@@ -1,38 +1,123 @@
 use alloy_dyn_abi::DynSolType;
-use pagerank::{PageRankConfig, TrustConfig};
+use pagerank::{DuplicatePolicy, PageRankConfig, SelfEdgePolicy, TrustConfig};
 use wavs_wasi_utils::evm::alloy_primitives::{Address, U256};
 
-use crate::bindings::host::{config_var, get_evm_chain_config};
-use std::{path::PathBuf, str::FromStr};
+use crate::bindings::{
+    host::{self, config_var, get_evm_chain_config},
+    wavs::types::service::{AggregatorSubmit, Submit},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    str::FromStr,
+};
+
+/// Where the per-account events are written after each run
+pub enum EventsOutput {
+    /// One JSON file per account in a directory
+    Directory(PathBuf),
+    /// A single JSONL file with one line per account
+    Jsonl(PathBuf),
+    /// A single JSON document with all account events uploaded to IPFS
+    Ipfs,
+    /// Do not output account events
+    None,
+}
+
+impl EventsOutput {
+    fn load() -> Result<Self, String> {
+        let output = config_var("events_output").unwrap_or_else(|| "directory".to_string());
+
+        let events_output = match output.as_str() {
+            "directory" => {
+                let events_dir = config_var("events_dir").unwrap_or_else(|| "./events".to_string());
+                let events_dir = PathBuf::from(events_dir.trim_end_matches("/"));
+
+                if !events_dir.exists() {
+                    std::fs::create_dir_all(&events_dir).map_err(|e| {
+                        format!(
+                            "Failed to create events directory at {}: {}",
+                            events_dir.display(),
+                            e.to_string()
+                        )
+                    })?;
+                } else if !events_dir.is_dir() {
+                    return Err(format!(
+                        "Events directory {} is not a directory",
+                        events_dir.display()
+                    ));
+                }
+
+                EventsOutput::Directory(events_dir)
+            }
+            "jsonl" => {
+                let events_file =
+                    config_var("events_file").unwrap_or_else(|| "./events.jsonl".to_string());
+                let events_file = PathBuf::from(events_file);
+
+                if let Some(parent) = events_file.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    std::fs::create_dir_all(parent).map_err(|e| {
+                        format!(
+                            "Failed to create events file directory at {}: {}",
+                            parent.display(),
+                            e
+                        )
+                    })?;
+                }
+
+                EventsOutput::Jsonl(events_file)
+            }
+            "ipfs" => EventsOutput::Ipfs,
+            "none" => EventsOutput::None,
+            other => {
+                return Err(format!(
+                    "Invalid events_output '{other}', expected one of: directory, jsonl, ipfs, none"
+                ))
+            }
+        };
+
+        Ok(events_output)
+    }
+}
 
 pub struct MerklerConfig {
-    pub events_dir: PathBuf,
+    pub events_output: EventsOutput,
     pub eas_address: String,
     pub indexer_address: String,
     pub chain_name: String,
     pub chain_id: String,
     pub http_endpoint: String,
     pub ipfs_url: String,
+    pub ipfs_gateway_url: String,
     pub ipfs_api_key: Option<String>,
+    /// Pinata gateway token sent with downloads, separate from the upload API key
+    pub ipfs_gateway_token: Option<String>,
+    /// MerkleSnapshot contract the roots are published to, used to find the previous snapshot
+    pub merkle_snapshot_address: Option<Address>,
+    /// Explicit CID of the previous snapshot, overriding the on-chain lookup
+    pub previous_snapshot_cid: Option<String>,
+    /// Seconds until the merkle root payload expires
+    pub expiry_seconds: u64,
+    /// Compute everything but skip uploads and return a preview instead of the on-chain payload
+    pub dry_run: bool,
+    /// Dry run when triggered by a raw trigger, i.e. a local `wavs exec` run
+    pub raw_trigger_dry_run: bool,
+    /// Block to pin all reads to, overriding the trigger block (e.g. to reproduce a run)
+    pub pinned_block: Option<u64>,
+    /// Number of blocks to stay behind the trigger or latest block
+    pub finality_lag: u64,
+    /// Seconds to stay behind the trigger time, for triggers without a block on the chain
+    pub trigger_time_lag_seconds: u64,
+    /// Skip the run when its inputs match those of the previous snapshot
+    pub skip_if_unchanged: bool,
+    /// Minimum number of blocks between roots published for `EventIndexed` triggers
+    pub event_debounce_blocks: u64,
 }
 
 impl MerklerConfig {
     pub fn load() -> Result<Self, String> {
-        // Events directory configuration
-        let events_dir = config_var("events_dir").unwrap_or_else(|| "./events".to_string());
-        let events_dir = PathBuf::from(events_dir.trim_end_matches("/"));
-
-        if !events_dir.exists() {
-            std::fs::create_dir_all(&events_dir).map_err(|e| {
-                format!(
-                    "Failed to create events directory at {}: {}",
-                    events_dir.display(),
-                    e.to_string()
-                )
-            })?;
-        } else if !events_dir.is_dir() {
-            return Err(format!("Events directory {} is not a directory", events_dir.display()));
-        }
+        // Account events output configuration
+        let events_output = EventsOutput::load()?;
 
         // EAS-related configuration
         let eas_address = config_var("eas_address").ok_or_else(|| "Failed to get EAS address")?;
@@ -46,18 +131,64 @@ impl MerklerConfig {
         println!("  - Chain: {}", chain_name);
 
         // IPFS configuration - use Pinata only if api_key exists and is nonempty, otherwise local IPFS
-        let (ipfs_url, ipfs_api_key) = match std::env::var("WAVS_ENV_PINATA_API_KEY") {
-            Ok(api_key) if !api_key.trim().is_empty() => {
-                let url = std::env::var("WAVS_ENV_PINATA_API_URL")
-                    .unwrap_or_else(|_| "https://uploads.pinata.cloud/v3/files".to_string());
-                println!("🌐 Using Pinata IPFS service");
-                (url, Some(api_key))
-            }
-            _ => {
-                println!("🏠 Pinata API key not set or empty, using local IPFS node");
-                ("http://localhost:5001/api/v0/add".to_string(), None)
-            }
+        let (ipfs_url, ipfs_gateway_url, ipfs_api_key) =
+            match std::env::var("WAVS_ENV_PINATA_API_KEY") {
+                Ok(api_key) if !api_key.trim().is_empty() => {
+                    let url = std::env::var("WAVS_ENV_PINATA_API_URL")
+                        .unwrap_or_else(|_| "https://uploads.pinata.cloud/v3/files".to_string());
+                    let gateway_url = std::env::var("WAVS_ENV_PINATA_GATEWAY_URL")
+                        .unwrap_or_else(|_| "https://gateway.pinata.cloud/ipfs".to_string());
+                    println!("🌐 Using Pinata IPFS service");
+                    (url, gateway_url, Some(api_key))
+                }
+                _ => {
+                    println!("🏠 Pinata API key not set or empty, using local IPFS node");
+                    (
+                        "http://localhost:5001/api/v0/add".to_string(),
+                        "http://127.0.0.1:8080/ipfs".to_string(),
+                        None,
+                    )
+                }
+            };
+
+        let ipfs_gateway_token = std::env::var("WAVS_ENV_PINATA_GATEWAY_TOKEN")
+            .ok()
+            .filter(|token| !token.trim().is_empty());
+
+        // Snapshot history configuration
+        let merkle_snapshot_address = match config_var("merkle_snapshot_address") {
+            Some(address) => Some(
+                Address::from_str(&address)
+                    .map_err(|e| format!("Failed to parse merkle_snapshot_address: {e}"))?,
+            ),
+            None => submit_contract_address(&chain_name),
         };
+        let previous_snapshot_cid =
+            config_var("previous_snapshot_cid").filter(|cid| !cid.trim().is_empty());
+
+        match (&previous_snapshot_cid, &merkle_snapshot_address) {
+            (Some(cid), _) => println!("  - Previous snapshot CID: {}", cid),
+            (None, Some(address)) => println!("  - Merkle snapshot: {}", address),
+            (None, None) => println!("  - Merkle snapshot: not configured, skipping score diffs"),
+        }
+
+        // Payload configuration
+        let expiry_seconds = parse_config_var("merkle_expiry_seconds")?.unwrap_or(5 * 60);
+        if expiry_seconds == 0 {
+            return Err("merkle_expiry_seconds must be greater than 0".to_string());
+        }
+
+        let dry_run = config_var("dry_run") == Some("true".to_string());
+        let raw_trigger_dry_run = config_var("raw_trigger_dry_run") != Some("false".to_string());
+
+        // Block pinning configuration
+        let pinned_block = parse_config_var("pinned_block")?;
+        let finality_lag = parse_config_var("block_finality_lag")?.unwrap_or(0);
+        let trigger_time_lag_seconds = parse_config_var("trigger_time_lag_seconds")?.unwrap_or(60);
+
+        // Epoch configuration
+        let skip_if_unchanged = config_var("skip_if_unchanged") == Some("true".to_string());
+        let event_debounce_blocks = parse_config_var("event_debounce_blocks")?.unwrap_or(0);
 
         // Chain configuration
         let chain_config = get_evm_chain_config(&chain_name)
@@ -68,34 +199,424 @@ impl MerklerConfig {
             .ok_or_else(|| format!("Failed to get HTTP endpoint for {chain_name}"))?;
 
         Ok(MerklerConfig {
-            events_dir,
+            events_output,
             eas_address,
             indexer_address,
             chain_name,
             chain_id,
             http_endpoint,
             ipfs_url,
+            ipfs_gateway_url,
             ipfs_api_key,
+            ipfs_gateway_token,
+            merkle_snapshot_address,
+            previous_snapshot_cid,
+            expiry_seconds,
+            dry_run,
+            raw_trigger_dry_run,
+            pinned_block,
+            finality_lag,
+            trigger_time_lag_seconds,
+            skip_if_unchanged,
+            event_debounce_blocks,
+        })
+    }
+}
+
+/// Find the contract this workflow submits to on the given chain, via the aggregator submit config.
+fn submit_contract_address(chain_name: &str) -> Option<Address> {
+    match host::get_workflow().workflow.submit {
+        Submit::Aggregator(AggregatorSubmit { component, .. }) => component
+            .config
+            .iter()
+            .find(|(key, _)| key == chain_name)
+            .and_then(|(_, address)| Address::from_str(address).ok()),
+        _ => None,
+    }
+}
+
+/// How attestations are fetched from the chain
+pub struct FetchConfig {
+    /// Number of attestations per indexer page
+    pub batch_size: u64,
+    /// Maximum number of requests made at the same time
+    pub concurrency: usize,
+    /// Number of times a failed request is retried before the run fails
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each subsequent retry
+    pub retry_backoff_ms: u64,
+}
+
+impl FetchConfig {
+    fn load() -> Result<Self, String> {
+        let batch_size = parse_config_var("pagerank_batch_size")?.unwrap_or(100);
+        if batch_size == 0 {
+            return Err("pagerank_batch_size must be greater than 0".to_string());
+        }
+
+        let concurrency = parse_config_var("pagerank_fetch_concurrency")?.unwrap_or(4);
+        if concurrency == 0 {
+            return Err("pagerank_fetch_concurrency must be greater than 0".to_string());
+        }
+
+        Ok(Self {
+            batch_size,
+            concurrency,
+            max_retries: parse_config_var("pagerank_fetch_retries")?.unwrap_or(3),
+            retry_backoff_ms: parse_config_var("pagerank_fetch_backoff_ms")?.unwrap_or(500),
         })
     }
 }
 
+/// Where the attestations of the trust graph are read from
+#[derive(Clone, Copy, PartialEq)]
+pub enum AttestationDataSource {
+    /// Indexed attestation events of the WAVS indexer
+    Indexer,
+    /// EAS `Attested`/`Revoked` logs, for chains without an indexer
+    EasLogs,
+}
+
+impl AttestationDataSource {
+    fn load() -> Result<Self, String> {
+        match config_var("pagerank_data_source").as_deref().unwrap_or("indexer") {
+            "indexer" => Ok(Self::Indexer),
+            "eas_logs" => Ok(Self::EasLogs),
+            other => Err(format!(
+                "Invalid pagerank_data_source '{other}', expected one of: indexer, eas_logs"
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Indexer => "indexer",
+            Self::EasLogs => "eas_logs",
+        }
+    }
+}
+
+/// How EAS logs are scanned
+pub struct EasLogsConfig {
+    /// First block to scan, ideally the EAS deployment block
+    pub from_block: u64,
+    /// Maximum number of blocks per `eth_getLogs` request
+    pub chunk_size: u64,
+}
+
+impl EasLogsConfig {
+    fn load() -> Result<Self, String> {
+        let chunk_size = parse_config_var("eas_logs_chunk_size")?.unwrap_or(10_000);
+        if chunk_size == 0 {
+            return Err("eas_logs_chunk_size must be greater than 0".to_string());
+        }
+
+        Ok(Self { from_block: parse_config_var("eas_logs_from_block")?.unwrap_or(0), chunk_size })
+    }
+}
+
+/// Off-chain EIP-712 signed EAS attestations to add to the graph
+pub struct OffchainAttestationsConfig {
+    /// IPFS CID of a JSON array of signed attestations
+    pub cid: Option<String>,
+    /// Directory of JSON files, each with one signed attestation or an array of them
+    pub dir: Option<PathBuf>,
+    /// Multiplier applied to the weight of off-chain attestations before capping
+    pub weight_multiplier: f64,
+    /// Chain ID of the EIP-712 domain
+    pub chain_id: u64,
+    pub ipfs_gateway_url: String,
+    pub ipfs_gateway_token: Option<String>,
+}
+
+impl OffchainAttestationsConfig {
+    fn load(merkler_config: &MerklerConfig) -> Result<Option<Self>, String> {
+        if config_var("offchain_attestations_enabled") != Some("true".to_string()) {
+            return Ok(None);
+        }
+
+        let cid = config_var("offchain_attestations_cid").filter(|cid| !cid.trim().is_empty());
+        let dir = config_var("offchain_attestations_dir").map(PathBuf::from);
+        if cid.is_none() && dir.is_none() {
+            return Err(
+                "Off-chain attestations enabled but neither offchain_attestations_cid nor \
+                 offchain_attestations_dir configured"
+                    .to_string(),
+            );
+        }
+
+        let weight_multiplier =
+            parse_config_var("offchain_attestations_weight_multiplier")?.unwrap_or(1.0);
+        if !(weight_multiplier >= 0.0) {
+            return Err("offchain_attestations_weight_multiplier must not be negative".to_string());
+        }
+
+        let chain_id = merkler_config
+            .chain_id
+            .parse()
+            .map_err(|e| format!("Failed to parse chain ID {}: {e}", merkler_config.chain_id))?;
+
+        Ok(Some(Self {
+            cid,
+            dir,
+            weight_multiplier,
+            chain_id,
+            ipfs_gateway_url: merkler_config.ipfs_gateway_url.clone(),
+            ipfs_gateway_token: merkler_config.ipfs_gateway_token.clone(),
+        }))
+    }
+}
+
+/// Which attesters may add edges to the graph
+pub struct EligibilityConfig {
+    /// Only these attesters are eligible, if set
+    pub allowlist: Option<HashSet<Address>>,
+    /// These attesters are never eligible
+    pub denylist: HashSet<Address>,
+    /// ERC-20 or ERC-721 token attesters must hold at the pinned block
+    pub token_address: Option<Address>,
+    /// Minimum token balance for the token gate
+    pub token_min_balance: U256,
+    /// Minimum attester nonce at the pinned block
+    pub min_nonce: u64,
+    /// Minimum number of blocks since the attester first sent a transaction
+    pub min_age_blocks: u64,
+    /// Maximum number of distinct recipients per attester per epoch
+    pub max_edges_per_epoch: Option<usize>,
+    /// Length of an edge limit epoch in seconds, or 0 for a single epoch
+    pub edge_epoch_seconds: u64,
+}
+
+impl EligibilityConfig {
+    fn load() -> Result<Self, String> {
+        let config = Self {
+            allowlist: config_var("attester_allowlist")
+                .map(|list| parse_address_list("attester_allowlist", &list))
+                .transpose()?,
+            denylist: config_var("attester_denylist")
+                .map(|list| parse_address_list("attester_denylist", &list))
+                .transpose()?
+                .unwrap_or_default(),
+            token_address: config_var("attester_token_address")
+                .map(|address| {
+                    Address::from_str(&address)
+                        .map_err(|e| format!("Failed to parse attester_token_address: {e}"))
+                })
+                .transpose()?,
+            token_min_balance: parse_config_var("attester_token_min_balance")?.unwrap_or(U256::ONE),
+            min_nonce: parse_config_var("attester_min_nonce")?.unwrap_or(0),
+            min_age_blocks: parse_config_var("attester_min_age_blocks")?.unwrap_or(0),
+            max_edges_per_epoch: parse_config_var("attester_max_edges_per_epoch")?,
+            edge_epoch_seconds: parse_config_var("attester_edge_epoch_seconds")?.unwrap_or(0),
+        };
+
+        if config.is_enabled() {
+            println!("🚧 Attester eligibility filters enabled");
+        }
+
+        Ok(config)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.allowlist.is_some()
+            || !self.denylist.is_empty()
+            || self.token_address.is_some()
+            || self.min_nonce > 0
+            || self.min_age_blocks > 0
+            || self.max_edges_per_epoch.is_some()
+    }
+
+    /// Whether the filters read chain state that can change without any new attestation
+    pub fn reads_chain_state(&self) -> bool {
+        self.token_address.is_some() || self.min_nonce > 0 || self.min_age_blocks > 0
+    }
+}
+
+/// Where the trusted seeds of a run come from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedSource {
+    /// The `pagerank_trusted_seeds` config var
+    Static,
+    /// The current Safe owners, read through a SignerSyncManagerModule
+    SafeSigners(Address),
+    /// A governance-managed ITrustSeedRegistry contract
+    Registry(Address),
+    /// The accounts with the highest values in the previous snapshot
+    PreviousTopK(usize),
+}
+
+impl SeedSource {
+    fn load() -> Result<Self, String> {
+        let seed_source = config_var("pagerank_seed_source").unwrap_or_else(|| "static".into());
+        let contract = || -> Result<Address, String> {
+            let address = config_var("pagerank_seed_contract").ok_or_else(|| {
+                format!("pagerank_seed_source {} requires pagerank_seed_contract", seed_source)
+            })?;
+            Address::from_str(&address)
+                .map_err(|e| format!("Failed to parse pagerank_seed_contract: {e}"))
+        };
+
+        match seed_source.as_str() {
+            "static" => Ok(Self::Static),
+            "safe_signers" => Ok(Self::SafeSigners(contract()?)),
+            "registry" => Ok(Self::Registry(contract()?)),
+            "previous_top_k" => match parse_config_var("pagerank_seed_top_k")? {
+                Some(0) | None => {
+                    Err("pagerank_seed_source previous_top_k requires pagerank_seed_top_k > 0"
+                        .to_string())
+                }
+                Some(k) => Ok(Self::PreviousTopK(k)),
+            },
+            other => Err(format!(
+                "Invalid pagerank_seed_source '{other}', expected one of: static, safe_signers, \
+                 registry, previous_top_k"
+            )),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Static => "static",
+            Self::SafeSigners(_) => "safe_signers",
+            Self::Registry(_) => "registry",
+            Self::PreviousTopK(_) => "previous_top_k",
+        }
+    }
+}
+
+/// How the edge weight is read from the decoded attestation data
+pub struct WeightConfig {
+    /// Path of the weight field, through nested tuples and arrays (e.g. [1, 0] for "1.0")
+    pub path: Vec<usize>,
+    /// Weight of a `true` bool field. `false` weighs 0.
+    pub bool_weight: f64,
+    /// Weights of the values of an integer (enum) field, keyed by their decimal value
+    pub enum_weights: Option<HashMap<String, f64>>,
+    /// Weights of the values of a string field
+    pub string_weights: Option<HashMap<String, f64>>,
+}
+
+impl WeightConfig {
+    fn load(schema_abi: &DynSolType, max_weight: f64) -> Result<Self, String> {
+        let path = config_var("vouching_schema_abi_weight_index").ok_or_else(|| {
+            "PageRank enabled but vouching_schema_abi_weight_index not configured".to_string()
+        })?;
+        let path = path
+            .split('.')
+            .map(|index| {
+                index.trim().parse().map_err(|e| {
+                    format!("Invalid vouching_schema_abi_weight_index '{}': {}", path, e)
+                })
+            })
+            .collect::<Result<Vec<usize>, String>>()?;
+
+        let config = Self {
+            path,
+            bool_weight: parse_config_var("vouching_schema_bool_weight")?.unwrap_or(max_weight),
+            enum_weights: parse_weight_table("vouching_schema_enum_weights")?,
+            string_weights: parse_weight_table("vouching_schema_string_weights")?,
+        };
+        config.validate(schema_abi)?;
+
+        Ok(config)
+    }
+
+    /// The weight path as configured, e.g. "1.0"
+    pub fn path_string(&self) -> String {
+        self.path.iter().map(|index| index.to_string()).collect::<Vec<_>>().join(".")
+    }
+
+    /// Check that the path exists in the schema and leads to a field a weight can be read from.
+    fn validate(&self, schema_abi: &DynSolType) -> Result<(), String> {
+        let mut field = schema_abi;
+        for index in &self.path {
+            field = match field {
+                DynSolType::Tuple(fields) => fields.get(*index),
+                DynSolType::FixedArray(inner, len) if index < len => Some(inner.as_ref()),
+                // Array lengths are only known when decoding
+                DynSolType::Array(inner) => Some(inner.as_ref()),
+                _ => None,
+            }
+            .ok_or_else(|| {
+                format!("Weight path {} not found in schema {}", self.path_string(), schema_abi)
+            })?;
+        }
+
+        match field {
+            DynSolType::Uint(_) | DynSolType::Int(_) | DynSolType::Bool => Ok(()),
+            DynSolType::String if self.string_weights.is_some() => Ok(()),
+            DynSolType::String => {
+                Err("A string weight field requires vouching_schema_string_weights".to_string())
+            }
+            other => {
+                Err(format!("Weight field {} has unsupported type {}", self.path_string(), other))
+            }
+        }
+    }
+}
+
+fn parse_weight_table(key: &str) -> Result<Option<HashMap<String, f64>>, String> {
+    config_var(key)
+        .map(|table| {
+            serde_json::from_str(&table).map_err(|e| format!("Failed to parse {}: {}", key, e))
+        })
+        .transpose()
+}
+
+fn parse_address_list(key: &str, list: &str) -> Result<HashSet<Address>, String> {
+    list.split(',')
+        .map(|address| address.trim())
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            Address::from_str(address)
+                .map_err(|e| format!("Invalid address {address} in {key}: {e}"))
+        })
+        .collect()
+}
+
+fn parse_config_var<T: FromStr>(key: &str) -> Result<Option<T>, String>
+where
+    T::Err: std::fmt::Display,
+{
+    config_var(key)
+        .map(|value| value.parse().map_err(|e| format!("Failed to parse {key}: {e}")))
+        .transpose()
+}
+
 /// Trust Aware PageRank-based source configuration
 pub struct PageRankSourceConfig {
     /// Schema UID for attestations
     pub schema_uid: String,
     /// Schema ABI type (e.g. "string,uint256")
     pub schema_abi: DynSolType,
-    /// Index of the weight in the schema ABI (e.g. 1 for the uint256 in "string,uint256")
-    pub schema_abi_weight_index: usize,
+    /// Where the weight is read from in the schema data
+    pub weight: WeightConfig,
     /// Total pool to distribute
     pub total_pool: U256,
     /// PageRank configuration (including trust settings)
     pub pagerank_config: PageRankConfig,
+    /// Indexer fetch configuration
+    pub fetch: FetchConfig,
+    /// Where attestations are read from
+    pub data_source: AttestationDataSource,
+    /// EAS log scanning configuration, used by the eas_logs data source and cross-checks
+    pub eas_logs: EasLogsConfig,
+    /// Also read attestations from the other data source and report differences in the metadata
+    pub cross_check: bool,
+    /// Off-chain attestations added alongside the on-chain ones, if enabled
+    pub offchain: Option<OffchainAttestationsConfig>,
+    /// Attester eligibility filters
+    pub eligibility: EligibilityConfig,
+    /// How attestations from an attester to itself are handled
+    pub self_edge_policy: SelfEdgePolicy,
+    /// How several attestations from the same attester to the same recipient are combined
+    pub duplicate_policy: DuplicatePolicy,
+    /// Where the trusted seeds are loaded from at run time
+    pub seed_source: SeedSource,
 }
 
 impl PageRankSourceConfig {
-    pub fn load() -> Result<Option<Self>, String> {
+    pub fn load(merkler_config: &MerklerConfig) -> Result<Option<Self>, String> {
         let enabled = config_var("pagerank_enabled") == Some("true".to_string());
 
         if !enabled {
@@ -116,12 +637,6 @@ impl PageRankSourceConfig {
         let parsed_schema_abi = DynSolType::parse(&vouching_schema_abi)
             .map_err(|e| format!("Failed to parse schema: {e}"))?;
 
-        let vouching_schema_abi_weight_index = config_var("vouching_schema_abi_weight_index")
-            .and_then(|s| s.parse().ok())
-            .ok_or_else(|| {
-                "PageRank enabled but vouching_schema_abi_weight_index not configured".to_string()
-            })?;
-
         let points_pool = U256::from_str(&pagerank_pool_str)
             .map_err(|err| format!("Failed to parse pagerank_points_pool: {err}"))?;
 
@@ -145,13 +660,15 @@ impl PageRankSourceConfig {
             trust_config: TrustConfig::default(),
         };
 
-        // Configure trusted seeds if provided
+        // Configure trusted seeds if provided. With an on-chain seed source these are the fallback
+        // used when the source has no seeds.
+        let seed_source = SeedSource::load()?;
+        let mut parsed_seeds = Vec::new();
         println!("🔍 Checking for pagerank_trusted_seeds configuration...");
         if let Some(trusted_seeds_str) = config_var("pagerank_trusted_seeds") {
             println!("🔍 Found pagerank_trusted_seeds: '{}'", trusted_seeds_str);
             let seed_addresses: Vec<&str> =
                 trusted_seeds_str.split(',').map(|s| s.trim()).collect();
-            let mut parsed_seeds = Vec::new();
 
             for seed_str in seed_addresses {
                 if seed_str.is_empty() {
@@ -166,58 +683,83 @@ impl PageRankSourceConfig {
             }
 
             println!("🔍 Parsed {} trusted seed addresses", parsed_seeds.len());
-            if !parsed_seeds.is_empty() {
-                let mut trust_config = TrustConfig::new(parsed_seeds.clone());
+        } else {
+            println!("ℹ️  No pagerank_trusted_seeds configured");
+        }
 
-                // Configure trust parameters
-                if let Some(multiplier_str) = config_var("pagerank_trust_multiplier") {
-                    if let Ok(multiplier) = multiplier_str.parse::<f64>() {
-                        trust_config = trust_config.with_trust_multiplier(multiplier);
-                    }
+        if !parsed_seeds.is_empty() || seed_source != SeedSource::Static {
+            let mut trust_config = TrustConfig::new(parsed_seeds.clone());
+
+            // Configure trust parameters
+            if let Some(multiplier_str) = config_var("pagerank_trust_multiplier") {
+                if let Ok(multiplier) = multiplier_str.parse::<f64>() {
+                    trust_config = trust_config.with_trust_multiplier(multiplier);
                 }
+            }
 
-                if let Some(boost_str) = config_var("pagerank_trust_share") {
-                    if let Ok(boost) = boost_str.parse::<f64>() {
-                        trust_config = trust_config.with_trust_share(boost);
-                    }
+            if let Some(boost_str) = config_var("pagerank_trust_share") {
+                if let Ok(boost) = boost_str.parse::<f64>() {
+                    trust_config = trust_config.with_trust_share(boost);
                 }
+            }
 
-                if let Some(decay_str) = config_var("pagerank_trust_decay") {
-                    if let Ok(decay) = decay_str.parse::<f64>() {
-                        trust_config = trust_config.with_trust_decay(decay);
-                    }
+            if let Some(decay_str) = config_var("pagerank_trust_decay") {
+                if let Ok(decay) = decay_str.parse::<f64>() {
+                    trust_config = trust_config.with_trust_decay(decay);
                 }
+            }
 
-                pagerank_config = pagerank_config.with_trust_config(trust_config);
-                println!(
+            pagerank_config = pagerank_config.with_trust_config(trust_config);
+            match &seed_source {
+                SeedSource::Static => println!(
                     "✅ Configured Trust Aware PageRank with {} trusted seeds",
                     parsed_seeds.len()
-                );
-                println!(
-                    "   Trust multiplier: {:.1}x",
-                    pagerank_config.trust_config.trust_multiplier
-                );
-                println!(
-                    "   Trust share: {:.1}%",
-                    pagerank_config.trust_config.trust_share * 100.0
-                );
-                println!(
-                    "   Trust decay: {:.1}%",
-                    pagerank_config.trust_config.trust_decay * 100.0
-                );
-            } else {
-                println!("⚠️  No valid trusted seed addresses found, using standard PageRank");
+                ),
+                seed_source => println!(
+                    "✅ Configured Trust Aware PageRank with trusted seeds from {} ({} fallback \
+                     seeds)",
+                    seed_source.name(),
+                    parsed_seeds.len()
+                ),
             }
+            println!("   Trust multiplier: {:.1}x", pagerank_config.trust_config.trust_multiplier);
+            println!("   Trust share: {:.1}%", pagerank_config.trust_config.trust_share * 100.0);
+            println!("   Trust decay: {:.1}%", pagerank_config.trust_config.trust_decay * 100.0);
         } else {
-            println!("ℹ️  No pagerank_trusted_seeds configured, using standard PageRank");
+            println!("⚠️  No valid trusted seed addresses found, using standard PageRank");
         }
 
+        let weight = WeightConfig::load(&parsed_schema_abi, pagerank_config.max_weight)?;
+
+        let self_edge_policy =
+            parse_config_var("pagerank_self_edge_policy")?.unwrap_or(SelfEdgePolicy::Ignore);
+        // Summed weights are capped at the max edge weight unless configured otherwise
+        let duplicate_cap =
+            parse_config_var("pagerank_duplicate_cap")?.unwrap_or(pagerank_config.max_weight);
+        let duplicate_policy = config_var("pagerank_duplicate_policy")
+            .map(|policy| DuplicatePolicy::parse(&policy, Some(duplicate_cap)))
+            .transpose()?
+            .unwrap_or(DuplicatePolicy::LatestWins);
+        println!(
+            "🔗 Self-edge policy: {}, duplicate policy: {}",
+            self_edge_policy, duplicate_policy
+        );
+
         Ok(Some(Self {
             schema_uid: vouching_schema_uid.clone(),
             schema_abi: parsed_schema_abi,
-            schema_abi_weight_index: vouching_schema_abi_weight_index,
+            weight,
             total_pool: points_pool,
             pagerank_config,
+            fetch: FetchConfig::load()?,
+            data_source: AttestationDataSource::load()?,
+            eas_logs: EasLogsConfig::load()?,
+            cross_check: config_var("pagerank_cross_check") == Some("true".to_string()),
+            offchain: OffchainAttestationsConfig::load(merkler_config)?,
+            eligibility: EligibilityConfig::load()?,
+            self_edge_policy,
+            duplicate_policy,
+            seed_source,
         }))
     }
 
@@ -0,0 +1,104 @@
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use wavs_wasi_utils::evm::alloy_primitives::{Address, U256};
+
+use crate::{bindings::WasmResponse, history::SnapshotDiff};
+
+/// Number of top accounts listed in the summary
+const SUMMARY_TOP_ACCOUNTS: usize = 10;
+
+/// Build the response of a dry run: the merkle JSON that would have been uploaded to IPFS and a
+/// human-readable summary, instead of the on-chain payload. `events_cid_unknown` tells that the
+/// account events weren't uploaded, so the metadata and root differ from those of a real run.
+pub fn dry_run_response(
+    ipfs_data: &impl Serialize,
+    root: &str,
+    values: &HashMap<Address, U256>,
+    total_value: U256,
+    diff: Option<&SnapshotDiff>,
+    events_cid_unknown: bool,
+) -> Result<WasmResponse, String> {
+    let mut summary = summarize(root, values, total_value, diff);
+    if events_cid_unknown {
+        summary.push_str(
+            "\n⚠️ The account events CID is unknown without uploading them to IPFS, so the metadata \
+             and root differ from a real run",
+        );
+    }
+    println!("{}", summary);
+
+    let payload = serde_json::to_vec_pretty(&json!({
+        "dry_run": true,
+        "root_matches_real_run": !events_cid_unknown,
+        "summary": summary,
+        "ipfs_data": ipfs_data,
+    }))
+    .map_err(|e| format!("Failed to serialize dry run output: {}", e))?;
+
+    Ok(WasmResponse { payload, ordering: None })
+}
+
+fn summarize(
+    root: &str,
+    values: &HashMap<Address, U256>,
+    total_value: U256,
+    diff: Option<&SnapshotDiff>,
+) -> String {
+    let mut lines = vec![
+        "🧪 Dry run: nothing was uploaded to IPFS or submitted on chain".to_string(),
+        format!("Merkle root: {}", root),
+        format!("Accounts: {}", values.len()),
+        format!("Total value: {}", total_value),
+    ];
+
+    // Account deltas by account, if there is a previous snapshot to compare against
+    let mut deltas = HashMap::new();
+    match diff {
+        Some(diff) => {
+            lines.push(format!(
+                "Since {}: {} new, {} dropped, {} changed, {} unchanged",
+                diff.previous_root,
+                diff.num_new,
+                diff.num_dropped,
+                diff.num_changed,
+                diff.num_unchanged
+            ));
+            for delta in diff.new_accounts.iter().chain(&diff.changed_accounts) {
+                deltas.insert(delta.account.clone(), delta);
+            }
+        }
+        None => lines.push("No previous snapshot to compare against".to_string()),
+    }
+
+    let mut sorted: Vec<_> = values.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+
+    lines.push(format!("Top {} accounts:", SUMMARY_TOP_ACCOUNTS.min(sorted.len())));
+    for (i, (account, value)) in sorted.into_iter().take(SUMMARY_TOP_ACCOUNTS).enumerate() {
+        // Share of the total in basis points
+        let share = if total_value.is_zero() {
+            0
+        } else {
+            (*value * U256::from(10_000) / total_value).to::<u64>()
+        };
+        let change = match (diff, deltas.get(&account.to_string())) {
+            (None, _) => String::new(),
+            (Some(_), None) => " (unchanged)".to_string(),
+            (Some(_), Some(delta)) if delta.previous_rank.is_none() => " (new)".to_string(),
+            (Some(_), Some(delta)) if delta.delta.starts_with('-') => format!(" ({})", delta.delta),
+            (Some(_), Some(delta)) => format!(" (+{})", delta.delta),
+        };
+        lines.push(format!(
+            "  {}. {}: {} ({}.{:02}%){}",
+            i + 1,
+            account,
+            value,
+            share / 100,
+            share % 100,
+            change
+        ));
+    }
+
+    lines.join("\n")
+}
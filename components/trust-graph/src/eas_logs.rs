@@ -0,0 +1,270 @@
+use alloy_provider::Provider;
+use alloy_rpc_types::{Filter, Log, TransactionInput, TransactionRequest};
+use alloy_sol_types::{sol, SolCall, SolEvent};
+use anyhow::Result;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use wavs_indexer_api::solidity::IndexedEvent;
+use wavs_indexer_api::IndexedAttestation;
+use wavs_merkle_sources::sources::SourceContext;
+use wavs_wasi_utils::evm::alloy_primitives::{FixedBytes, TxKind, U256};
+
+use crate::{
+    block::PinnedBlock,
+    config::{EasLogsConfig, FetchConfig},
+    retry::{transport_error, with_retry},
+};
+
+/// Reconstruct the attestations of a schema from EAS `Attested`/`Revoked` logs up to the pinned
+/// block, in the order they were made.
+///
+/// The logs are scanned in chunks of `eth_getLogs` requests, then every attestation is read with
+/// `getAttestation` at the pinned block to get its data.
+pub async fn get_attestations_from_logs(
+    ctx: &SourceContext,
+    schema: FixedBytes<32>,
+    block: &PinnedBlock,
+    eas_logs: &EasLogsConfig,
+    fetch: &FetchConfig,
+) -> Result<Vec<IndexedAttestation>> {
+    let chunks: Vec<(u64, u64)> = (eas_logs.from_block..=block.number)
+        .step_by(eas_logs.chunk_size as usize)
+        .map(|start| (start, (start + eas_logs.chunk_size - 1).min(block.number)))
+        .collect();
+
+    println!(
+        "📜 Scanning EAS logs from block {} to {} in {} chunks",
+        eas_logs.from_block,
+        block.number,
+        chunks.len()
+    );
+
+    let logs: Vec<Vec<Log>> = stream::iter(chunks)
+        .map(|(from, to)| {
+            with_retry(fetch, format!("EAS logs for blocks {} to {}", from, to), move || {
+                get_logs(ctx, schema, from, to)
+            })
+        })
+        .buffered(fetch.concurrency)
+        .try_collect()
+        .await?;
+
+    // Attested uids in log order, with the block they were attested in
+    let mut attested = Vec::new();
+    let mut revoked = HashSet::new();
+    for log in logs.iter().flatten() {
+        let topic0 = log.topic0();
+        if topic0 == Some(&IEAS::Attested::SIGNATURE_HASH) {
+            let event = IEAS::Attested::decode_log_data(log.data())?;
+            attested.push((event.uid, log.block_number.unwrap_or_default()));
+        } else if topic0 == Some(&IEAS::Revoked::SIGNATURE_HASH) {
+            let event = IEAS::Revoked::decode_log_data(log.data())?;
+            revoked.insert(event.uid);
+        }
+    }
+
+    println!("📜 Found {} attestations and {} revocations", attested.len(), revoked.len());
+
+    stream::iter(attested)
+        .map(|(uid, block_number)| {
+            let revoked = &revoked;
+            async move {
+                let attestation = with_retry(fetch, format!("EAS attestation {}", uid), || {
+                    get_attestation(ctx, uid, block)
+                })
+                .await?;
+                let deleted = revoked.contains(&uid) || attestation.revocationTime != 0;
+
+                Ok(indexed_attestation(ctx, attestation, block_number, deleted))
+            }
+        })
+        .buffered(fetch.concurrency)
+        .try_collect()
+        .await
+}
+
+async fn get_logs(
+    ctx: &SourceContext,
+    schema: FixedBytes<32>,
+    from: u64,
+    to: u64,
+) -> Result<Vec<Log>> {
+    let filter = Filter::new()
+        .address(ctx.eas_address)
+        .event_signature(vec![IEAS::Attested::SIGNATURE_HASH, IEAS::Revoked::SIGNATURE_HASH])
+        .topic3(schema)
+        .from_block(from)
+        .to_block(to);
+
+    ctx.provider
+        .get_logs(&filter)
+        .await
+        .map_err(|e| transport_error(format!("Failed to get logs: {}", e)))
+}
+
+async fn get_attestation(
+    ctx: &SourceContext,
+    uid: FixedBytes<32>,
+    block: &PinnedBlock,
+) -> Result<AttestationStruct> {
+    let call = IEAS::getAttestationCall { uid };
+    let tx = TransactionRequest {
+        to: Some(TxKind::Call(ctx.eas_address)),
+        input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
+        ..Default::default()
+    };
+
+    let result = ctx
+        .provider
+        .call(tx)
+        .block(block.block_id())
+        .await
+        .map_err(|e| transport_error(format!("Failed to get attestation: {}", e)))?;
+
+    IEAS::getAttestationCall::abi_decode_returns(&result)
+        .map_err(|e| anyhow::anyhow!("Failed to decode attestation: {}", e))
+}
+
+/// Convert an EAS attestation to the shape the indexer returns, so both data sources build the
+/// graph the same way.
+fn indexed_attestation(
+    ctx: &SourceContext,
+    attestation: AttestationStruct,
+    block_number: u64,
+    deleted: bool,
+) -> IndexedAttestation {
+    let event = IndexedEvent {
+        eventId: FixedBytes::ZERO,
+        chainId: ctx.chain_id.clone(),
+        relevantContract: ctx.eas_address,
+        blockNumber: U256::from(block_number),
+        timestamp: attestation.time as u128,
+        eventType: "attestation".to_string(),
+        tags: vec![format!("uid:{}", attestation.uid), format!("schema:{}", attestation.schema)],
+        relevantAddresses: vec![attestation.attester, attestation.recipient],
+        data: attestation.data,
+        metadata: Vec::new().into(),
+        deleted,
+    };
+
+    IndexedAttestation {
+        uid: attestation.uid,
+        schema_uid: attestation.schema,
+        attester: attestation.attester,
+        recipient: attestation.recipient,
+        event,
+    }
+}
+
+/// Differences between the attestations of two data sources
+#[derive(Debug, Clone, Serialize)]
+pub struct CrossCheckReport {
+    pub data_source: String,
+    pub reference_source: String,
+    pub num_attestations: usize,
+    pub num_reference_attestations: usize,
+    /// Uids only found in the reference source
+    pub missing: Vec<String>,
+    /// Uids only found in the data source
+    pub unexpected: Vec<String>,
+    /// Uids revoked in one source but not the other
+    pub revocation_mismatches: Vec<String>,
+}
+
+impl CrossCheckReport {
+    pub fn compare(
+        data_source: &str,
+        attestations: &[IndexedAttestation],
+        reference_source: &str,
+        reference: &[IndexedAttestation],
+    ) -> Self {
+        let deleted: HashMap<_, _> =
+            attestations.iter().map(|a| (a.uid, a.event.deleted)).collect();
+        let reference_deleted: HashMap<_, _> =
+            reference.iter().map(|a| (a.uid, a.event.deleted)).collect();
+
+        let mut missing: Vec<String> = reference_deleted
+            .keys()
+            .filter(|uid| !deleted.contains_key(*uid))
+            .map(|uid| uid.to_string())
+            .collect();
+        let mut unexpected: Vec<String> = deleted
+            .keys()
+            .filter(|uid| !reference_deleted.contains_key(*uid))
+            .map(|uid| uid.to_string())
+            .collect();
+        let mut revocation_mismatches: Vec<String> = deleted
+            .iter()
+            .filter(|(uid, deleted)| reference_deleted.get(*uid).is_some_and(|d| d != *deleted))
+            .map(|(uid, _)| uid.to_string())
+            .collect();
+        missing.sort();
+        unexpected.sort();
+        revocation_mismatches.sort();
+
+        let report = Self {
+            data_source: data_source.to_string(),
+            reference_source: reference_source.to_string(),
+            num_attestations: attestations.len(),
+            num_reference_attestations: reference.len(),
+            missing,
+            unexpected,
+            revocation_mismatches,
+        };
+
+        if report.is_consistent() {
+            println!("✅ Cross-check: {} matches {}", data_source, reference_source);
+        } else {
+            println!("⚠️  Cross-check: {} differs from {}:", data_source, reference_source);
+            println!("   - Missing: {}", report.missing.len());
+            println!("   - Unexpected: {}", report.unexpected.len());
+            println!("   - Revocation mismatches: {}", report.revocation_mismatches.len());
+        }
+
+        report
+    }
+
+    pub fn is_consistent(&self) -> bool {
+        self.missing.is_empty()
+            && self.unexpected.is_empty()
+            && self.revocation_mismatches.is_empty()
+    }
+}
+
+sol! {
+    struct AttestationStruct {
+        bytes32 uid;
+        bytes32 schema;
+        uint64 time;
+        uint64 expirationTime;
+        uint64 revocationTime;
+        bytes32 refUID;
+        address recipient;
+        address attester;
+        bool revocable;
+        bytes data;
+    }
+
+    interface IEAS {
+        event Attested(address indexed recipient, address indexed attester, bytes32 uid, bytes32 indexed schemaUID);
+        event Revoked(address indexed recipient, address indexed attester, bytes32 uid, bytes32 indexed schemaUID);
+
+        function getAttestation(bytes32 uid) external view returns (AttestationStruct memory);
+        function version() external view returns (string memory);
+        function getSchemaRegistry() external view returns (address);
+        function isAttestationValid(bytes32 uid) external view returns (bool);
+        function getRevokeOffchain(address revoker, bytes32 data) external view returns (uint64);
+    }
+
+    struct SchemaRecord {
+        bytes32 uid;
+        address resolver;
+        bool revocable;
+        string schema;
+    }
+
+    interface ISchemaRegistry {
+        function getSchema(bytes32 uid) external view returns (SchemaRecord memory);
+    }
+}
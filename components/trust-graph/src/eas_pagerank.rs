@@ -1,15 +1,23 @@
-use alloy_sol_types::sol;
 use anyhow::Result;
 use async_trait::async_trait;
-use futures::lock::Mutex;
-use pagerank::PageRankGraphComputer;
+use futures::{lock::Mutex, stream, StreamExt, TryStreamExt};
+use pagerank::{DuplicatePolicy, GraphManifest, PageRankGraphComputer};
 use std::collections::HashMap;
 use wavs_indexer_api::solidity::IndexedEvent;
 use wavs_indexer_api::IndexedAttestation;
 use wavs_merkle_sources::sources::{Source, SourceEvent};
 use wavs_wasi_utils::evm::alloy_primitives::{hex, Address, FixedBytes, U256};
 
-use crate::config::PageRankSourceConfig;
+use crate::{
+    block::PinnedBlock,
+    config::{AttestationDataSource, PageRankSourceConfig},
+    eas_logs::{get_attestations_from_logs, CrossCheckReport},
+    eligibility::{filter_attestations, EligibilityReport},
+    manifest::{AttestationCounts, ComponentInfo, ReproducibilityManifest, MANIFEST_VERSION},
+    offchain::{load_offchain_attestations, OffchainReport, OFFCHAIN_ATTESTATION_EVENT_TYPE},
+    retry::{transport_error, with_retry},
+    weight::{extract_weight, WeightFailure, WeightReport},
+};
 
 pub use wavs_merkle_sources::sources;
 
@@ -17,12 +25,28 @@ pub use wavs_merkle_sources::sources;
 pub struct EasPageRankSource {
     /// PageRank points configuration
     pub config: PageRankSourceConfig,
+    /// Block all indexer reads are pinned to
+    pub block: PinnedBlock,
     /// Cached points to avoid recalculation
     cached_points: Mutex<Option<HashMap<Address, U256>>>,
+    /// Result of the data source cross-check, if enabled
+    cross_check: Mutex<Option<CrossCheckReport>>,
+    /// Summary of the off-chain attestations, if enabled
+    offchain: Mutex<Option<OffchainReport>>,
+    /// Summary of the attester eligibility filters, if enabled
+    eligibility: Mutex<Option<EligibilityReport>>,
+    /// Summary of the weights read from the attestations
+    weights: Mutex<Option<WeightReport>>,
+    /// Number of rejected self-edges and merged duplicate edges of the last graph
+    edge_stats: Mutex<Option<(usize, usize)>>,
+    /// What happened to the attestations of the last graph
+    attestation_counts: Mutex<Option<AttestationCounts>>,
+    /// Final edge list and parameters of the last calculation, to reproduce it
+    graph_manifest: Mutex<Option<GraphManifest>>,
 }
 
 impl EasPageRankSource {
-    pub fn new(config: PageRankSourceConfig) -> Result<Self> {
+    pub fn new(config: PageRankSourceConfig, block: PinnedBlock) -> Result<Self> {
         if config.total_pool.is_zero() {
             return Err(anyhow::anyhow!("PageRank points pool cannot be zero"));
         }
@@ -42,7 +66,18 @@ impl EasPageRankSource {
             println!("📊 Standard PageRank (no trust seeds configured)");
         }
 
-        Ok(Self { config, cached_points: Mutex::new(None) })
+        Ok(Self {
+            config,
+            block,
+            cached_points: Mutex::new(None),
+            cross_check: Mutex::new(None),
+            offchain: Mutex::new(None),
+            eligibility: Mutex::new(None),
+            weights: Mutex::new(None),
+            edge_stats: Mutex::new(None),
+            attestation_counts: Mutex::new(None),
+            graph_manifest: Mutex::new(None),
+        })
     }
 
     fn parse_schema_uid(&self, schema_uid: &str) -> Result<FixedBytes<32>> {
@@ -63,7 +98,9 @@ impl EasPageRankSource {
         let schema = self.parse_schema_uid(schema_uid)?;
         let count = ctx
             .indexer_querier
-            .get_attestation_count_by_schema(schema)
+            .getEventCountByTypeAndTag(ATTESTATION_EVENT_TYPE.to_string(), schema_tag(schema))
+            .block(self.block.block_id())
+            .call()
             .await
             .map_err(|e| anyhow::anyhow!("Failed to get schema attestation count: {}", e))?;
         Ok(count.to::<u64>())
@@ -75,34 +112,142 @@ impl EasPageRankSource {
         schema_uid: &str,
         start: u64,
         length: u64,
-    ) -> Result<Vec<IndexedAttestation>> {
+    ) -> Result<(Vec<IndexedAttestation>, usize)> {
         let schema = self.parse_schema_uid(schema_uid)?;
-        let attestations = ctx
+        let events = ctx
             .indexer_querier
-            .get_indexed_attestations_by_schema(schema, start, length, false)
+            .getEventsByTypeAndTag(
+                ATTESTATION_EVENT_TYPE.to_string(),
+                schema_tag(schema),
+                U256::from(start),
+                U256::from(length),
+                false,
+            )
+            .block(self.block.block_id())
+            .call()
             .await
-            .map_err(|e| anyhow::anyhow!("Failed to get indexed schema attestations: {}", e))?;
-        Ok(attestations)
+            .map_err(|e| {
+                transport_error(format!("Failed to get indexed schema attestations: {}", e))
+            })?;
+
+        // A short page means the indexer returned fewer attestations than it counted
+        if events.len() as u64 != length {
+            return Err(anyhow::anyhow!(
+                "Expected {} attestations from index {}, got {}",
+                length,
+                start,
+                events.len()
+            ));
+        }
+
+        Ok(indexed_attestations(schema, events))
+    }
+
+    /// Fetch all attestations of the schema at the pinned block in concurrent pages, in order,
+    /// with the number of malformed events skipped.
+    async fn get_all_indexed_attestations(
+        &self,
+        ctx: &sources::SourceContext,
+        schema_uid: &str,
+    ) -> Result<(Vec<IndexedAttestation>, usize)> {
+        let fetch = &self.config.fetch;
+        let total_attestations = self.get_total_schema_attestations(ctx, schema_uid).await?;
+
+        println!(
+            "📊 Fetching {} total attestations at block {} in batches of {} ({} concurrent)",
+            total_attestations, self.block.number, fetch.batch_size, fetch.concurrency
+        );
+
+        let pages = (0..total_attestations)
+            .step_by(fetch.batch_size as usize)
+            .map(|start| (start, std::cmp::min(fetch.batch_size, total_attestations - start)));
+        let batches: Vec<(Vec<IndexedAttestation>, usize)> = stream::iter(pages)
+            .map(|(start, length)| async move {
+                println!("🔄 Fetching attestation batch: {} to {}", start, start + length - 1);
+                with_retry(
+                    fetch,
+                    format!("Attestation batch {} to {}", start, start + length - 1),
+                    || self.get_indexed_attestations(ctx, schema_uid, start, length),
+                )
+                .await
+            })
+            .buffered(fetch.concurrency)
+            .try_collect()
+            .await?;
+
+        let malformed = batches.iter().map(|(_, malformed)| malformed).sum();
+        Ok((batches.into_iter().flat_map(|(attestations, _)| attestations).collect(), malformed))
+    }
+
+    /// Get the attestations of the schema from the given data source, with the number of
+    /// malformed events skipped.
+    async fn get_attestations_from(
+        &self,
+        ctx: &sources::SourceContext,
+        schema_uid: &str,
+        data_source: AttestationDataSource,
+    ) -> Result<(Vec<IndexedAttestation>, usize)> {
+        match data_source {
+            AttestationDataSource::Indexer => {
+                self.get_all_indexed_attestations(ctx, schema_uid).await
+            }
+            AttestationDataSource::EasLogs => {
+                let attestations = get_attestations_from_logs(
+                    ctx,
+                    self.parse_schema_uid(schema_uid)?,
+                    &self.block,
+                    &self.config.eas_logs,
+                    &self.config.fetch,
+                )
+                .await?;
+                Ok((attestations, 0))
+            }
+        }
     }
 
-    // async fn get_attestation_details(
-    //     &self,
-    //     ctx: &sources::SourceContext,
-    //     uid: FixedBytes<32>,
-    // ) -> Result<(Address, Address, Vec<u8>)> {
-    //     let call = IEAS::getAttestationCall { uid };
-    //     let tx: alloy_rpc_types::TransactionRequest = alloy_rpc_types::eth::TransactionRequest {
-    //         to: Some(TxKind::Call(ctx.eas_address)),
-    //         input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
-    //         ..Default::default()
-    //     };
-
-    //     let result = ctx.provider.call(tx).await?;
-
-    //     let decoded = IEAS::getAttestationCall::abi_decode_returns(&result)
-    //         .map_err(|e| anyhow::anyhow!("Failed to decode attestation: {}", e))?;
-    //     Ok((decoded.attester, decoded.recipient, decoded.data.to_vec()))
-    // }
+    /// Get the attestations of the schema from the configured data source, cross-checking them
+    /// against the other data source if enabled, plus the verified off-chain attestations. Also
+    /// returns the number of malformed events skipped from the configured data source.
+    async fn get_attestations(
+        &self,
+        ctx: &sources::SourceContext,
+        schema_uid: &str,
+    ) -> Result<(Vec<IndexedAttestation>, usize)> {
+        let data_source = self.config.data_source;
+        let (mut attestations, malformed) =
+            self.get_attestations_from(ctx, schema_uid, data_source).await?;
+
+        if self.config.cross_check {
+            let reference_source = match data_source {
+                AttestationDataSource::Indexer => AttestationDataSource::EasLogs,
+                AttestationDataSource::EasLogs => AttestationDataSource::Indexer,
+            };
+            println!("🔎 Cross-checking attestations against {}", reference_source.name());
+            let (reference, _) =
+                self.get_attestations_from(ctx, schema_uid, reference_source).await?;
+
+            *self.cross_check.lock().await = Some(CrossCheckReport::compare(
+                data_source.name(),
+                &attestations,
+                reference_source.name(),
+                &reference,
+            ));
+        }
+
+        if let Some(offchain_config) = &self.config.offchain {
+            let (offchain_attestations, report) = load_offchain_attestations(
+                ctx,
+                offchain_config,
+                self.parse_schema_uid(schema_uid)?,
+                &self.block,
+            )
+            .await?;
+            attestations.extend(offchain_attestations);
+            *self.offchain.lock().await = Some(report);
+        }
+
+        Ok((attestations, malformed))
+    }
 
     /// Build PageRank graph computer from EAS data
     async fn build_pagerank_graph_computer(
@@ -112,112 +257,120 @@ impl EasPageRankSource {
         let schema_uid: &String = &self.config.schema_uid;
         println!("🏗️  Building attestation graph for schema: {}", schema_uid);
 
-        let total_attestations = self.get_total_schema_attestations(ctx, schema_uid).await?;
-        println!("📊 Processing {} total attestations", total_attestations);
+        let (mut attestations, malformed) = self.get_attestations(ctx, schema_uid).await?;
+        println!("📊 Processing {} total attestations", attestations.len());
+
+        let mut graph = PageRankGraphComputer::new()
+            .with_self_edge_policy(self.config.self_edge_policy)
+            .with_duplicate_policy(self.config.duplicate_policy);
+        // Malformed events were read but never became attestations, so they count as failed
+        let mut counts = AttestationCounts {
+            processed: attestations.len() + malformed,
+            failed: malformed,
+            ..Default::default()
+        };
+
+        if attestations.is_empty() {
+            *self.attestation_counts.lock().await = Some(counts);
+            return Ok(graph);
+        }
 
-        if total_attestations == 0 {
-            return Ok(PageRankGraphComputer::new());
+        // Sort ascending so the newest attestations are processed last and override existing edges.
+        // The sort is stable, so attestations with the same timestamp keep their index order.
+        attestations.sort_by_key(|a| a.event.timestamp);
+
+        if self.config.eligibility.is_enabled() {
+            let (eligible, report) = filter_attestations(
+                ctx,
+                &self.config.eligibility,
+                &self.block,
+                &self.config.fetch,
+                attestations,
+            )
+            .await?;
+            attestations = eligible;
+            counts.ineligible = report.filtered.total();
+            *self.eligibility.lock().await = Some(report);
         }
 
-        let mut graph = PageRankGraphComputer::new().with_allow_duplicates(false);
         let mut edge_count = 0;
         let mut unique_attesters = std::collections::HashSet::new();
         let mut unique_recipients = std::collections::HashSet::new();
-        let batch_size = 100u64;
-        let mut start = 0u64;
-
-        while start < total_attestations {
-            let length = std::cmp::min(batch_size, total_attestations - start);
-            println!("🔄 Processing attestation batch: {} to {}", start, start + length - 1);
-
-            let mut attestations =
-                self.get_indexed_attestations(ctx, schema_uid, start, length).await?;
-            // Sort ascending so the newest attestations are processed last and override existing edges.
-            attestations.sort_by_key(|a| a.event.timestamp);
-
-            for IndexedAttestation {
-                uid,
-                schema_uid,
-                attester,
-                recipient,
-                event: IndexedEvent { deleted, data, .. },
-            } in attestations
-            {
-                // Debug attestation data
-                println!("🔍 Attestation UID: {:?}", uid);
-                println!("   Schema UID: {:?}", schema_uid);
-                println!("   Attester: {}", attester);
-                println!("   Recipient: {}", recipient);
-                println!("   Data length: {}", data.len());
-
-                if deleted {
-                    println!("❌  Attestation was revoked, skipping...");
-                    continue;
-                }
-
-                if data.len() > 0 {
-                    println!("   Data (hex): 0x{}", hex::encode(&data[..data.len().min(64)]));
-                }
+        let mut weight_report =
+            WeightReport { weight_path: self.config.weight.path_string(), ..Default::default() };
+
+        for IndexedAttestation {
+            uid,
+            schema_uid,
+            attester,
+            recipient,
+            event: IndexedEvent { deleted, data, eventType: event_type, .. },
+        } in attestations
+        {
+            // Debug attestation data
+            println!("🔍 Attestation UID: {:?}", uid);
+            println!("   Schema UID: {:?}", schema_uid);
+            println!("   Attester: {}", attester);
+            println!("   Recipient: {}", recipient);
+            println!("   Data length: {}", data.len());
+
+            if deleted {
+                println!("❌  Attestation was revoked, skipping...");
+                counts.revoked += 1;
+                continue;
+            }
 
-                let weight: Option<f64> = match self.config.schema_abi.abi_decode_params(&data) {
-                    Err(e) => {
-                        println!("⚠️  Failed to decode attestation data: {e}");
-                        None
-                    }
-                    Ok(decoded_data) => match decoded_data.as_tuple() {
-                        None => {
-                            println!("⚠️  Attestation data is not a tuple");
-                            None
-                        }
-                        Some(decoded_data) => match decoded_data
-                            .get(self.config.schema_abi_weight_index)
-                            .map(|v| v.as_uint())
-                        {
-                            None => {
-                                println!(
-                                    "⚠️  Index {} not found in attestation data",
-                                    self.config.schema_abi_weight_index
-                                );
-                                None
-                            }
-                            Some(None) => {
-                                println!(
-                                    "⚠️  Attestation data field at index {} is not a uint",
-                                    self.config.schema_abi_weight_index
-                                );
-                                None
-                            }
-                            Some(Some((value, _))) => match value.try_into() {
-                                Err(e) => {
-                                    println!("⚠️  Failed to convert attestation data field at index {} to f64: {e}", self.config.schema_abi_weight_index);
-                                    None
-                                }
-                                Ok(value) => Some(value),
-                            },
-                        },
-                    },
-                };
-
-                // Cap weight to min and max values
-                let weight = weight
-                    .unwrap_or_default()
-                    .max(self.config.pagerank_config.min_weight)
-                    .min(self.config.pagerank_config.max_weight);
-
-                // Override existing edge if it exists
-                graph.add_edge(attester, recipient, weight);
-                edge_count += 1;
-                unique_attesters.insert(attester);
-                unique_recipients.insert(recipient);
-
-                // Log all edges for debugging
-                println!(
-                    "  Edge #{}: {} → {} (weight: {})",
-                    edge_count, attester, recipient, weight
-                );
+            if data.len() > 0 {
+                println!("   Data (hex): 0x{}", hex::encode(&data[..data.len().min(64)]));
             }
 
-            start += length;
+            let weight = match extract_weight(&self.config.weight, &self.config.schema_abi, &data) {
+                Ok(weight) => {
+                    weight_report.num_decoded += 1;
+                    weight
+                }
+                Err(reason) => {
+                    println!("⚠️  Failed to read weight, skipping attestation: {}", reason);
+                    // With `LatestWins` the attestation supersedes the older ones of the pair, so
+                    // their edge must not outlive it. A newer readable attestation adds it back.
+                    // The other policies combine every attestation, so theirs are kept.
+                    let latest_wins = self.config.duplicate_policy == DuplicatePolicy::LatestWins;
+                    if latest_wins && graph.remove_edge(attester, recipient) > 0 {
+                        println!(
+                            "   Removed the edge {} → {} of older attestations",
+                            attester, recipient
+                        );
+                        weight_report.num_removed_edges += 1;
+                    }
+                    weight_report.failures.push(WeightFailure {
+                        uid: uid.to_string(),
+                        attester: attester.to_string(),
+                        reason,
+                    });
+                    continue;
+                }
+            };
+
+            // Weigh off-chain attestations separately from on-chain ones
+            let is_offchain = event_type == OFFCHAIN_ATTESTATION_EVENT_TYPE;
+            let weight = match &self.config.offchain {
+                Some(offchain) if is_offchain => weight * offchain.weight_multiplier,
+                _ => weight,
+            };
+
+            // Cap weight to min and max values
+            let weight = weight
+                .max(self.config.pagerank_config.min_weight)
+                .min(self.config.pagerank_config.max_weight);
+
+            // Combine with an existing edge as configured by the duplicate policy
+            graph.add_edge(attester, recipient, weight);
+            edge_count += 1;
+            unique_attesters.insert(attester);
+            unique_recipients.insert(recipient);
+
+            // Log all edges for debugging
+            println!("  Edge #{}: {} → {} (weight: {})", edge_count, attester, recipient, weight);
         }
 
         graph.sort();
@@ -227,6 +380,17 @@ impl EasPageRankSource {
         println!("   - Total edges: {}", edge_count);
         println!("   - Unique attesters: {}", unique_attesters.len());
         println!("   - Unique recipients: {}", unique_recipients.len());
+        println!("   - Rejected self-edges: {}", graph.rejected_self_edges());
+        println!("   - Merged duplicate edges: {}", graph.merged_duplicates());
+        println!("   - Weight read failures: {}", weight_report.failures.len());
+        println!("   - Malformed indexed attestations: {}", malformed);
+        weight_report.num_failed = weight_report.failures.len();
+        counts.failed += weight_report.num_failed;
+        counts.edges = edge_count;
+        *self.attestation_counts.lock().await = Some(counts);
+        *self.weights.lock().await = Some(weight_report);
+        *self.edge_stats.lock().await =
+            Some((graph.rejected_self_edges(), graph.merged_duplicates()));
 
         // Log graph structure for debugging
         println!("\n📊 Graph structure:");
@@ -253,6 +417,11 @@ impl EasPageRankSource {
         }
 
         let pagerank = self.build_pagerank_graph_computer(ctx).await?;
+        *self.graph_manifest.lock().await = Some(GraphManifest::new(
+            &pagerank,
+            &self.config.pagerank_config,
+            self.config.total_pool,
+        ));
         let scores: HashMap<Address, f64> = pagerank
             .calculate_pagerank(&self.config.pagerank_config)
             .into_iter()
@@ -357,14 +526,33 @@ impl Source for EasPageRankSource {
     }
 
     async fn get_metadata(&self, ctx: &sources::SourceContext) -> Result<serde_json::Value> {
+        let edge_stats = *self.edge_stats.lock().await;
+        let attestation_counts = self.attestation_counts.lock().await.clone();
+        let manifest =
+            self.graph_manifest.lock().await.clone().map(|graph| ReproducibilityManifest {
+                manifest_version: MANIFEST_VERSION,
+                component: ComponentInfo::CURRENT,
+                block: self.block,
+                schema_uid: self.config.schema_uid.to_lowercase(),
+                data_source: self.config.data_source.name(),
+                attestations: attestation_counts.clone().unwrap_or_default(),
+                graph,
+            });
+        // Sorted so the metadata is the same for every operator
+        let mut trusted_seeds: Vec<_> =
+            self.config.pagerank_config.trust_config.trusted_seeds.iter().collect();
+        trusted_seeds.sort();
+        let trusted_seeds: Vec<String> =
+            trusted_seeds.iter().map(|addr| addr.to_string()).collect();
+
         let trust_info = if self.config.has_trust_enabled() {
             serde_json::json!({
                 "enabled": true,
-                "trusted_seeds": self.config.pagerank_config.trust_config.trusted_seeds.iter()
-                    .map(|addr| addr.to_string())
-                    .collect::<Vec<_>>(),
+                "seed_source": self.config.seed_source.name(),
+                "trusted_seeds": trusted_seeds,
                 "trust_multiplier": self.config.pagerank_config.trust_config.trust_multiplier,
                 "trust_share": self.config.pagerank_config.trust_config.trust_share,
+                "trust_decay": self.config.pagerank_config.trust_config.trust_decay,
             })
         } else {
             serde_json::json!({
@@ -382,8 +570,16 @@ impl Source for EasPageRankSource {
                 "pagerank_attestations"
             },
             "schema_uid": self.config.schema_uid,
+            "block_number": self.block.number,
+            "component": ComponentInfo::CURRENT,
+            "attestations": attestation_counts,
+            "data_source": self.config.data_source.name(),
+            "cross_check": self.cross_check.lock().await.clone(),
+            "offchain_attestations": self.offchain.lock().await.clone(),
+            "eligibility": self.eligibility.lock().await.clone(),
             "schema_abi": self.config.schema_abi.to_string(),
-            "schema_abi_weight_index": self.config.schema_abi_weight_index,
+            "schema_abi_weight_index": self.config.weight.path_string(),
+            "weights": self.weights.lock().await.clone(),
             "total_pool": self.config.total_pool.to_string(),
             "pagerank_config": {
                 "damping_factor": self.config.pagerank_config.damping_factor,
@@ -392,27 +588,192 @@ impl Source for EasPageRankSource {
                 "min_weight": self.config.pagerank_config.min_weight,
                 "max_weight": self.config.pagerank_config.max_weight,
             },
-            "trust_config": trust_info
+            "trust_config": trust_info,
+            "edge_policy": {
+                "self_edge_policy": self.config.self_edge_policy.to_string(),
+                "duplicate_policy": self.config.duplicate_policy.to_string(),
+                "rejected_self_edges": edge_stats.map(|(rejected, _)| rejected),
+                "merged_duplicates": edge_stats.map(|(_, merged)| merged),
+            },
+            "fetch": {
+                "batch_size": self.config.fetch.batch_size,
+                "concurrency": self.config.fetch.concurrency,
+                "max_retries": self.config.fetch.max_retries,
+            },
+            "manifest": manifest,
         }))
     }
 }
 
-// Reuse the sol! interfaces from the original EAS source
-sol! {
-    struct AttestationStruct {
-        bytes32 uid;
-        bytes32 schema;
-        uint64 time;
-        uint64 expirationTime;
-        uint64 revocationTime;
-        bytes32 refUID;
-        address recipient;
-        address attester;
-        bool revocable;
-        bytes data;
+/// Event type of attestations in the WAVS indexer
+pub const ATTESTATION_EVENT_TYPE: &str = "attestation";
+
+pub fn schema_tag(schema: FixedBytes<32>) -> String {
+    format!("schema:{}", schema)
+}
+
+/// Convert an indexed attestation event, as written by the wavs-indexer attestation transformer:
+/// tagged with its `uid:`, `schema:`, `attester:` and `recipient:`, with the attester and
+/// recipient as its relevant addresses. Events that don't match that layout are rejected rather
+/// than read as edges between the wrong accounts.
+fn indexed_attestation(schema: FixedBytes<32>, event: IndexedEvent) -> Result<IndexedAttestation> {
+    if event.eventType != ATTESTATION_EVENT_TYPE {
+        return Err(anyhow::anyhow!(
+            "Indexed event {} is a {} event, not an attestation",
+            event.eventId,
+            event.eventType
+        ));
+    }
+
+    let uid = single_tag(&event, "uid")?
+        .parse()
+        .map_err(|e| anyhow::anyhow!("Invalid uid tag on {}: {}", event.eventId, e))?;
+    if single_tag(&event, "schema")? != schema.to_string() {
+        return Err(anyhow::anyhow!("Indexed attestation {} has another schema", event.eventId));
+    }
+
+    let (attester, recipient) = match event.relevantAddresses.as_slice() {
+        [attester, recipient] => (*attester, *recipient),
+        _ => {
+            return Err(anyhow::anyhow!(
+                "Indexed attestation {} must have its attester and recipient as relevant addresses",
+                event.eventId
+            ))
+        }
+    };
+    for (key, address) in [("attester", attester), ("recipient", recipient)] {
+        if single_tag(&event, key)? != address.to_string() {
+            return Err(anyhow::anyhow!(
+                "Indexed attestation {} has a {} tag that differs from its relevant address",
+                event.eventId,
+                key
+            ));
+        }
+    }
+
+    Ok(IndexedAttestation { uid, schema_uid: schema, attester, recipient, event })
+}
+
+/// Convert a page of indexed attestation events, skipping the malformed ones: they would fail the
+/// same way on every run, so they must not block the whole graph. Returns the attestations and
+/// the number of events skipped.
+fn indexed_attestations(
+    schema: FixedBytes<32>,
+    events: Vec<IndexedEvent>,
+) -> (Vec<IndexedAttestation>, usize) {
+    let mut malformed = 0;
+    let attestations = events
+        .into_iter()
+        .filter_map(|event| match indexed_attestation(schema, event) {
+            Ok(attestation) => Some(attestation),
+            Err(e) => {
+                println!("⚠️  Skipping malformed indexed attestation: {}", e);
+                malformed += 1;
+                None
+            }
+        })
+        .collect();
+    (attestations, malformed)
+}
+
+/// Get the value of the only `<key>:` tag of an event.
+fn single_tag<'a>(event: &'a IndexedEvent, key: &str) -> Result<&'a str> {
+    let prefix = format!("{}:", key);
+    let mut values = event
+        .tags
+        .iter()
+        .filter_map(|tag| tag.strip_prefix(&prefix))
+        // Composite tags like `schema:<uid>/attester:<address>` aren't values of the key
+        .filter(|value| !value.contains('/'));
+    match (values.next(), values.next()) {
+        (Some(value), None) => Ok(value),
+        (None, _) => {
+            Err(anyhow::anyhow!("Indexed attestation {} has no {} tag", event.eventId, key))
+        }
+        (Some(_), Some(_)) => {
+            Err(anyhow::anyhow!("Indexed attestation {} has several {} tags", event.eventId, key))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wavs_wasi_utils::evm::alloy_primitives::Bytes;
+
+    fn attestation_event(attester: Address, recipient: Address) -> IndexedEvent {
+        let schema = FixedBytes::repeat_byte(1);
+        IndexedEvent {
+            eventId: FixedBytes::repeat_byte(9),
+            chainId: "31337".to_string(),
+            relevantContract: Address::ZERO,
+            blockNumber: U256::from(1),
+            timestamp: 0,
+            eventType: ATTESTATION_EVENT_TYPE.to_string(),
+            tags: vec![
+                format!("uid:{}", FixedBytes::<32>::repeat_byte(2)),
+                schema_tag(schema),
+                format!("attester:{}", attester),
+                format!("recipient:{}", recipient),
+                format!("schema:{}/attester:{}", schema, attester),
+                format!("schema:{}/recipient:{}", schema, recipient),
+            ],
+            relevantAddresses: vec![attester, recipient],
+            data: Bytes::new(),
+            metadata: Bytes::new(),
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn test_indexed_attestation() {
+        let (attester, recipient) = (Address::repeat_byte(3), Address::repeat_byte(4));
+        let attestation =
+            indexed_attestation(FixedBytes::repeat_byte(1), attestation_event(attester, recipient))
+                .unwrap();
+
+        assert_eq!(attestation.uid, FixedBytes::<32>::repeat_byte(2));
+        assert_eq!(attestation.attester, attester);
+        assert_eq!(attestation.recipient, recipient);
+    }
+
+    #[test]
+    fn test_indexed_attestation_rejects_other_layouts() {
+        let (attester, recipient) = (Address::repeat_byte(3), Address::repeat_byte(4));
+        let schema = FixedBytes::repeat_byte(1);
+        let invalid = |update: fn(&mut IndexedEvent)| {
+            let mut event = attestation_event(attester, recipient);
+            update(&mut event);
+            indexed_attestation(schema, event).is_err()
+        };
+
+        assert!(invalid(|event| event.eventType = "transfer".to_string()));
+        assert!(invalid(|event| event.tags.retain(|tag| !tag.starts_with("uid:"))));
+        assert!(invalid(|event| event.tags.push(format!("uid:{}", FixedBytes::<32>::ZERO))));
+        assert!(invalid(|event| event.relevantAddresses.reverse()));
+        assert!(invalid(|event| event.relevantAddresses.truncate(1)));
+        assert!(invalid(|event| event.relevantAddresses.push(Address::ZERO)));
+        assert!(
+            indexed_attestation(FixedBytes::ZERO, attestation_event(attester, recipient)).is_err()
+        );
     }
 
-    interface IEAS {
-        function getAttestation(bytes32 uid) external view returns (AttestationStruct memory);
+    #[test]
+    fn test_indexed_attestations_skip_malformed_events() {
+        let (attester, recipient) = (Address::repeat_byte(3), Address::repeat_byte(4));
+        let mut malformed = attestation_event(attester, recipient);
+        malformed.relevantAddresses.reverse();
+        let events = vec![
+            attestation_event(attester, recipient),
+            malformed,
+            attestation_event(recipient, attester),
+        ];
+
+        let (attestations, skipped) = indexed_attestations(FixedBytes::repeat_byte(1), events);
+
+        assert_eq!(skipped, 1);
+        assert_eq!(attestations.len(), 2);
+        assert_eq!(attestations[0].attester, attester);
+        assert_eq!(attestations[1].attester, recipient);
     }
 }
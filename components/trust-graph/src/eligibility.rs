@@ -0,0 +1,250 @@
+use alloy_provider::Provider;
+use alloy_rpc_types::{BlockId, TransactionInput, TransactionRequest};
+use alloy_sol_types::{sol, SolCall};
+use anyhow::Result;
+use futures::{stream, StreamExt, TryStreamExt};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use wavs_indexer_api::IndexedAttestation;
+use wavs_merkle_sources::sources::SourceContext;
+use wavs_wasi_utils::evm::alloy_primitives::{Address, TxKind, U256};
+
+use crate::{
+    block::PinnedBlock,
+    config::{EligibilityConfig, FetchConfig},
+    retry::{transport_error, with_retry},
+};
+
+/// Why an attester may not add edges to the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Ineligibility {
+    Denylist,
+    Allowlist,
+    TokenGate,
+    Nonce,
+    AccountAge,
+}
+
+/// Number of attestations dropped by each filter
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FilteredCounts {
+    pub denylist: usize,
+    pub allowlist: usize,
+    pub token_gate: usize,
+    pub nonce: usize,
+    pub account_age: usize,
+    pub edge_limit: usize,
+}
+
+impl FilteredCounts {
+    fn add(&mut self, reason: Ineligibility) {
+        match reason {
+            Ineligibility::Denylist => self.denylist += 1,
+            Ineligibility::Allowlist => self.allowlist += 1,
+            Ineligibility::TokenGate => self.token_gate += 1,
+            Ineligibility::Nonce => self.nonce += 1,
+            Ineligibility::AccountAge => self.account_age += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.denylist
+            + self.allowlist
+            + self.token_gate
+            + self.nonce
+            + self.account_age
+            + self.edge_limit
+    }
+}
+
+/// Summary of the attester eligibility filters of a run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct EligibilityReport {
+    pub num_attesters: usize,
+    pub num_ineligible_attesters: usize,
+    pub num_attestations: usize,
+    pub num_accepted: usize,
+    pub filtered: FilteredCounts,
+    pub ineligible_attesters: Vec<String>,
+}
+
+/// Drop the attestations of ineligible attesters, and those exceeding the per-epoch edge limit.
+///
+/// The attestations must be sorted by timestamp, so the edge limit keeps the earliest edges of an
+/// epoch. Revoked attestations are kept, since they don't add edges. All chain state is read at
+/// the pinned block.
+pub async fn filter_attestations(
+    ctx: &SourceContext,
+    config: &EligibilityConfig,
+    block: &PinnedBlock,
+    fetch: &FetchConfig,
+    attestations: Vec<IndexedAttestation>,
+) -> Result<(Vec<IndexedAttestation>, EligibilityReport)> {
+    let mut attesters: Vec<Address> = attestations
+        .iter()
+        .filter(|a| !a.event.deleted)
+        .map(|a| a.attester)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    attesters.sort();
+
+    println!("🚧 Checking eligibility of {} attesters", attesters.len());
+
+    let checks: Vec<(Address, Option<Ineligibility>)> = stream::iter(attesters)
+        .map(|attester| async move {
+            let reason = check_attester(ctx, config, block, fetch, attester).await?;
+            Ok::<_, anyhow::Error>((attester, reason))
+        })
+        .buffered(fetch.concurrency)
+        .try_collect()
+        .await?;
+
+    let mut report = EligibilityReport {
+        num_attesters: checks.len(),
+        num_attestations: attestations.len(),
+        ..Default::default()
+    };
+    let ineligible: HashMap<Address, Ineligibility> =
+        checks.into_iter().filter_map(|(attester, reason)| Some((attester, reason?))).collect();
+    report.num_ineligible_attesters = ineligible.len();
+    report.ineligible_attesters = ineligible.keys().map(|attester| attester.to_string()).collect();
+    report.ineligible_attesters.sort();
+
+    // Distinct recipients of each attester per epoch
+    let mut epoch_edges: HashMap<(Address, u64), HashSet<Address>> = HashMap::new();
+    let mut accepted = Vec::with_capacity(attestations.len());
+    for attestation in attestations {
+        if attestation.event.deleted {
+            accepted.push(attestation);
+            continue;
+        }
+
+        if let Some(reason) = ineligible.get(&attestation.attester) {
+            report.filtered.add(*reason);
+            continue;
+        }
+
+        if let Some(max_edges) = config.max_edges_per_epoch {
+            let epoch = match config.edge_epoch_seconds {
+                0 => 0,
+                seconds => (attestation.event.timestamp / seconds as u128) as u64,
+            };
+            let recipients = epoch_edges.entry((attestation.attester, epoch)).or_default();
+            // Attesting to a recipient again only overrides the existing edge
+            if !recipients.contains(&attestation.recipient) {
+                if recipients.len() >= max_edges {
+                    report.filtered.edge_limit += 1;
+                    continue;
+                }
+                recipients.insert(attestation.recipient);
+            }
+        }
+
+        accepted.push(attestation);
+    }
+    report.num_accepted = accepted.len();
+
+    println!(
+        "✅ Accepted {} of {} attestations ({} filtered, {} ineligible attesters)",
+        report.num_accepted,
+        report.num_attestations,
+        report.filtered.total(),
+        report.num_ineligible_attesters
+    );
+
+    Ok((accepted, report))
+}
+
+/// Check an attester against the filters, cheapest first. Returns the first filter it fails.
+async fn check_attester(
+    ctx: &SourceContext,
+    config: &EligibilityConfig,
+    block: &PinnedBlock,
+    fetch: &FetchConfig,
+    attester: Address,
+) -> Result<Option<Ineligibility>> {
+    if config.denylist.contains(&attester) {
+        return Ok(Some(Ineligibility::Denylist));
+    }
+    if config.allowlist.as_ref().is_some_and(|allowlist| !allowlist.contains(&attester)) {
+        return Ok(Some(Ineligibility::Allowlist));
+    }
+
+    if let Some(token) = config.token_address {
+        let balance = with_retry(fetch, format!("Token balance of {}", attester), || {
+            get_token_balance(ctx, token, attester, block)
+        })
+        .await?;
+        if balance < config.token_min_balance {
+            return Ok(Some(Ineligibility::TokenGate));
+        }
+    }
+
+    if config.min_nonce > 0 {
+        let nonce = with_retry(fetch, format!("Nonce of {}", attester), || {
+            get_nonce(ctx, attester, block.block_id())
+        })
+        .await?;
+        if nonce < config.min_nonce {
+            return Ok(Some(Ineligibility::Nonce));
+        }
+    }
+
+    // An account is old enough if it had sent a transaction (or, for a contract, was deployed)
+    // `min_age_blocks` before the pinned block
+    if config.min_age_blocks > 0 {
+        let Some(age_block) = block.number.checked_sub(config.min_age_blocks) else {
+            return Ok(Some(Ineligibility::AccountAge));
+        };
+        let nonce =
+            with_retry(fetch, format!("Nonce of {} at block {}", attester, age_block), || {
+                get_nonce(ctx, attester, BlockId::number(age_block))
+            })
+            .await?;
+        if nonce == 0 {
+            return Ok(Some(Ineligibility::AccountAge));
+        }
+    }
+
+    Ok(None)
+}
+
+async fn get_nonce(ctx: &SourceContext, account: Address, block: BlockId) -> Result<u64> {
+    ctx.provider
+        .get_transaction_count(account)
+        .block_id(block)
+        .await
+        .map_err(|e| transport_error(format!("Failed to get nonce: {}", e)))
+}
+
+async fn get_token_balance(
+    ctx: &SourceContext,
+    token: Address,
+    account: Address,
+    block: &PinnedBlock,
+) -> Result<U256> {
+    let call = IBalanceOf::balanceOfCall { account };
+    let tx = TransactionRequest {
+        to: Some(TxKind::Call(token)),
+        input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
+        ..Default::default()
+    };
+
+    let result = ctx
+        .provider
+        .call(tx)
+        .block(block.block_id())
+        .await
+        .map_err(|e| transport_error(format!("Failed to get token balance: {}", e)))?;
+
+    IBalanceOf::balanceOfCall::abi_decode_returns(&result)
+        .map_err(|e| anyhow::anyhow!("Failed to decode token balance: {}", e))
+}
+
+sol! {
+    /// `balanceOf` as shared by ERC-20 and ERC-721
+    interface IBalanceOf {
+        function balanceOf(address account) external view returns (uint256);
+    }
+}
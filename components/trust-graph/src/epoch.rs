@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use wavs_merkle_sources::sources::SourceContext;
+use wavs_wasi_utils::evm::alloy_primitives::{FixedBytes, Keccak256, U256};
+
+use crate::{
+    bindings::{host, wavs::operator::input::TriggerData, TriggerAction},
+    block::PinnedBlock,
+    config::MerklerConfig,
+    eas_pagerank::{schema_tag, ATTESTATION_EVENT_TYPE},
+    history::PreviousSnapshot,
+    manifest::ComponentInfo,
+    trigger::decode_event_indexed_trigger,
+};
+
+/// What kind of trigger started the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TriggerKind {
+    /// An `EventIndexed` event of the WAVS indexer
+    EventIndexed,
+    /// Any other EVM contract event
+    EvmEvent,
+    CosmosEvent,
+    BlockInterval,
+    Cron,
+    /// A local `wavs exec` run
+    Raw,
+}
+
+impl TriggerKind {
+    pub fn of(action: &TriggerAction) -> Self {
+        match &action.data {
+            TriggerData::EvmContractEvent(_) => match decode_event_indexed_trigger(action) {
+                Some(_) => Self::EventIndexed,
+                None => Self::EvmEvent,
+            },
+            TriggerData::CosmosContractEvent(_) => Self::CosmosEvent,
+            TriggerData::BlockInterval(_) => Self::BlockInterval,
+            TriggerData::Cron(_) => Self::Cron,
+            TriggerData::Raw(_) => Self::Raw,
+        }
+    }
+}
+
+/// Summary of everything a run reads, recorded in the merkle metadata under `inputs` so the next
+/// run can tell whether anything changed. Only runs reading nothing but the WAVS indexer, the
+/// trusted seeds and the config are fingerprinted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputFingerprint {
+    pub schema_uid: String,
+    /// Number of attestations of the schema in the indexer at the pinned block
+    pub attestation_count: u64,
+    /// Digest of the ids of the schema's attestation events and whether they are deleted, so
+    /// revocations change the fingerprint too
+    pub events_digest: String,
+    /// Trusted seeds of the run, which can change without any new attestations
+    pub trusted_seeds: Vec<String>,
+    /// Digest of the component build and its config vars, which hold the PageRank, trust and
+    /// eligibility parameters
+    pub config_digest: String,
+}
+
+impl InputFingerprint {
+    /// Fingerprint the inputs of a run, reading the schema's attestation events in pages of
+    /// `batch_size`.
+    pub async fn load(
+        ctx: &SourceContext,
+        schema_uid: &str,
+        trusted_seeds: Vec<String>,
+        block: &PinnedBlock,
+        batch_size: u64,
+    ) -> Result<Self, String> {
+        let schema = FixedBytes::<32>::from_str(schema_uid)
+            .map_err(|e| format!("Invalid schema UID {}: {}", schema_uid, e))?;
+
+        let attestation_count = ctx
+            .indexer_querier
+            .getEventCountByTypeAndTag(ATTESTATION_EVENT_TYPE.to_string(), schema_tag(schema))
+            .block(block.block_id())
+            .call()
+            .await
+            .map_err(|e| format!("Failed to get schema attestation count: {}", e))?
+            .to::<u64>();
+
+        let mut events_digest = Keccak256::new();
+        for start in (0..attestation_count).step_by(batch_size.max(1) as usize) {
+            let length = batch_size.min(attestation_count - start);
+            let events = ctx
+                .indexer_querier
+                .getEventsByTypeAndTag(
+                    ATTESTATION_EVENT_TYPE.to_string(),
+                    schema_tag(schema),
+                    U256::from(start),
+                    U256::from(length),
+                    false,
+                )
+                .block(block.block_id())
+                .call()
+                .await
+                .map_err(|e| format!("Failed to get schema attestations: {}", e))?;
+            if events.len() as u64 != length {
+                return Err(format!(
+                    "Expected {} attestations from index {}, got {}",
+                    length,
+                    start,
+                    events.len()
+                ));
+            }
+
+            for event in events {
+                events_digest.update(event.eventId);
+                events_digest.update([event.deleted as u8]);
+            }
+        }
+
+        Ok(Self {
+            schema_uid: schema_uid.to_lowercase(),
+            attestation_count,
+            events_digest: events_digest.finalize().to_string(),
+            trusted_seeds,
+            config_digest: config_digest(),
+        })
+    }
+}
+
+/// Digest of the component build and its config vars, sorted by key.
+fn config_digest() -> String {
+    let mut config = host::get_workflow().workflow.component.config;
+    config.sort();
+
+    let mut digest = Keccak256::new();
+    digest.update(ComponentInfo::CURRENT.build_hash);
+    for (key, value) in config {
+        // Lengths keep `a=bc` and `ab=c` apart
+        for part in [key, value] {
+            digest.update((part.len() as u64).to_be_bytes());
+            digest.update(part);
+        }
+    }
+
+    digest.finalize().to_string()
+}
+
+/// Decide whether the run can be skipped, returning the reason if so. Dry runs are never skipped.
+/// The fingerprint is None when the inputs can't be fingerprinted, e.g. off-chain attestations or
+/// balance filters, or when fingerprinting them failed.
+///
+/// A run is skipped when its inputs match those of the previous snapshot, or when it was started
+/// by an `EventIndexed` trigger within `event_debounce_blocks` of the previous root. Debounced
+/// changes are picked up by the next trigger after the window, so pairing event triggers with a
+/// periodic (Cron or BlockInterval) trigger guarantees the last event of a burst is published.
+pub fn skip_reason(
+    config: &MerklerConfig,
+    trigger: TriggerKind,
+    dry_run: bool,
+    block: &PinnedBlock,
+    fingerprint: Option<&InputFingerprint>,
+    previous_snapshot: Option<&PreviousSnapshot>,
+) -> Option<String> {
+    if dry_run {
+        return None;
+    }
+    let previous_snapshot = previous_snapshot?;
+
+    if let (true, Some(fingerprint)) = (config.skip_if_unchanged, fingerprint) {
+        let previous_inputs = previous_snapshot
+            .metadata
+            .get("inputs")
+            .and_then(|inputs| serde_json::from_value::<InputFingerprint>(inputs.clone()).ok());
+        if previous_inputs.as_ref() == Some(fingerprint) {
+            return Some(format!(
+                "inputs unchanged since root {} ({} attestations)",
+                previous_snapshot.root, fingerprint.attestation_count
+            ));
+        }
+    }
+
+    if trigger == TriggerKind::EventIndexed && config.event_debounce_blocks > 0 {
+        if let Some(published_block) = previous_snapshot.published_block {
+            let next_block = published_block.saturating_add(config.event_debounce_blocks);
+            if block.number < next_block {
+                return Some(format!(
+                    "previous root was published at block {}, debouncing events until block {}",
+                    published_block, next_block
+                ));
+            }
+        }
+    }
+
+    None
+}
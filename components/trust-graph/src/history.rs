@@ -0,0 +1,329 @@
+use alloy_network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types::{TransactionInput, TransactionRequest};
+use alloy_sol_types::SolCall;
+use serde::Serialize;
+use std::collections::HashMap;
+use wavs_merkler::MerkleTreeIpfsData;
+use wavs_wasi_utils::evm::{
+    alloy_primitives::{Address, TxKind, U256},
+    new_evm_provider,
+};
+
+use crate::{
+    block::PinnedBlock,
+    config::MerklerConfig,
+    ipfs,
+    solidity::{IMerkleSnapshot, MerkleState},
+};
+
+/// The snapshot that was published before the current run
+pub struct PreviousSnapshot {
+    pub root: String,
+    pub cid: String,
+    pub values: HashMap<Address, U256>,
+    /// Metadata the snapshot was published with
+    pub metadata: serde_json::Value,
+    /// Block the root was published at, if read from the MerkleSnapshot contract
+    pub published_block: Option<u64>,
+}
+
+impl PreviousSnapshot {
+    /// Load the previous snapshot, either from the configured CID or from the latest state of the
+    /// MerkleSnapshot contract at the pinned block. Returns None if there is no previous snapshot
+    /// to compare against.
+    pub async fn load(config: &MerklerConfig, block: &PinnedBlock) -> Result<Option<Self>, String> {
+        let (cid, published_block) =
+            match (&config.previous_snapshot_cid, config.merkle_snapshot_address) {
+                (Some(cid), _) => (cid.clone(), None),
+                (None, None) => return Ok(None),
+                (None, Some(address)) => {
+                    match get_latest_state(&config.http_endpoint, address, block).await {
+                        Ok(state) if !state.ipfsHashCid.is_empty() => {
+                            (state.ipfsHashCid, Some(state.blockNumber.saturating_to()))
+                        }
+                        Ok(_) => {
+                            println!(
+                                "ℹ️  Latest merkle state has no IPFS CID, skipping score diffs"
+                            );
+                            return Ok(None);
+                        }
+                        Err(e) => {
+                            println!(
+                                "ℹ️  No previous merkle state found ({}), skipping score diffs",
+                                e
+                            );
+                            return Ok(None);
+                        }
+                    }
+                }
+            };
+
+        // The CID is read from the contract on every run, so an unavailable or invalid snapshot
+        // only skips the diff instead of failing every run after it
+        println!("📜 Loading previous snapshot from IPFS CID: {}", cid);
+        let data: MerkleTreeIpfsData = match ipfs::download_json(
+            &cid,
+            &config.ipfs_gateway_url,
+            config.ipfs_gateway_token.as_deref(),
+        )
+        .await
+        {
+            Ok(data) => data,
+            Err(e) => {
+                println!(
+                    "⚠️  Failed to download previous snapshot {}, skipping score diffs: {}",
+                    cid, e
+                );
+                return Ok(None);
+            }
+        };
+
+        let values = match data
+            .tree
+            .iter()
+            .map(|entry| Ok((entry.parsed_account()?, entry.parsed_value()?)))
+            .collect::<Result<HashMap<_, _>, String>>()
+        {
+            Ok(values) => values,
+            Err(e) => {
+                println!("⚠️  Invalid previous snapshot {}, skipping score diffs: {}", cid, e);
+                return Ok(None);
+            }
+        };
+
+        println!("✅ Loaded previous snapshot {} with {} accounts", data.root, values.len());
+
+        Ok(Some(Self { root: data.root, cid, values, metadata: data.metadata, published_block }))
+    }
+}
+
+/// Query the latest merkle state published to a MerkleSnapshot contract as of the pinned block.
+async fn get_latest_state(
+    http_endpoint: &str,
+    address: Address,
+    block: &PinnedBlock,
+) -> Result<MerkleState, String> {
+    let provider = new_evm_provider::<Ethereum>(http_endpoint.to_string());
+
+    let call = IMerkleSnapshot::getLatestStateCall {};
+    let tx = TransactionRequest {
+        to: Some(TxKind::Call(address)),
+        input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
+        ..Default::default()
+    };
+
+    let result = provider
+        .call(tx)
+        .block(block.block_id())
+        .await
+        .map_err(|e| format!("Failed to call getLatestState: {}", e))?;
+
+    IMerkleSnapshot::getLatestStateCall::abi_decode_returns(&result)
+        .map_err(|e| format!("Failed to decode getLatestState result: {}", e))
+}
+
+/// Change of a single account between two snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountDelta {
+    pub account: String,
+    pub previous_value: String,
+    pub value: String,
+    /// Signed difference between the current and previous value
+    pub delta: String,
+    /// 1-based rank in the previous snapshot, if the account was present
+    pub previous_rank: Option<usize>,
+    /// 1-based rank in the current snapshot, if the account is present
+    pub rank: Option<usize>,
+    /// Number of places moved up (positive) or down (negative), if present in both
+    pub rank_change: Option<i64>,
+}
+
+/// Per-account differences between the previous and current snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiff {
+    pub previous_root: String,
+    pub previous_cid: String,
+    pub num_new: usize,
+    pub num_dropped: usize,
+    pub num_changed: usize,
+    pub num_unchanged: usize,
+    pub new_accounts: Vec<AccountDelta>,
+    pub dropped_accounts: Vec<AccountDelta>,
+    pub changed_accounts: Vec<AccountDelta>,
+}
+
+impl SnapshotDiff {
+    /// Compare the current account values against the previous snapshot.
+    pub fn compute(previous: &PreviousSnapshot, current: &HashMap<Address, U256>) -> Self {
+        let previous_ranks = ranks(&previous.values);
+        let current_ranks = ranks(current);
+
+        let mut new_accounts = Vec::new();
+        let mut dropped_accounts = Vec::new();
+        let mut changed_accounts = Vec::new();
+        let mut num_unchanged = 0;
+
+        for (account, value) in current {
+            let rank = current_ranks.get(account).copied();
+            match previous.values.get(account) {
+                None => new_accounts.push(delta(account, U256::ZERO, *value, None, rank)),
+                Some(previous_value) => {
+                    let previous_rank = previous_ranks.get(account).copied();
+                    if previous_value == value && previous_rank == rank {
+                        num_unchanged += 1;
+                    } else {
+                        changed_accounts.push(delta(
+                            account,
+                            *previous_value,
+                            *value,
+                            previous_rank,
+                            rank,
+                        ));
+                    }
+                }
+            }
+        }
+
+        for (account, previous_value) in &previous.values {
+            if !current.contains_key(account) {
+                let previous_rank = previous_ranks.get(account).copied();
+                dropped_accounts.push(delta(
+                    account,
+                    *previous_value,
+                    U256::ZERO,
+                    previous_rank,
+                    None,
+                ));
+            }
+        }
+
+        // Sort by rank for deterministic output
+        new_accounts.sort_by_key(|d| d.rank);
+        changed_accounts.sort_by_key(|d| d.rank);
+        dropped_accounts.sort_by_key(|d| d.previous_rank);
+
+        println!("📈 Score changes since previous snapshot {}:", previous.root);
+        println!("   - New accounts: {}", new_accounts.len());
+        println!("   - Dropped accounts: {}", dropped_accounts.len());
+        println!("   - Changed accounts: {}", changed_accounts.len());
+        println!("   - Unchanged accounts: {}", num_unchanged);
+
+        Self {
+            previous_root: previous.root.clone(),
+            previous_cid: previous.cid.clone(),
+            num_new: new_accounts.len(),
+            num_dropped: dropped_accounts.len(),
+            num_changed: changed_accounts.len(),
+            num_unchanged,
+            new_accounts,
+            dropped_accounts,
+            changed_accounts,
+        }
+    }
+}
+
+/// Rank accounts by value descending, breaking ties by address so ranks are deterministic.
+fn ranks(values: &HashMap<Address, U256>) -> HashMap<Address, usize> {
+    let mut sorted: Vec<_> = values.iter().collect();
+    sorted.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    sorted.into_iter().enumerate().map(|(i, (account, _))| (*account, i + 1)).collect()
+}
+
+fn delta(
+    account: &Address,
+    previous_value: U256,
+    value: U256,
+    previous_rank: Option<usize>,
+    rank: Option<usize>,
+) -> AccountDelta {
+    let delta = if value >= previous_value {
+        (value - previous_value).to_string()
+    } else {
+        format!("-{}", previous_value - value)
+    };
+    let rank_change = match (previous_rank, rank) {
+        (Some(previous_rank), Some(rank)) => Some(previous_rank as i64 - rank as i64),
+        _ => None,
+    };
+
+    AccountDelta {
+        account: account.to_string(),
+        previous_value: previous_value.to_string(),
+        value: value.to_string(),
+        delta,
+        previous_rank,
+        rank,
+        rank_change,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn account(byte: u8) -> Address {
+        Address::repeat_byte(byte)
+    }
+
+    fn values(entries: &[(u8, u64)]) -> HashMap<Address, U256> {
+        entries.iter().map(|(byte, value)| (account(*byte), U256::from(*value))).collect()
+    }
+
+    #[test]
+    fn test_snapshot_diff_added_removed_and_changed_accounts() {
+        let previous = PreviousSnapshot {
+            root: "0xroot".to_string(),
+            cid: "cid".to_string(),
+            // Ranks: 1 -> 1st, 2 -> 2nd, 3 -> 3rd, 4 -> 4th
+            values: values(&[(1, 400), (2, 300), (3, 200), (4, 100)]),
+            metadata: serde_json::Value::Null,
+            published_block: None,
+        };
+        // 1 unchanged, 2 loses value and drops to 3rd, 3 gains and rises to 2nd, 4 is dropped
+        // and 5 is new
+        let current = values(&[(1, 400), (2, 150), (3, 250), (5, 50)]);
+
+        let diff = SnapshotDiff::compute(&previous, &current);
+
+        assert_eq!(
+            (diff.num_new, diff.num_dropped, diff.num_changed, diff.num_unchanged),
+            (1, 1, 2, 1)
+        );
+
+        let new = &diff.new_accounts[0];
+        assert_eq!(new.account, account(5).to_string());
+        assert_eq!((new.previous_value.as_str(), new.value.as_str()), ("0", "50"));
+        assert_eq!((new.previous_rank, new.rank, new.rank_change), (None, Some(4), None));
+
+        let dropped = &diff.dropped_accounts[0];
+        assert_eq!(dropped.account, account(4).to_string());
+        assert_eq!(dropped.delta, "-100");
+        assert_eq!((dropped.previous_rank, dropped.rank), (Some(4), None));
+
+        // Changed accounts are sorted by their current rank
+        let risen = &diff.changed_accounts[0];
+        assert_eq!(risen.account, account(3).to_string());
+        assert_eq!((risen.delta.as_str(), risen.rank_change), ("50", Some(1)));
+        let fallen = &diff.changed_accounts[1];
+        assert_eq!(fallen.account, account(2).to_string());
+        assert_eq!((fallen.delta.as_str(), fallen.rank_change), ("-150", Some(-1)));
+    }
+
+    #[test]
+    fn test_snapshot_diff_counts_rank_only_changes() {
+        let previous = PreviousSnapshot {
+            root: "0xroot".to_string(),
+            cid: "cid".to_string(),
+            values: values(&[(1, 100), (2, 200)]),
+            metadata: serde_json::Value::Null,
+            published_block: None,
+        };
+        // Account 1 keeps its value but moves up when account 2 drops out
+        let diff = SnapshotDiff::compute(&previous, &values(&[(1, 100)]));
+
+        assert_eq!((diff.num_changed, diff.num_dropped, diff.num_unchanged), (1, 1, 0));
+        assert_eq!(diff.changed_accounts[0].delta, "0");
+        assert_eq!(diff.changed_accounts[0].rank_change, Some(1));
+    }
+}
@@ -0,0 +1,32 @@
+use serde::de::DeserializeOwned;
+use wavs_wasi_utils::http::{fetch_bytes, http_request_get};
+
+/// Download a JSON document from an IPFS gateway and parse it.
+///
+/// If a gateway token is provided, it is sent as a Pinata gateway token. It must not be the
+/// upload API key, which would leak to the gateway.
+pub async fn download_json<T: DeserializeOwned>(
+    cid: &str,
+    gateway_url: &str,
+    gateway_token: Option<&str>,
+) -> Result<T, String> {
+    let url = format!("{}/{}", gateway_url.trim_end_matches('/'), cid);
+    println!("🔗 Fetching from IPFS: {}", url);
+
+    let mut request =
+        http_request_get(&url).map_err(|e| format!("Failed to create request: {}", e))?;
+
+    if let Some(gateway_token) = gateway_token {
+        request.headers_mut().insert(
+            "x-pinata-gateway-token",
+            wstd::http::HeaderValue::from_str(gateway_token)
+                .map_err(|e| format!("Invalid header value: {}", e))?,
+        );
+    }
+
+    let response =
+        fetch_bytes(request).await.map_err(|e| format!("Failed to fetch {}: {}", url, e))?;
+
+    serde_json::from_slice(&response)
+        .map_err(|e| format!("Failed to parse JSON from IPFS CID {}: {}", cid, e))
+}
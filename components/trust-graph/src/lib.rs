@@ -1,23 +1,43 @@
 #[rustfmt::skip]
 pub mod bindings;
+mod block;
 mod config;
+mod dry_run;
+mod eas_logs;
 mod eas_pagerank;
+mod eligibility;
+mod epoch;
+mod history;
+mod ipfs;
+mod manifest;
+mod offchain;
+mod output;
+mod retry;
+mod seeds;
 pub mod solidity;
 mod trigger;
+mod weight;
 
 use crate::{
-    bindings::{export, Guest, TriggerAction},
+    bindings::{export, wavs::operator::input::TriggerData, Guest, TriggerAction},
     trigger::decode_event_indexed_trigger,
 };
 
 use bindings::WasmResponse;
-use config::{MerklerConfig, PageRankSourceConfig};
+use block::PinnedBlock;
+use config::{AttestationDataSource, EventsOutput, MerklerConfig, PageRankSourceConfig};
+use dry_run::dry_run_response;
 use eas_pagerank::{sources, EasPageRankSource};
+use epoch::{skip_reason, InputFingerprint, TriggerKind};
+use history::{PreviousSnapshot, SnapshotDiff};
+use output::{output_sink, AccountEvents};
+use seeds::SeedSet;
 use serde_json::json;
-use std::fs::File;
+use std::{collections::HashMap, str::FromStr};
 use trigger::encode_trigger_output;
 use wavs_merkle_sources::core::build_merkle_ipfs_data;
-use wavs_wasi_utils::evm::alloy_primitives::hex;
+use wavs_merkler::{verify_tree, MerkleTreeIpfsData};
+use wavs_wasi_utils::evm::alloy_primitives::{hex, Address, U256};
 use wstd::runtime::block_on;
 
 struct Component;
@@ -28,51 +48,90 @@ impl Guest for Component {
         println!("🚀 Starting trust-graph component execution");
 
         let event_indexed_event = decode_event_indexed_trigger(&action);
+        let trigger_kind = TriggerKind::of(&action);
+        println!("⏰ Triggered by {:?}", trigger_kind);
 
         // Load all configuration
         let config = MerklerConfig::load()?;
-        let mut registry = sources::SourceRegistry::new();
+        let Some(mut pagerank_config) = PageRankSourceConfig::load(&config)? else {
+            println!("⚠️  PageRank not configured, exiting");
+            return Err("PageRank not configured".to_string());
+        };
 
-        // Add PageRank-based EAS points if configured
-        if let Some(pagerank_config) = PageRankSourceConfig::load()? {
-            // Expected schema tag for indexed event.
+        // If trigger is due to indexed event, verify the schema UID is for the current trust
+        // graph before any chain or IPFS reads. If not, ignore.
+        if let Some(event_indexed_event) = event_indexed_event {
             let expected_schema_tag =
                 format!("schema:{}", pagerank_config.schema_uid).to_lowercase();
-
-            let has_trust = pagerank_config.has_trust_enabled();
-            match EasPageRankSource::new(pagerank_config) {
-                Ok(pagerank_source) => {
-                    let total_pool = pagerank_source.config.total_pool.to_string();
-                    registry.add_source(pagerank_source);
-                    if has_trust {
-                        println!(
-                            "✅ Added Trust Aware EAS PageRank source with {} points pool",
-                            total_pool
-                        );
-                    } else {
-                        println!("✅ Added EAS PageRank source with {} points pool", total_pool);
-                    }
-                }
-                Err(e) => {
-                    println!("⚠️  Failed to create PageRank source: {}", e);
-                    return Err(e.to_string());
-                }
+            let found_schema_tag = event_indexed_event
+                .tags
+                .iter()
+                .any(|tag| tag.to_lowercase() == expected_schema_tag);
+            if !found_schema_tag {
+                println!("⚠️  Indexed event trigger schema does not match current trust graph schema, ignoring");
+                return Ok(None);
             }
+        }
 
-            // If trigger is due to indexed event, verify the schema UID is for the current trust graph. If not, ignore.
-            if let Some(event_indexed_event) = event_indexed_event {
-                let found_schema_tag = event_indexed_event
-                    .tags
-                    .iter()
-                    .any(|tag| tag.to_lowercase() == expected_schema_tag);
-                if !found_schema_tag {
-                    println!("⚠️  Indexed event trigger schema does not match current trust graph schema, ignoring");
-                    return Ok(None);
+        // Raw triggers come from local `wavs exec` runs, which preview instead of publishing
+        // unless `raw_trigger_dry_run` is disabled
+        let dry_run = config.dry_run
+            || (config.raw_trigger_dry_run && matches!(action.data, TriggerData::Raw(_)));
+        if dry_run {
+            println!("🧪 Dry run enabled, nothing will be uploaded or submitted");
+        }
+
+        // Pin all reads to one block so every operator computes the same root
+        let pinned_block = block_on(PinnedBlock::resolve(&action, &config, dry_run))?;
+        println!(
+            "📌 Reading chain state at block {} ({:?})",
+            pinned_block.number, pinned_block.source
+        );
+
+        // The previous snapshot is compared against, and can provide the trusted seeds
+        let previous_snapshot = block_on(PreviousSnapshot::load(&config, &pinned_block))?;
+
+        let mut registry = sources::SourceRegistry::new();
+        // Schema and page size the run's inputs are fingerprinted by, if they can be: only
+        // indexer reads can. Off-chain attestations expire with the block time and are revoked
+        // on-chain, and the balance, nonce and age filters read chain state, none of which the
+        // fingerprint covers.
+        let fingerprintable = config.skip_if_unchanged
+            && pagerank_config.data_source == AttestationDataSource::Indexer
+            && pagerank_config.offchain.is_none()
+            && !pagerank_config.eligibility.reads_chain_state();
+        let fingerprint_inputs = fingerprintable
+            .then(|| (pagerank_config.schema_uid.clone(), pagerank_config.fetch.batch_size));
+
+        // Resolve the trusted seeds of this run, e.g. from on-chain state
+        let trust_config = &mut pagerank_config.pagerank_config.trust_config;
+        let (seed_set, trusted_seeds) = block_on(SeedSet::resolve(
+            &pagerank_config.seed_source,
+            &trust_config.trusted_seeds,
+            &config,
+            &pinned_block,
+            previous_snapshot.as_ref(),
+        ))?;
+        trust_config.trusted_seeds = trusted_seeds;
+
+        let has_trust = pagerank_config.has_trust_enabled();
+        match EasPageRankSource::new(pagerank_config, pinned_block) {
+            Ok(pagerank_source) => {
+                let total_pool = pagerank_source.config.total_pool.to_string();
+                registry.add_source(pagerank_source);
+                if has_trust {
+                    println!(
+                        "✅ Added Trust Aware EAS PageRank source with {} points pool",
+                        total_pool
+                    );
+                } else {
+                    println!("✅ Added EAS PageRank source with {} points pool", total_pool);
                 }
             }
-        } else {
-            println!("⚠️  PageRank not configured, exiting");
-            return Err("PageRank not configured".to_string());
+            Err(e) => {
+                println!("⚠️  Failed to create PageRank source: {}", e);
+                return Err(e.to_string());
+            }
         }
 
         block_on(async move {
@@ -86,6 +145,40 @@ impl Guest for Component {
             .await
             .map_err(|e| e.to_string())?;
 
+            // Skip the run if nothing changed, before doing any of the heavy lifting. A run whose
+            // inputs can't be fingerprinted is never skipped.
+            let inputs = match fingerprint_inputs {
+                Some((schema_uid, batch_size)) => {
+                    let inputs = InputFingerprint::load(
+                        &ctx,
+                        &schema_uid,
+                        seed_set.seeds.clone(),
+                        &pinned_block,
+                        batch_size,
+                    )
+                    .await;
+                    match inputs {
+                        Ok(inputs) => Some(inputs),
+                        Err(e) => {
+                            println!("⚠️  Failed to fingerprint inputs, not skipping: {}", e);
+                            None
+                        }
+                    }
+                }
+                None => None,
+            };
+            if let Some(reason) = skip_reason(
+                &config,
+                trigger_kind,
+                dry_run,
+                &pinned_block,
+                inputs.as_ref(),
+                previous_snapshot.as_ref(),
+            ) {
+                println!("⏭️  Skipping run: {}", reason);
+                return Ok(None);
+            }
+
             println!("🔍 Fetching accounts and values from all sources...");
 
             let (results, total_value) =
@@ -107,10 +200,55 @@ impl Guest for Component {
             let sources_with_metadata =
                 registry.get_sources_with_metadata(&ctx).await.map_err(|e| e.to_string())?;
 
+            let current_values = results
+                .iter()
+                .map(|(account, (_, value))| {
+                    Address::from_str(&account.to_string())
+                        .map(|account| (account, *value))
+                        .map_err(|e| format!("Invalid account {}: {}", account, e))
+                })
+                .collect::<Result<HashMap<Address, U256>, String>>()?;
+
+            // Compare against the previous snapshot to link the snapshots together
+            let diff = previous_snapshot
+                .as_ref()
+                .map(|previous_snapshot| SnapshotDiff::compute(previous_snapshot, &current_values));
+
+            // Prepare the account events before publishing so the metadata can reference them.
+            // Local files are only written once the snapshot was uploaded, and a dry run prepares
+            // them the same way so its root matches a real run. Only an upload to IPFS can't be
+            // previewed, since its CID isn't known without uploading.
+            let account_events: AccountEvents = results
+                .iter()
+                .map(|(account, (events, _))| (account.to_string(), events.as_slice()))
+                .collect();
+            let events_sink = output_sink(&config);
+            let events_output = events_sink.get_name().to_string();
+            let events_prepared = if dry_run && matches!(config.events_output, EventsOutput::Ipfs) {
+                println!("🧪 Dry run, skipping the upload of account events");
+                None
+            } else {
+                Some(events_sink.prepare(&account_events).await?)
+            };
+            let account_events_cid = events_prepared.as_ref().and_then(|p| p.cid.clone());
+            // The root of a dry run differs from a real run if the events CID is missing
+            let events_cid_unknown = events_prepared.is_none();
+
             let metadata = json!({
                 "num_accounts": results.len(),
+                "block": pinned_block,
+                "trigger": trigger_kind,
+                "inputs": inputs,
+                "seeds": seed_set,
                 "total_value": total_value.to_string(),
                 "sources": sources_with_metadata,
+                "previous_root": previous_snapshot.as_ref().map(|s| s.root.clone()),
+                "previous_cid": previous_snapshot.as_ref().map(|s| s.cid.clone()),
+                "diff": diff,
+                "account_events": {
+                    "output": events_output,
+                    "cid": account_events_cid,
+                },
             });
 
             let ipfs_data = build_merkle_ipfs_data(tree_data, metadata)?;
@@ -119,6 +257,26 @@ impl Guest for Component {
 
             println!("🌳 Generated merkle tree with root: {}", root);
 
+            // Self-check the tree with the leaf encoding the contracts use before publishing it
+            let tree_data: MerkleTreeIpfsData = serde_json::from_value(
+                serde_json::to_value(&ipfs_data).map_err(|e| e.to_string())?,
+            )
+            .map_err(|e| e.to_string())?;
+            let report = verify_tree(&tree_data)?.into_result()?;
+            println!("✅ Verified merkle root and {} proofs", report.num_entries);
+
+            if dry_run {
+                return dry_run_response(
+                    &ipfs_data,
+                    &root,
+                    &current_values,
+                    total_value,
+                    diff.as_ref(),
+                    events_cid_unknown,
+                )
+                .map(Some);
+            }
+
             let ipfs_data_json = serde_json::to_string(&ipfs_data).map_err(|e| e.to_string())?;
             let cid = wavs_ipfs::upload_json_to_ipfs(
                 &ipfs_data_json,
@@ -131,18 +289,6 @@ impl Guest for Component {
 
             println!("✅ Successfully uploaded to IPFS with CID: {}", cid);
 
-            println!("🗃️ Writing account events to {}...", config.events_dir.display());
-            results
-                .into_iter()
-                .map(|(account, (events, _))| {
-                    let file_path = config.events_dir.join(format!("{}.json", account));
-                    let file = File::create(file_path).unwrap();
-                    serde_json::to_writer(file, &events)
-                })
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|e| format!("Failed to write events to files: {}", e.to_string()))?;
-            println!("✅ Successfully wrote account events");
-
             let ipfs_hash = cid.hash().digest();
             let payload = encode_trigger_output(
                 &action,
@@ -150,9 +296,18 @@ impl Guest for Component {
                 ipfs_hash,
                 cid.to_string(),
                 total_value,
+                config.expiry_seconds,
             )
             .await?;
 
+            // The snapshot is published by now, so a failed write must not drop the submission:
+            // the next run rewrites the account events
+            if let Some(prepared) = events_prepared {
+                if let Err(e) = prepared.commit() {
+                    println!("⚠️ Failed to write account events: {}", e);
+                }
+            }
+
             println!("🎉 Rewards component execution completed successfully");
             println!("📦 Final payload size: {} bytes", payload.len());
 
@@ -0,0 +1,53 @@
+use pagerank::GraphManifest;
+use serde::Serialize;
+
+use crate::block::PinnedBlock;
+
+/// Version of the reproducibility manifest format
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Component build a run was computed with
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ComponentInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// Git commit the component was built from, set by the build script
+    pub build_hash: &'static str,
+}
+
+impl ComponentInfo {
+    pub const CURRENT: Self = Self {
+        name: env!("CARGO_PKG_NAME"),
+        version: env!("CARGO_PKG_VERSION"),
+        build_hash: env!("TRUST_GRAPH_BUILD_HASH"),
+    };
+}
+
+/// What happened to the attestations a run read
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AttestationCounts {
+    /// Attestations read from all data sources
+    pub processed: usize,
+    /// Revoked attestations, which add no edges
+    pub revoked: usize,
+    /// Attestations dropped by the eligibility filters
+    pub ineligible: usize,
+    /// Malformed indexed attestations and attestations whose weight could not be read
+    pub failed: usize,
+    /// Attestations passed to the graph as edges, before the self-edge and duplicate policies
+    pub edges: usize,
+}
+
+/// Everything needed to reproduce the root of a run offline, recorded in the merkle metadata
+/// under `manifest`. The `graph` holds the final edge list and every parameter the points are
+/// computed from; see `wavs_merkler::reproduce_root`.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReproducibilityManifest {
+    pub manifest_version: u32,
+    pub component: ComponentInfo,
+    pub block: PinnedBlock,
+    pub schema_uid: String,
+    pub data_source: &'static str,
+    pub attestations: AttestationCounts,
+    pub graph: GraphManifest,
+}
@@ -0,0 +1,617 @@
+use alloy_provider::Provider;
+use alloy_rpc_types::{TransactionInput, TransactionRequest};
+use alloy_sol_types::{Eip712Domain, SolCall, SolStruct, SolValue};
+use anyhow::Result;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::{collections::HashSet, path::Path};
+use wavs_indexer_api::solidity::IndexedEvent;
+use wavs_indexer_api::IndexedAttestation;
+use wavs_merkle_sources::sources::SourceContext;
+use wavs_wasi_utils::evm::alloy_primitives::{
+    keccak256, Address, Bytes, FixedBytes, Signature, TxKind, B256, U256,
+};
+
+use crate::{
+    block::PinnedBlock,
+    config::OffchainAttestationsConfig,
+    eas_logs::{ISchemaRegistry, IEAS},
+    ipfs,
+};
+
+/// Name of the EIP-712 domain EAS signs off-chain attestations with
+const EAS_DOMAIN_NAME: &str = "EAS Attestation";
+
+/// Event type given to verified off-chain attestations, to tell them apart from on-chain ones
+pub const OFFCHAIN_ATTESTATION_EVENT_TYPE: &str = "offchain_attestation";
+
+/// Off-chain attestation as exported by the EAS SDK
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignedOffchainAttestation {
+    pub sig: OffchainAttestationSig,
+    pub signer: Address,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OffchainAttestationSig {
+    #[serde(default)]
+    pub uid: Option<B256>,
+    pub message: OffchainAttestationMessage,
+    pub signature: OffchainSignature,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OffchainAttestationMessage {
+    #[serde(default, deserialize_with = "number_or_string")]
+    pub version: u64,
+    pub schema: B256,
+    pub recipient: Address,
+    #[serde(deserialize_with = "number_or_string")]
+    pub time: u64,
+    #[serde(deserialize_with = "number_or_string")]
+    pub expiration_time: u64,
+    pub revocable: bool,
+    #[serde(rename = "refUID")]
+    pub ref_uid: B256,
+    pub data: Bytes,
+    #[serde(default)]
+    pub salt: B256,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OffchainSignature {
+    pub v: u8,
+    pub r: B256,
+    pub s: B256,
+}
+
+/// Accept numbers encoded as JSON numbers or as decimal strings, like the EAS SDK exports them.
+fn number_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum NumberOrString {
+        Number(u64),
+        String(String),
+    }
+
+    match NumberOrString::deserialize(deserializer)? {
+        NumberOrString::Number(n) => Ok(n),
+        NumberOrString::String(s) => s.parse().map_err(serde::de::Error::custom),
+    }
+}
+
+/// An off-chain attestation that did not pass verification
+#[derive(Debug, Clone, Serialize)]
+pub struct RejectedOffchainAttestation {
+    pub uid: Option<String>,
+    pub attester: String,
+    pub reason: String,
+}
+
+/// Summary of the off-chain attestations of a run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OffchainReport {
+    pub num_loaded: usize,
+    pub num_accepted: usize,
+    pub weight_multiplier: f64,
+    pub rejected: Vec<RejectedOffchainAttestation>,
+}
+
+/// Load the off-chain attestations of a schema and verify them like `OffchainAttestationVerifier`,
+/// at the pinned block: EIP-712 signature against the EAS domain, time, expiration, schema,
+/// referenced attestation and off-chain revocation. Verified attestations are returned in the
+/// shape the indexer returns, identified by their signer and EIP-712 hash.
+pub async fn load_offchain_attestations(
+    ctx: &SourceContext,
+    config: &OffchainAttestationsConfig,
+    schema: FixedBytes<32>,
+    block: &PinnedBlock,
+) -> Result<(Vec<IndexedAttestation>, OffchainReport)> {
+    let mut signed = Vec::new();
+    if let Some(cid) = &config.cid {
+        println!("📥 Loading off-chain attestations from IPFS CID: {}", cid);
+        let attestations: Vec<SignedOffchainAttestation> = ipfs::download_json(
+            cid,
+            &config.ipfs_gateway_url,
+            config.ipfs_gateway_token.as_deref(),
+        )
+        .await
+        .map_err(|e| anyhow::anyhow!(e))?;
+        signed.extend(attestations);
+    }
+    if let Some(dir) = &config.dir {
+        println!("📥 Loading off-chain attestations from {}", dir.display());
+        signed.extend(read_dir(dir)?);
+    }
+
+    let domain = Eip712Domain::new(
+        Some(EAS_DOMAIN_NAME.into()),
+        Some(call(ctx, ctx.eas_address, IEAS::versionCall {}, block).await?.into()),
+        Some(U256::from(config.chain_id)),
+        Some(ctx.eas_address),
+        None,
+    );
+    let now = get_block_timestamp(ctx, block).await?;
+    let schema_exists = schema_exists(ctx, schema, block).await?;
+
+    let mut report = OffchainReport {
+        num_loaded: signed.len(),
+        weight_multiplier: config.weight_multiplier,
+        ..Default::default()
+    };
+    let mut seen = HashSet::new();
+    let mut attestations = Vec::new();
+    for attestation in signed {
+        if attestation.sig.message.schema != schema {
+            continue;
+        }
+
+        let verified = match verify(&attestation, &domain, now) {
+            Ok(_) if !schema_exists => Err("schema does not exist".to_string()),
+            Ok(hash) => match on_chain_rejection(ctx, &attestation, block).await? {
+                Some(reason) => Err(reason),
+                None => Ok(hash),
+            },
+            Err(reason) => Err(reason),
+        };
+
+        // The EIP-712 hash doesn't cover the signer, so several signers can sign the same message
+        match verified {
+            Ok(hash) if !seen.insert((attestation.signer, hash)) => {
+                report.rejected.push(rejected(&attestation, "duplicate"))
+            }
+            Ok(hash) => attestations.push(indexed_attestation(ctx, hash, attestation)),
+            Err(reason) => report.rejected.push(rejected(&attestation, &reason)),
+        }
+    }
+    report.num_accepted = attestations.len();
+
+    println!(
+        "✅ Accepted {} of {} off-chain attestations ({} rejected)",
+        report.num_accepted,
+        report.num_loaded,
+        report.rejected.len()
+    );
+    for rejected in &report.rejected {
+        println!("   ⚠️  Rejected attestation from {}: {}", rejected.attester, rejected.reason);
+    }
+
+    Ok((attestations, report))
+}
+
+/// Read every JSON file in a directory, each holding one signed attestation or an array of them.
+fn read_dir(dir: &Path) -> Result<Vec<SignedOffchainAttestation>> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(Box<SignedOffchainAttestation>),
+        Many(Vec<SignedOffchainAttestation>),
+    }
+
+    let mut paths = std::fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    // Sort for a deterministic order across operators
+    paths.sort();
+
+    let mut attestations = Vec::new();
+    for path in paths.iter().filter(|path| path.extension().is_some_and(|ext| ext == "json")) {
+        let contents = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        match serde_json::from_slice(&contents)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))?
+        {
+            OneOrMany::One(attestation) => attestations.push(*attestation),
+            OneOrMany::Many(many) => attestations.extend(many),
+        }
+    }
+
+    Ok(attestations)
+}
+
+/// Verify the checks of `OffchainAttestationVerifier` that don't need the chain, and that the
+/// attestation hasn't expired at `now`. Returns the EIP-712 hash of the attestation.
+fn verify(
+    attestation: &SignedOffchainAttestation,
+    domain: &Eip712Domain,
+    now: u64,
+) -> Result<B256, String> {
+    let message = &attestation.sig.message;
+    if attestation.signer == Address::ZERO {
+        return Err("signer is the zero address".to_string());
+    }
+    if message.time > now {
+        return Err(format!("time {} is in the future", message.time));
+    }
+    if message.expiration_time != 0 && message.expiration_time <= now {
+        return Err(format!("expired at {}", message.expiration_time));
+    }
+
+    let hash = match message.version {
+        0 => legacy::Attestation {
+            schema: message.schema,
+            recipient: message.recipient,
+            time: message.time,
+            expirationTime: message.expiration_time,
+            revocable: message.revocable,
+            refUID: message.ref_uid,
+            data: message.data.clone(),
+        }
+        .eip712_signing_hash(domain),
+        1 => version1::Attest {
+            version: 1,
+            schema: message.schema,
+            recipient: message.recipient,
+            time: message.time,
+            expirationTime: message.expiration_time,
+            revocable: message.revocable,
+            refUID: message.ref_uid,
+            data: message.data.clone(),
+        }
+        .eip712_signing_hash(domain),
+        2 => version2::Attest {
+            version: 2,
+            schema: message.schema,
+            recipient: message.recipient,
+            time: message.time,
+            expirationTime: message.expiration_time,
+            revocable: message.revocable,
+            refUID: message.ref_uid,
+            data: message.data.clone(),
+            salt: message.salt,
+        }
+        .eip712_signing_hash(domain),
+        version => return Err(format!("unknown version {}", version)),
+    };
+
+    let signature = &attestation.sig.signature;
+    let y_parity = match signature.v {
+        0 | 27 => false,
+        1 | 28 => true,
+        v => return Err(format!("invalid signature v {}", v)),
+    };
+    let signature = Signature::new(signature.r.into(), signature.s.into(), y_parity);
+    let recovered = signature
+        .recover_address_from_prehash(&hash)
+        .map_err(|e| format!("invalid signature: {}", e))?;
+
+    if recovered != attestation.signer {
+        return Err(format!("signature recovers to {}, not the signer", recovered));
+    }
+
+    Ok(hash)
+}
+
+fn rejected(attestation: &SignedOffchainAttestation, reason: &str) -> RejectedOffchainAttestation {
+    RejectedOffchainAttestation {
+        uid: attestation.sig.uid.map(|uid| uid.to_string()),
+        attester: attestation.signer.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Check the referenced attestation and the off-chain revocations of a verified attestation.
+/// Returns the reason to reject it, if any.
+async fn on_chain_rejection(
+    ctx: &SourceContext,
+    attestation: &SignedOffchainAttestation,
+    block: &PinnedBlock,
+) -> Result<Option<String>> {
+    let message = &attestation.sig.message;
+    if message.ref_uid != B256::ZERO {
+        let valid = IEAS::isAttestationValidCall { uid: message.ref_uid };
+        if !call(ctx, ctx.eas_address, valid, block).await? {
+            return Ok(Some(format!("referenced attestation {} does not exist", message.ref_uid)));
+        }
+    }
+
+    // Off-chain revocations are recorded under the off-chain UID of the EAS SDK. The exported UID
+    // isn't covered by the signature, so it is only checked in addition to the derived one.
+    let derived = offchain_uid(message);
+    let exported = attestation.sig.uid.filter(|uid| *uid != derived);
+    for uid in std::iter::once(derived).chain(exported) {
+        let revoked = IEAS::getRevokeOffchainCall { revoker: attestation.signer, data: uid };
+        let revocation_time = call(ctx, ctx.eas_address, revoked, block).await?;
+        if revocation_time != 0 {
+            return Ok(Some(format!("revoked off-chain at {}", revocation_time)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Whether the schema is registered in the schema registry of EAS.
+async fn schema_exists(ctx: &SourceContext, schema: B256, block: &PinnedBlock) -> Result<bool> {
+    let registry = call(ctx, ctx.eas_address, IEAS::getSchemaRegistryCall {}, block).await?;
+    let record = call(ctx, registry, ISchemaRegistry::getSchemaCall { uid: schema }, block).await?;
+
+    Ok(record.uid != B256::ZERO)
+}
+
+/// Off-chain UID of an attestation, like `getOffchainUID` of the EAS SDK derives it. The SDK packs
+/// the schema as the UTF-8 bytes of its hex string, and a zero attester.
+fn offchain_uid(message: &OffchainAttestationMessage) -> B256 {
+    let schema = Bytes::from(message.schema.to_string().into_bytes());
+    let packed = match message.version {
+        0 => (
+            schema,
+            message.recipient,
+            Address::ZERO,
+            message.time,
+            message.expiration_time,
+            message.revocable,
+            message.ref_uid,
+            message.data.clone(),
+            0u32,
+        )
+            .abi_encode_packed(),
+        1 => (
+            message.version as u16,
+            schema,
+            message.recipient,
+            Address::ZERO,
+            message.time,
+            message.expiration_time,
+            message.revocable,
+            message.ref_uid,
+            message.data.clone(),
+            0u32,
+        )
+            .abi_encode_packed(),
+        _ => (
+            message.version as u16,
+            schema,
+            message.recipient,
+            Address::ZERO,
+            message.time,
+            message.expiration_time,
+            message.revocable,
+            message.ref_uid,
+            message.data.clone(),
+            message.salt,
+            0u32,
+        )
+            .abi_encode_packed(),
+    };
+
+    keccak256(packed)
+}
+
+async fn get_block_timestamp(ctx: &SourceContext, block: &PinnedBlock) -> Result<u64> {
+    let pinned = ctx
+        .provider
+        .get_block(block.block_id())
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Block {} not found", block.number))?;
+
+    Ok(pinned.header.timestamp)
+}
+
+/// Call a view function at the pinned block.
+async fn call<C: SolCall>(
+    ctx: &SourceContext,
+    address: Address,
+    call: C,
+    block: &PinnedBlock,
+) -> Result<C::Return> {
+    let tx = TransactionRequest {
+        to: Some(TxKind::Call(address)),
+        input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
+        ..Default::default()
+    };
+
+    let result = ctx.provider.call(tx).block(block.block_id()).await?;
+
+    C::abi_decode_returns(&result)
+        .map_err(|e| anyhow::anyhow!("Failed to decode {} result: {}", C::SIGNATURE, e))
+}
+
+/// UID of a verified off-chain attestation. The EIP-712 hash alone would give the same UID to
+/// every signer of the same message.
+fn attestation_uid(signer: Address, hash: B256) -> B256 {
+    keccak256((signer, hash).abi_encode_packed())
+}
+
+fn indexed_attestation(
+    ctx: &SourceContext,
+    hash: B256,
+    attestation: SignedOffchainAttestation,
+) -> IndexedAttestation {
+    let uid = attestation_uid(attestation.signer, hash);
+    let message = attestation.sig.message;
+    let event = IndexedEvent {
+        eventId: FixedBytes::ZERO,
+        chainId: ctx.chain_id.clone(),
+        relevantContract: ctx.eas_address,
+        blockNumber: U256::ZERO,
+        timestamp: message.time as u128,
+        eventType: OFFCHAIN_ATTESTATION_EVENT_TYPE.to_string(),
+        tags: vec![format!("uid:{}", uid), format!("schema:{}", message.schema)],
+        relevantAddresses: vec![attestation.signer, message.recipient],
+        data: message.data,
+        metadata: Vec::new().into(),
+        deleted: false,
+    };
+
+    IndexedAttestation {
+        uid,
+        schema_uid: message.schema,
+        attester: attestation.signer,
+        recipient: message.recipient,
+        event,
+    }
+}
+
+mod legacy {
+    alloy_sol_types::sol! {
+        struct Attestation {
+            bytes32 schema;
+            address recipient;
+            uint64 time;
+            uint64 expirationTime;
+            bool revocable;
+            bytes32 refUID;
+            bytes data;
+        }
+    }
+}
+
+mod version1 {
+    alloy_sol_types::sol! {
+        struct Attest {
+            uint16 version;
+            bytes32 schema;
+            address recipient;
+            uint64 time;
+            uint64 expirationTime;
+            bool revocable;
+            bytes32 refUID;
+            bytes data;
+        }
+    }
+}
+
+mod version2 {
+    alloy_sol_types::sol! {
+        struct Attest {
+            uint16 version;
+            bytes32 schema;
+            address recipient;
+            uint64 time;
+            uint64 expirationTime;
+            bool revocable;
+            bytes32 refUID;
+            bytes data;
+            bytes32 salt;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wavs_wasi_utils::evm::alloy_primitives::{address, b256};
+
+    /// Signer of the vectors, the first default anvil account
+    const SIGNER: Address = address!("0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266");
+    const TIME: u64 = 1_700_000_000;
+
+    fn domain() -> Eip712Domain {
+        Eip712Domain::new(
+            Some(EAS_DOMAIN_NAME.into()),
+            Some("1.3.0".into()),
+            Some(U256::from(1)),
+            Some(address!("0xA1207F3BBa224E2c9c3c6D5aF63D0eb1582Ce587")),
+            None,
+        )
+    }
+
+    /// Attestation signed by [`SIGNER`], as exported by the EAS SDK
+    fn attestation(version: u64, r: &str, s: &str) -> SignedOffchainAttestation {
+        serde_json::from_value(serde_json::json!({
+            "sig": {
+                "message": {
+                    "version": version,
+                    "schema": B256::repeat_byte(0x11),
+                    "recipient": Address::repeat_byte(0x22),
+                    "time": TIME.to_string(),
+                    "expirationTime": "0",
+                    "revocable": true,
+                    "refUID": B256::ZERO,
+                    "data": B256::with_last_byte(42),
+                    "salt": B256::repeat_byte(0x33),
+                },
+                "signature": { "v": 28, "r": r, "s": s },
+            },
+            "signer": SIGNER,
+        }))
+        .unwrap()
+    }
+
+    fn version1() -> SignedOffchainAttestation {
+        attestation(
+            1,
+            "0x128b05531c44bb59014c4aaa22387b321eb510a7cbe1c7a19e5725a9c24c7855",
+            "0x0fea47b4aa8e1ade54285f140d7b9d95f4febefc74863ab2ec710c55f7291f85",
+        )
+    }
+
+    fn version2() -> SignedOffchainAttestation {
+        attestation(
+            2,
+            "0xb9e7a71fea9a7db2fdaba7a425d01229abe6f54f146c20916c0e1cbd7c0f1417",
+            "0x14026452c8e543883f8209676d0bfab0831f64fd091808d4e60dd75c2fb1c423",
+        )
+    }
+
+    #[test]
+    fn test_type_hashes_match_the_verifier_contract() {
+        let type_hash = |encoded: std::borrow::Cow<'static, str>| keccak256(encoded.as_bytes());
+
+        assert_eq!(
+            type_hash(legacy::Attestation::eip712_encode_type()),
+            b256!("0x2fcbc49c85ccde58f6986371b0828354351185c921aebbaace3e89e0e023b25d")
+        );
+        assert_eq!(
+            type_hash(version1::Attest::eip712_encode_type()),
+            b256!("0x9a1ef129b3715afc513574bddcf4404e21b0296e3ca20fec532fe1ec8d0932ec")
+        );
+        assert_eq!(
+            type_hash(version2::Attest::eip712_encode_type()),
+            b256!("0x258b757fc37a6699a984f4be01258122f834200374b34b4457b6db0dd0f71132")
+        );
+    }
+
+    #[test]
+    fn test_verify_signed_attestations() {
+        assert_eq!(
+            verify(&version1(), &domain(), TIME).unwrap(),
+            b256!("0xa9744ea4e57ea607f7a4a94aa74d95534f76c1cb418d5c6067c9e1bed6a88d8e")
+        );
+        assert_eq!(
+            verify(&version2(), &domain(), TIME).unwrap(),
+            b256!("0x6f3bd7e91252bb6fda74b0fd1d6793755712c09a1fc0db2447080078c10130ba")
+        );
+    }
+
+    #[test]
+    fn test_verify_rejects_tampered_attestations() {
+        let mut tampered = version2();
+        tampered.sig.message.recipient = Address::repeat_byte(0x44);
+        assert!(verify(&tampered, &domain(), TIME).unwrap_err().contains("not the signer"));
+
+        // A version 1 signature doesn't cover the salt
+        let mut salted = version1();
+        salted.sig.message.salt = B256::ZERO;
+        assert!(verify(&salted, &domain(), TIME).is_ok());
+        salted.sig.message.version = 2;
+        assert!(verify(&salted, &domain(), TIME).is_err());
+
+        let mut unsigned = version2();
+        unsigned.signer = Address::ZERO;
+        assert!(verify(&unsigned, &domain(), TIME).is_err());
+    }
+
+    #[test]
+    fn test_verify_rejects_future_and_expired_attestations() {
+        assert!(verify(&version2(), &domain(), TIME - 1).unwrap_err().contains("future"));
+
+        let mut expiring = version2();
+        expiring.sig.message.expiration_time = TIME + 10;
+        // The expiration time isn't signed in this vector, so the signature check fails once the
+        // time checks pass
+        assert!(verify(&expiring, &domain(), TIME + 10).unwrap_err().contains("expired"));
+        assert!(verify(&expiring, &domain(), TIME + 9).unwrap_err().contains("not the signer"));
+    }
+
+    #[test]
+    fn test_attestation_uid_depends_on_the_signer() {
+        let hash = verify(&version2(), &domain(), TIME).unwrap();
+        let other = Address::repeat_byte(0x55);
+
+        assert_eq!(attestation_uid(SIGNER, hash), attestation_uid(SIGNER, hash));
+        assert_ne!(attestation_uid(SIGNER, hash), attestation_uid(other, hash));
+        assert_ne!(attestation_uid(SIGNER, hash), hash);
+    }
+}
@@ -0,0 +1,299 @@
+use async_trait::async_trait;
+use serde_json::json;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use wavs_merkle_sources::sources::SourceEvent;
+use wavs_wasi_utils::evm::alloy_primitives::Address;
+
+use crate::config::{EventsOutput, MerklerConfig};
+
+/// Account events to output, keyed by account
+pub type AccountEvents<'a> = BTreeMap<String, &'a [SourceEvent]>;
+
+/// Destination for the per-account events produced by a run
+#[async_trait(?Send)]
+pub trait OutputSink {
+    /// Get the name of the sink
+    fn get_name(&self) -> &str;
+
+    /// Prepare the output of the events of all accounts without writing anything locally, so a
+    /// run that fails to publish its root leaves the previous output in place.
+    async fn prepare(&self, events: &AccountEvents<'_>) -> Result<PreparedOutput, String>;
+}
+
+/// Account events output prepared in memory, written by [`PreparedOutput::commit`] once the root
+/// was published
+#[derive(Debug, Default)]
+pub struct PreparedOutput {
+    /// CID of the output, if it was uploaded to IPFS
+    pub cid: Option<String>,
+    /// Files to write, with their contents
+    files: Vec<(PathBuf, Vec<u8>)>,
+    /// Directory whose account files that aren't written are pruned
+    prune_dir: Option<PathBuf>,
+}
+
+impl PreparedOutput {
+    /// Write the prepared files and prune the stale account files.
+    pub fn commit(self) -> Result<(), String> {
+        let mut written = HashSet::new();
+        for (path, contents) in &self.files {
+            write_atomic(path, contents)?;
+            if let Some(file_name) = path.file_name() {
+                written.insert(file_name.to_string_lossy().to_string());
+            }
+        }
+
+        match &self.prune_dir {
+            Some(dir) => {
+                let pruned = prune_stale(dir, &written)?;
+                println!(
+                    "✅ Wrote events for {} accounts, pruned {} stale files",
+                    written.len(),
+                    pruned
+                );
+            }
+            None if !self.files.is_empty() => println!("✅ Wrote account events"),
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// Create the output sink configured by `events_output`.
+pub fn output_sink(config: &MerklerConfig) -> Box<dyn OutputSink + '_> {
+    match &config.events_output {
+        EventsOutput::Directory(dir) => Box::new(DirectorySink { dir }),
+        EventsOutput::Jsonl(path) => Box::new(JsonlSink { path }),
+        EventsOutput::Ipfs => Box::new(IpfsSink {
+            ipfs_url: &config.ipfs_url,
+            ipfs_api_key: config.ipfs_api_key.as_deref(),
+        }),
+        EventsOutput::None => Box::new(NoopSink),
+    }
+}
+
+/// Writes one `<account>.json` file per account and removes files of accounts that dropped out
+pub struct DirectorySink<'a> {
+    dir: &'a PathBuf,
+}
+
+#[async_trait(?Send)]
+impl OutputSink for DirectorySink<'_> {
+    fn get_name(&self) -> &str {
+        "directory"
+    }
+
+    async fn prepare(&self, events: &AccountEvents<'_>) -> Result<PreparedOutput, String> {
+        println!("🗃️ Preparing account events for {}...", self.dir.display());
+
+        let files = events
+            .iter()
+            .map(|(account, account_events)| {
+                let contents = serde_json::to_vec(account_events)
+                    .map_err(|e| format!("Failed to serialize events for {}: {}", account, e))?;
+                Ok((self.dir.join(format!("{}.json", account)), contents))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(PreparedOutput { cid: None, files, prune_dir: Some(self.dir.clone()) })
+    }
+}
+
+/// Writes a single JSONL file with one `{"account", "events"}` line per account
+pub struct JsonlSink<'a> {
+    path: &'a PathBuf,
+}
+
+#[async_trait(?Send)]
+impl OutputSink for JsonlSink<'_> {
+    fn get_name(&self) -> &str {
+        "jsonl"
+    }
+
+    async fn prepare(&self, events: &AccountEvents<'_>) -> Result<PreparedOutput, String> {
+        println!("🗃️ Preparing account events for {}...", self.path.display());
+
+        let mut contents = Vec::new();
+        for (account, account_events) in events {
+            serde_json::to_writer(
+                &mut contents,
+                &json!({ "account": account, "events": account_events }),
+            )
+            .map_err(|e| format!("Failed to serialize events for {}: {}", account, e))?;
+            contents.push(b'\n');
+        }
+
+        Ok(PreparedOutput {
+            cid: None,
+            files: vec![(self.path.clone(), contents)],
+            prune_dir: None,
+        })
+    }
+}
+
+/// Uploads a single JSON document with the events of all accounts to IPFS. The upload is content
+/// addressed, so it happens while preparing: an unpublished run only leaves an unreferenced file.
+pub struct IpfsSink<'a> {
+    ipfs_url: &'a str,
+    ipfs_api_key: Option<&'a str>,
+}
+
+#[async_trait(?Send)]
+impl OutputSink for IpfsSink<'_> {
+    fn get_name(&self) -> &str {
+        "ipfs"
+    }
+
+    async fn prepare(&self, events: &AccountEvents<'_>) -> Result<PreparedOutput, String> {
+        println!("🗃️ Uploading account events to IPFS...");
+
+        let events_json = serde_json::to_string(events)
+            .map_err(|e| format!("Failed to serialize account events: {}", e))?;
+        let cid = wavs_ipfs::upload_json_to_ipfs(
+            &events_json,
+            "account_events.json",
+            self.ipfs_url,
+            self.ipfs_api_key,
+        )
+        .await
+        .map_err(|e| format!("Failed to upload account events to IPFS: {}", e))?;
+
+        println!("✅ Uploaded events for {} accounts with CID: {}", events.len(), cid);
+
+        Ok(PreparedOutput { cid: Some(cid.to_string()), ..Default::default() })
+    }
+}
+
+/// Discards account events
+pub struct NoopSink;
+
+#[async_trait(?Send)]
+impl OutputSink for NoopSink {
+    fn get_name(&self) -> &str {
+        "none"
+    }
+
+    async fn prepare(&self, _events: &AccountEvents<'_>) -> Result<PreparedOutput, String> {
+        println!("ℹ️  Account events output disabled");
+        Ok(PreparedOutput::default())
+    }
+}
+
+/// Remove the files of a directory named after an account that aren't in `keep`. Other files are
+/// never touched. Returns the number of files removed.
+fn prune_stale(dir: &Path, keep: &HashSet<String>) -> Result<usize, String> {
+    let mut pruned = 0;
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read events directory {}: {}", dir.display(), e))?;
+    for entry in entries {
+        let entry = entry.map_err(|e| format!("Failed to read events directory entry: {}", e))?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_account_file = file_name
+            .strip_suffix(".json")
+            .is_some_and(|account| Address::from_str(account).is_ok());
+
+        if is_account_file && !keep.contains(&file_name) {
+            std::fs::remove_file(entry.path())
+                .map_err(|e| format!("Failed to remove stale events file {}: {}", file_name, e))?;
+            pruned += 1;
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Write a file atomically by writing to a temporary file and renaming it into place.
+fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), String> {
+    let tmp_path = path.with_file_name(format!(
+        "{}.tmp",
+        path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default()
+    ));
+
+    let mut file = File::create(&tmp_path)
+        .map_err(|e| format!("Failed to create {}: {}", tmp_path.display(), e))?;
+    file.write_all(contents)
+        .and_then(|_| file.sync_all())
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to move {} to {}: {}", tmp_path.display(), path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Empty scratch directory for a test
+    fn scratch_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "trust-graph-output-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn account_file(byte: u8) -> String {
+        format!("{}.json", Address::repeat_byte(byte))
+    }
+
+    #[test]
+    fn test_write_atomic_replaces_the_file() {
+        let dir = scratch_dir("atomic");
+        let path = dir.join("events.jsonl");
+
+        write_atomic(&path, b"first").unwrap();
+        write_atomic(&path, b"second").unwrap();
+
+        assert_eq!(std::fs::read(&path).unwrap(), b"second");
+        // The temporary file was moved into place
+        assert!(!dir.join("events.jsonl.tmp").exists());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_prune_stale_only_removes_account_files() {
+        let dir = scratch_dir("prune");
+        for file_name in [account_file(1), account_file(2), "notes.json".to_string()] {
+            std::fs::write(dir.join(file_name), b"[]").unwrap();
+        }
+        std::fs::write(dir.join("0x1234.json"), b"[]").unwrap();
+
+        let keep = HashSet::from([account_file(1)]);
+        assert_eq!(prune_stale(&dir, &keep).unwrap(), 1);
+
+        assert!(dir.join(account_file(1)).exists());
+        assert!(!dir.join(account_file(2)).exists());
+        // Files not named after an account are left alone
+        assert!(dir.join("notes.json").exists());
+        assert!(dir.join("0x1234.json").exists());
+    }
+
+    #[test]
+    fn test_prepared_output_is_only_written_on_commit() {
+        let dir = scratch_dir("commit");
+        std::fs::write(dir.join(account_file(2)), b"[]").unwrap();
+
+        let prepared = PreparedOutput {
+            cid: None,
+            files: vec![(dir.join(account_file(1)), b"[1]".to_vec())],
+            prune_dir: Some(dir.clone()),
+        };
+        // Nothing changes until the output is committed
+        assert!(!dir.join(account_file(1)).exists());
+        assert!(dir.join(account_file(2)).exists());
+
+        prepared.commit().unwrap();
+        assert_eq!(std::fs::read(dir.join(account_file(1))).unwrap(), b"[1]");
+        assert!(!dir.join(account_file(2)).exists());
+    }
+}
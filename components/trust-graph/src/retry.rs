@@ -0,0 +1,63 @@
+use anyhow::Result;
+use std::{fmt::Display, future::Future};
+
+use crate::config::FetchConfig;
+
+/// A failed request to the chain. Only these are retried by [`with_retry`]: any other error, like
+/// a response that doesn't decode, would fail the same way on every attempt.
+#[derive(Debug)]
+pub struct TransportError(String);
+
+impl Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for TransportError {}
+
+/// Mark the error of a chain request as a transport error, so that [`with_retry`] retries it.
+pub fn transport_error(e: impl Display) -> anyhow::Error {
+    anyhow::Error::new(TransportError(e.to_string()))
+}
+
+fn is_transport_error(e: &anyhow::Error) -> bool {
+    e.chain().any(|cause| cause.is::<TransportError>())
+}
+
+/// Run a fallible chain read, retrying transport errors with exponential backoff as configured.
+pub async fn with_retry<T, F, Fut>(fetch: &FetchConfig, what: impl Display, mut f: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match f().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < fetch.max_retries && is_transport_error(&e) => {
+                let backoff = fetch.retry_backoff_ms.saturating_mul(2u64.saturating_pow(attempt));
+                attempt += 1;
+                println!(
+                    "⚠️  {} failed ({}), retry {}/{} in {}ms",
+                    what, e, attempt, fetch.max_retries, backoff
+                );
+                wstd::task::sleep(wstd::time::Duration::from_millis(backoff)).await;
+            }
+            Err(e) if attempt == 0 => return Err(e.context(format!("{} failed", what))),
+            Err(e) => return Err(e.context(format!("{} failed after {} retries", what, attempt))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_transport_errors_are_retried() {
+        assert!(is_transport_error(&transport_error("connection reset")));
+        assert!(is_transport_error(&transport_error("timeout").context("Nonce of 0x01")));
+        assert!(!is_transport_error(&anyhow::anyhow!("Failed to decode attestation")));
+    }
+}
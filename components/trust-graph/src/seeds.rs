@@ -0,0 +1,230 @@
+use alloy_network::Ethereum;
+use alloy_provider::Provider;
+use alloy_rpc_types::{TransactionInput, TransactionRequest};
+use alloy_sol_types::{sol, SolCall};
+use serde::Serialize;
+use std::collections::HashSet;
+use wavs_wasi_utils::evm::{
+    alloy_primitives::{Address, TxKind},
+    new_evm_provider,
+};
+
+use crate::{
+    block::PinnedBlock,
+    config::{MerklerConfig, SeedSource},
+    history::PreviousSnapshot,
+    solidity::ITrustSeedRegistry,
+};
+
+/// Trusted seeds a run used, recorded in the merkle metadata under `seeds`
+#[derive(Debug, Clone, Serialize)]
+pub struct SeedSet {
+    /// Configured seed source
+    pub configured_source: &'static str,
+    /// Seed source the seeds came from, "static" if the configured source had no seeds
+    pub source: &'static str,
+    /// Contract the seeds were read from, for on-chain sources
+    pub contract: Option<String>,
+    pub top_k: Option<usize>,
+    /// Seeds in ascending order
+    pub seeds: Vec<String>,
+}
+
+impl SeedSet {
+    /// Resolve the trusted seeds of a run at the pinned block, falling back to the static seeds
+    /// if the configured source has none. Returns the seed set record and the seeds.
+    pub async fn resolve(
+        seed_source: &SeedSource,
+        static_seeds: &HashSet<Address>,
+        config: &MerklerConfig,
+        block: &PinnedBlock,
+        previous_snapshot: Option<&PreviousSnapshot>,
+    ) -> Result<(Self, HashSet<Address>), String> {
+        let (contract, top_k) = match seed_source {
+            SeedSource::Static => (None, None),
+            SeedSource::SafeSigners(contract) | SeedSource::Registry(contract) => {
+                (Some(*contract), None)
+            }
+            SeedSource::PreviousTopK(k) => (None, Some(*k)),
+        };
+
+        let loaded = match seed_source {
+            SeedSource::Static => None,
+            SeedSource::SafeSigners(module) => Some(
+                read_addresses(config, *module, ISignerSyncManagerModule::getSignersCall {}, block)
+                    .await?,
+            ),
+            SeedSource::Registry(registry) => Some(
+                read_addresses(
+                    config,
+                    *registry,
+                    ITrustSeedRegistry::getTrustedSeedsCall {},
+                    block,
+                )
+                .await?,
+            ),
+            SeedSource::PreviousTopK(k) => {
+                previous_snapshot.map(|snapshot| top_k_seeds(snapshot, *k))
+            }
+        };
+
+        let (source, seeds) = match loaded.filter(|seeds| !seeds.is_empty()) {
+            Some(seeds) => {
+                println!("🌱 Loaded {} trusted seeds from {}", seeds.len(), seed_source.name());
+                (seed_source.name(), seeds.into_iter().collect::<HashSet<_>>())
+            }
+            None => {
+                if *seed_source != SeedSource::Static {
+                    println!(
+                        "⚠️  No trusted seeds from {}, falling back to {} static seeds",
+                        seed_source.name(),
+                        static_seeds.len()
+                    );
+                }
+                ("static", static_seeds.clone())
+            }
+        };
+
+        let mut sorted_seeds: Vec<Address> = seeds.iter().copied().collect();
+        sorted_seeds.sort();
+
+        Ok((
+            Self {
+                configured_source: seed_source.name(),
+                source,
+                contract: contract.map(|contract| contract.to_string()),
+                top_k,
+                seeds: sorted_seeds.iter().map(|seed| seed.to_string()).collect(),
+            },
+            seeds,
+        ))
+    }
+}
+
+/// The seeds of the `previous_top_k` source: the `k` accounts with the highest values in the
+/// previous snapshot, ties broken by address. Seeds raise their own values, so the top accounts
+/// reinforce themselves, and any reshuffle around rank `k` would change the seeds, and with them
+/// the inputs of every run. To damp that, seeds of the previous snapshot stay seeds as long as
+/// they remain in the top `2k`, and only the free slots are filled by rank.
+fn top_k_seeds(snapshot: &PreviousSnapshot, k: usize) -> Vec<Address> {
+    let mut ranked: Vec<_> = snapshot.values.iter().filter(|(_, value)| !value.is_zero()).collect();
+    ranked.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+    let ranked: Vec<Address> = ranked.into_iter().map(|(account, _)| *account).collect();
+
+    let previous_seeds = previous_top_k_seeds(snapshot);
+    let mut seeds: Vec<Address> = ranked
+        .iter()
+        .take(k.saturating_mul(2))
+        .filter(|account| previous_seeds.contains(*account))
+        .take(k)
+        .copied()
+        .collect();
+    for account in ranked {
+        if seeds.len() >= k {
+            break;
+        }
+        if !seeds.contains(&account) {
+            seeds.push(account);
+        }
+    }
+
+    seeds
+}
+
+/// Seeds a snapshot was computed with, if they came from the `previous_top_k` source.
+fn previous_top_k_seeds(snapshot: &PreviousSnapshot) -> HashSet<Address> {
+    let Some(seeds) = snapshot.metadata.get("seeds") else {
+        return HashSet::new();
+    };
+    if seeds.get("source").and_then(|source| source.as_str())
+        != Some(SeedSource::PreviousTopK(0).name())
+    {
+        return HashSet::new();
+    }
+
+    seeds
+        .get("seeds")
+        .and_then(|seeds| seeds.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|seed| seed.as_str()?.parse().ok())
+        .collect()
+}
+
+/// Call a view function returning an address list at the pinned block.
+async fn read_addresses<C: SolCall<Return = Vec<Address>>>(
+    config: &MerklerConfig,
+    contract: Address,
+    call: C,
+    block: &PinnedBlock,
+) -> Result<Vec<Address>, String> {
+    let provider = new_evm_provider::<Ethereum>(config.http_endpoint.clone());
+
+    let tx = TransactionRequest {
+        to: Some(TxKind::Call(contract)),
+        input: TransactionInput { input: Some(call.abi_encode().into()), data: None },
+        ..Default::default()
+    };
+
+    let result = provider
+        .call(tx)
+        .block(block.block_id())
+        .await
+        .map_err(|e| format!("Failed to call {} on {}: {}", C::SIGNATURE, contract, e))?;
+
+    C::abi_decode_returns(&result)
+        .map_err(|e| format!("Failed to decode {} result: {}", C::SIGNATURE, e))
+}
+
+sol! {
+    interface ISignerSyncManagerModule {
+        function getSigners() external view returns (address[] memory);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wavs_wasi_utils::evm::alloy_primitives::U256;
+
+    fn snapshot(values: &[(u8, u64)], seeds: &[u8], source: &str) -> PreviousSnapshot {
+        let seeds: Vec<String> =
+            seeds.iter().map(|byte| Address::repeat_byte(*byte).to_string()).collect();
+        PreviousSnapshot {
+            root: "0x01".to_string(),
+            cid: "cid".to_string(),
+            values: values
+                .iter()
+                .map(|(byte, value)| (Address::repeat_byte(*byte), U256::from(*value)))
+                .collect(),
+            metadata: json!({ "seeds": { "source": source, "seeds": seeds } }),
+            published_block: None,
+        }
+    }
+
+    fn addresses(bytes: &[u8]) -> Vec<Address> {
+        bytes.iter().map(|byte| Address::repeat_byte(*byte)).collect()
+    }
+
+    #[test]
+    fn test_top_k_seeds_by_rank() {
+        let snapshot = snapshot(&[(1, 10), (2, 30), (3, 20), (4, 20), (5, 0)], &[], "static");
+
+        assert_eq!(top_k_seeds(&snapshot, 3), addresses(&[2, 3, 4]));
+        assert_eq!(top_k_seeds(&snapshot, 10), addresses(&[2, 3, 4, 1]));
+    }
+
+    #[test]
+    fn test_top_k_seeds_keep_previous_seeds_in_the_top_2k() {
+        let values = [(1, 60), (2, 50), (3, 40), (4, 30), (5, 20), (6, 10)];
+
+        // Seed 4 dropped to rank 4 but stays, seed 5 at rank 5 is replaced
+        let sticky = snapshot(&values, &[4, 5], "previous_top_k");
+        assert_eq!(top_k_seeds(&sticky, 2), addresses(&[4, 1]));
+
+        // Seeds from another source aren't kept
+        let fallback = snapshot(&values, &[4, 5], "static");
+        assert_eq!(top_k_seeds(&fallback, 2), addresses(&[1, 2]));
+    }
+}
@@ -8,3 +8,8 @@ pub use IWavsIndexer::EventIndexed;
 
 sol!("../../src/interfaces/merkle/IMerkler.sol");
 pub use IMerkler::*;
+
+sol!("../../src/interfaces/merkle/IMerkleSnapshot.sol");
+pub use IMerkleSnapshot::MerkleState;
+
+sol!("../../src/interfaces/ITrustSeedRegistry.sol");
@@ -21,21 +21,24 @@ pub fn decode_event_indexed_trigger(action: &TriggerAction) -> Option<solidity::
     }
 }
 
+/// Encodes a merkle root update that expires `expiry_seconds` after the execution time.
 pub async fn encode_trigger_output(
     action: &TriggerAction,
     root: &[u8],
     ipfs_hash: &[u8],
     ipfs_hash_cid: String,
     total_value: U256,
+    expiry_seconds: u64,
 ) -> Result<Vec<u8>, String> {
     let root = serde_json::from_value(root.into()).map_err(|e| e.to_string())?;
     let ipfs_hash = serde_json::from_value(ipfs_hash.into()).map_err(|e| e.to_string())?;
 
-    // Expire in 5 minutes.
-    let expires_at = U256::from(action.execution_timestamp_seconds().await? + 5 * 60);
+    let expires_at = U256::from(action.execution_timestamp_seconds().await? + expiry_seconds);
 
     Ok(solidity::MerklerAvsOutput {
         expiresAt: expires_at,
+        // A non-zero prune makes MerkleSnapshot prune envelopes instead of updating the root, so
+        // root updates never prune. Pruning is handled by the merkler-pruner component.
         prune: U256::ZERO,
         root,
         ipfsHash: ipfs_hash,
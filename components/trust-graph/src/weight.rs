@@ -0,0 +1,160 @@
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use serde::Serialize;
+
+use crate::config::WeightConfig;
+
+/// An attestation whose weight could not be read
+#[derive(Debug, Clone, Serialize)]
+pub struct WeightFailure {
+    pub uid: String,
+    pub attester: String,
+    pub reason: String,
+}
+
+/// Summary of the weights read from the attestations of a run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct WeightReport {
+    pub weight_path: String,
+    pub num_decoded: usize,
+    pub num_failed: usize,
+    /// Edges of older attestations removed because a newer attestation of the pair failed
+    pub num_removed_edges: usize,
+    pub failures: Vec<WeightFailure>,
+}
+
+/// Read the weight of an attestation from its schema data, as configured.
+pub fn extract_weight(
+    config: &WeightConfig,
+    schema_abi: &DynSolType,
+    data: &[u8],
+) -> Result<f64, String> {
+    let decoded = schema_abi
+        .abi_decode_params(data)
+        .map_err(|e| format!("failed to decode attestation data: {}", e))?;
+
+    let mut field = &decoded;
+    for (depth, index) in config.path.iter().enumerate() {
+        let fields = match field {
+            DynSolValue::Tuple(fields)
+            | DynSolValue::FixedArray(fields)
+            | DynSolValue::Array(fields) => fields,
+            _ => {
+                return Err(format!(
+                    "field at {} is not a tuple or array",
+                    path_prefix(&config.path[..depth])
+                ))
+            }
+        };
+        field = fields.get(*index).ok_or_else(|| {
+            format!("index {} not found in attestation data", path_prefix(&config.path[..=depth]))
+        })?;
+    }
+
+    match field {
+        DynSolValue::Uint(value, _) => match &config.enum_weights {
+            Some(table) => lookup(table, &value.to_string()),
+            None => value
+                .to_string()
+                .parse::<f64>()
+                .map_err(|e| format!("failed to convert {} to f64: {}", value, e)),
+        },
+        DynSolValue::Int(value, _) => match &config.enum_weights {
+            Some(table) => lookup(table, &value.to_string()),
+            None => value
+                .to_string()
+                .parse::<f64>()
+                .map_err(|e| format!("failed to convert {} to f64: {}", value, e)),
+        },
+        DynSolValue::Bool(value) => Ok(if *value { config.bool_weight } else { 0.0 }),
+        DynSolValue::String(value) => match &config.string_weights {
+            Some(table) => lookup(table, value),
+            None => Err("string field without a string weight table".to_string()),
+        },
+        other => Err(format!("field of type {:?} has no weight", other.as_type())),
+    }
+}
+
+fn lookup(table: &std::collections::HashMap<String, f64>, key: &str) -> Result<f64, String> {
+    table.get(key).copied().ok_or_else(|| format!("value '{}' not in the weight table", key))
+}
+
+fn path_prefix(path: &[usize]) -> String {
+    if path.is_empty() {
+        return "root".to_string();
+    }
+    path.iter().map(|index| index.to_string()).collect::<Vec<_>>().join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_dyn_abi::DynSolType;
+    use std::collections::HashMap;
+    use wavs_wasi_utils::evm::alloy_primitives::{Address, I256, U256};
+
+    fn config(path: &[usize]) -> WeightConfig {
+        WeightConfig {
+            path: path.to_vec(),
+            bool_weight: 100.0,
+            enum_weights: None,
+            string_weights: None,
+        }
+    }
+
+    fn encode(value: DynSolValue) -> Vec<u8> {
+        value.abi_encode_params()
+    }
+
+    #[test]
+    fn test_extract_weight_of_numbers_and_bools() {
+        let schema: DynSolType = "(address,uint8,(bool,int256))".parse().unwrap();
+        let data = encode(DynSolValue::Tuple(vec![
+            DynSolValue::Address(Address::ZERO),
+            DynSolValue::Uint(U256::from(42), 8),
+            DynSolValue::Tuple(vec![
+                DynSolValue::Bool(true),
+                DynSolValue::Int(I256::try_from(-7).unwrap(), 256),
+            ]),
+        ]));
+
+        assert_eq!(extract_weight(&config(&[1]), &schema, &data), Ok(42.0));
+        assert_eq!(extract_weight(&config(&[2, 0]), &schema, &data), Ok(100.0));
+        assert_eq!(extract_weight(&config(&[2, 1]), &schema, &data), Ok(-7.0));
+    }
+
+    #[test]
+    fn test_extract_weight_from_tables() {
+        let schema: DynSolType = "(uint8,string)".parse().unwrap();
+        let data = encode(DynSolValue::Tuple(vec![
+            DynSolValue::Uint(U256::from(1), 8),
+            DynSolValue::String("high".to_string()),
+        ]));
+
+        let mut enums = config(&[0]);
+        enums.enum_weights = Some(HashMap::from([("1".to_string(), 50.0)]));
+        assert_eq!(extract_weight(&enums, &schema, &data), Ok(50.0));
+        enums.enum_weights = Some(HashMap::from([("0".to_string(), 50.0)]));
+        assert!(extract_weight(&enums, &schema, &data).unwrap_err().contains("not in the weight"));
+
+        let mut strings = config(&[1]);
+        assert!(extract_weight(&strings, &schema, &data).is_err());
+        strings.string_weights = Some(HashMap::from([("high".to_string(), 80.0)]));
+        assert_eq!(extract_weight(&strings, &schema, &data), Ok(80.0));
+    }
+
+    #[test]
+    fn test_extract_weight_failures() {
+        let schema: DynSolType = "(uint256,address)".parse().unwrap();
+        let data = encode(DynSolValue::Tuple(vec![
+            DynSolValue::Uint(U256::from(1), 256),
+            DynSolValue::Address(Address::ZERO),
+        ]));
+
+        assert!(extract_weight(&config(&[0]), &schema, &data[..32]).is_err());
+        assert!(extract_weight(&config(&[1]), &schema, &data).unwrap_err().contains("no weight"));
+        assert!(extract_weight(&config(&[2]), &schema, &data).unwrap_err().contains("not found"));
+        assert!(extract_weight(&config(&[0, 0]), &schema, &data)
+            .unwrap_err()
+            .contains("not a tuple"));
+    }
+}
@@ -0,0 +1,555 @@
+use crate::bindings::host::{self, get_evm_chain_config};
+use crate::bindings::wavs::types::chain::EvmEventLogData;
+use crate::idempotency::IndexedEvents;
+use crate::reorg::{BlockHashes, SourceBlock};
+use crate::solidity::{IndexedEvent, IndexingPayload};
+use crate::transformers::attestation::ATTESTATION_EVENT_TYPE;
+use crate::transformers::{
+    add_source, error_event, indexer_querier, is_transport_error, log_chain_id, ErrorPolicy,
+    TransformerRegistry,
+};
+use crate::trigger::EventData;
+use alloy_network::Ethereum;
+use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{BlockId, BlockNumberOrTag, Filter, Log};
+use alloy_sol_types::SolValue;
+use anyhow::Result;
+use serde::Serialize;
+use std::str::FromStr;
+use wavs_indexer_api::query::WavsIndexerQuerier;
+use wavs_wasi_utils::evm::new_evm_provider;
+
+/// Event type of the checkpoints recording the last block a batch indexed
+pub const CHECKPOINT_EVENT_TYPE: &str = "indexer_checkpoint";
+
+/// Transformer named in the `indexing_error` events of logs a batch couldn't index
+const FAILED_LOG_TRANSFORMER: &str = "TransformerRegistry";
+
+/// Number of latest checkpoints searched for one that wasn't deleted by a reorg
+const CHECKPOINT_PAGE_SIZE: u64 = 10;
+
+/// How a batch workflow indexes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchMode {
+    /// Follow the chain, indexing new logs as blocks come in
+    Live,
+    /// Index a historical block range once, skipping logs that are already indexed
+    Backfill,
+    /// Delete recently indexed events whose block was reorged out, see [`crate::reorg`]
+    Reconcile,
+}
+
+impl BatchMode {
+    pub fn load() -> Result<Self> {
+        match host::config_var("batch_mode").as_deref().unwrap_or("live") {
+            "live" => Ok(Self::Live),
+            "backfill" => Ok(Self::Backfill),
+            "reconcile" => Ok(Self::Reconcile),
+            other => Err(anyhow::anyhow!("Invalid batch_mode: {}", other)),
+        }
+    }
+}
+
+/// Batch indexing configuration, read from the `batch_*` config variables
+pub struct BatchConfig {
+    pub mode: BatchMode,
+    /// Name of the batch, so several batch workflows can share an indexer
+    pub id: String,
+    /// Contracts whose logs are indexed
+    pub contracts: Vec<Address>,
+    /// First block indexed when there is no checkpoint yet
+    pub from_block: u64,
+    /// Last block indexed, required for a backfill
+    pub to_block: Option<u64>,
+    /// Maximum number of blocks scanned per trigger
+    pub max_block_range: u64,
+    /// Maximum number of blocks per `eth_getLogs` request
+    pub logs_chunk_size: u64,
+    /// Maximum number of events added or deleted per payload
+    pub max_events: usize,
+    /// Maximum ABI-encoded size of the events added per payload
+    pub max_payload_bytes: usize,
+}
+
+impl BatchConfig {
+    pub fn load() -> Result<Self> {
+        let mode = BatchMode::load()?;
+        if mode == BatchMode::Reconcile {
+            return Err(anyhow::anyhow!("Reconcile workflows don't index logs"));
+        }
+
+        let contracts = host::config_var("batch_contracts")
+            .ok_or(anyhow::anyhow!("batch_contracts is required for batch indexing"))?
+            .split(',')
+            .map(|address| address.trim())
+            .filter(|address| !address.is_empty())
+            .map(|address| {
+                Address::from_str(address).map_err(|e| {
+                    anyhow::anyhow!("Invalid address {} in batch_contracts: {}", address, e)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if contracts.is_empty() {
+            return Err(anyhow::anyhow!("batch_contracts is empty"));
+        }
+
+        let config = Self {
+            mode,
+            id: host::config_var("batch_id").unwrap_or_else(|| match mode {
+                BatchMode::Backfill => "backfill".to_string(),
+                _ => "default".to_string(),
+            }),
+            contracts,
+            from_block: parse_config_var("batch_from_block")?.unwrap_or(0),
+            to_block: parse_config_var("batch_to_block")?,
+            max_block_range: parse_config_var("batch_max_block_range")?.unwrap_or(10_000),
+            logs_chunk_size: parse_config_var("batch_logs_chunk_size")?.unwrap_or(2_000),
+            max_events: parse_config_var("batch_max_events")?.unwrap_or(100),
+            max_payload_bytes: parse_config_var("batch_max_payload_bytes")?.unwrap_or(100_000),
+        };
+        if config.max_block_range == 0
+            || config.logs_chunk_size == 0
+            || config.max_events == 0
+            || config.max_payload_bytes == 0
+        {
+            return Err(anyhow::anyhow!("Batch size limits must be greater than 0"));
+        }
+        if config.mode == BatchMode::Backfill && config.to_block.is_none() {
+            return Err(anyhow::anyhow!("batch_to_block is required for a backfill"));
+        }
+
+        Ok(config)
+    }
+
+    fn checkpoint_tag(&self, chain_id: &str) -> String {
+        format!("batch:{}/chain:{}", self.id, chain_id)
+    }
+
+    /// Share of a backfill's block range indexed once the checkpoint is at `block`, in percent
+    fn progress(&self, block: u64) -> Option<f64> {
+        let to_block = self.to_block.filter(|_| self.mode == BatchMode::Backfill)?;
+        let total = to_block.saturating_sub(self.from_block) + 1;
+        let done = (block + 1).saturating_sub(self.from_block).min(total);
+        Some(done as f64 * 100.0 / total as f64)
+    }
+}
+
+/// Summary of a batch, stored as JSON in the metadata of its checkpoint
+#[derive(Debug, Clone, Serialize)]
+struct CheckpointMetadata {
+    mode: BatchMode,
+    from_block: u64,
+    to_block: u64,
+    /// Supported logs found in the blocks
+    logs: usize,
+    /// Logs skipped because they were already indexed
+    skipped: usize,
+    /// Logs that failed to index, skipped or recorded as `indexing_error` events
+    failed: usize,
+    /// Share of the backfill range indexed, in percent
+    #[serde(skip_serializing_if = "Option::is_none")]
+    progress: Option<f64>,
+    /// Last block of the batch, so a reorg of it can be detected and the blocks indexed again
+    #[serde(skip_serializing_if = "Option::is_none")]
+    block: Option<SourceBlock>,
+}
+
+/// Events of one block, which are always indexed together so a checkpoint never splits a block
+struct BlockBatch {
+    block_number: u64,
+    payload: IndexingPayload,
+    logs: usize,
+    skipped: usize,
+    failed: usize,
+}
+
+impl BlockBatch {
+    fn num_events(&self) -> usize {
+        self.payload.toAdd.len() + self.payload.toDelete.len()
+    }
+
+    fn encoded_size(&self) -> usize {
+        self.payload.toAdd.iter().map(|event| event.abi_encoded_size()).sum::<usize>()
+            + self.payload.toDelete.len() * 32
+    }
+}
+
+/// Index every supported log of the configured contracts since the last checkpoint, up to
+/// `block_height`, as a single payload ending with a new checkpoint.
+///
+/// The payload stops at the last whole block that fits the size limits; the next trigger picks
+/// up from there. It also stops before a block with a log no transformer could index, so the
+/// block is retried at the start of the next payload, where its failing logs are recorded as
+/// `indexing_error` events. Returns `None` if there is nothing to index yet.
+pub async fn index_batch(chain: &str, block_height: u64) -> Result<Option<IndexingPayload>> {
+    let config = BatchConfig::load()?;
+    let chain_config = get_evm_chain_config(chain)
+        .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", chain))?;
+    let provider = new_evm_provider::<Ethereum>(
+        chain_config
+            .http_endpoint
+            .ok_or_else(|| anyhow::anyhow!("No HTTP endpoint configured for {}", chain))?,
+    );
+    let querier = indexer_querier(chain).await?;
+
+    let from_block = match last_checkpoint(&querier, &config, &chain_config.chain_id).await? {
+        Some(block) => block + 1,
+        None => config.from_block,
+    };
+    let end_block = block_height.min(config.to_block.unwrap_or(u64::MAX));
+    if from_block > end_block {
+        match config.mode {
+            BatchMode::Backfill => println!("Backfill {} complete", config.id),
+            _ => println!("Batch {} already indexed up to block {}", config.id, block_height),
+        }
+        return Ok(None);
+    }
+    let to_block = end_block.min(from_block + config.max_block_range - 1);
+
+    let logs = get_logs(&provider, &config, from_block, to_block).await?;
+    let registry = TransformerRegistry::all()?;
+    let indexed = IndexedEvents::new(chain)?;
+    let skip_indexed = (config.mode == BatchMode::Backfill).then_some(&querier);
+
+    let mut payload = IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() };
+    let (mut num_events, mut encoded_size) = (0, 0);
+    let mut metadata = CheckpointMetadata {
+        mode: config.mode,
+        from_block,
+        to_block,
+        logs: 0,
+        skipped: 0,
+        failed: 0,
+        progress: None,
+        block: None,
+    };
+
+    for block_logs in logs.chunk_by(|a, b| a.block_number == b.block_number) {
+        let block_number = block_logs[0].block_number.unwrap_or_default();
+        let Some(block) = transform_block(
+            &registry,
+            &indexed,
+            &provider,
+            skip_indexed,
+            chain,
+            block_number,
+            block_logs,
+            num_events == 0,
+        )
+        .await?
+        else {
+            metadata.to_block = block_number - 1;
+            break;
+        };
+
+        if num_events > 0
+            && (num_events + block.num_events() > config.max_events
+                || encoded_size + block.encoded_size() > config.max_payload_bytes)
+        {
+            println!("Batch {}: size limit reached at block {}", config.id, block.block_number);
+            metadata.to_block = block.block_number - 1;
+            break;
+        }
+
+        num_events += block.num_events();
+        encoded_size += block.encoded_size();
+        metadata.logs += block.logs;
+        metadata.skipped += block.skipped;
+        metadata.failed += block.failed;
+        payload.toAdd.extend(block.payload.toAdd);
+        payload.toDelete.extend(block.payload.toDelete);
+    }
+
+    // Without events there is nothing worth a transaction, unless the scan was capped and the
+    // checkpoint has to move on
+    if num_events == 0 && to_block == block_height {
+        println!("Batch {}: nothing to index up to block {}", config.id, block_height);
+        return Ok(None);
+    }
+
+    metadata.progress = config.progress(metadata.to_block);
+    println!(
+        "Batch {}: indexing blocks {} to {}, adding {} and deleting {} ({} logs, {} skipped, {} failed)",
+        config.id,
+        metadata.from_block,
+        metadata.to_block,
+        payload.toAdd.len(),
+        payload.toDelete.len(),
+        metadata.logs,
+        metadata.skipped,
+        metadata.failed
+    );
+    if let Some(progress) = metadata.progress {
+        println!(
+            "Backfill {}: {:.2}% of blocks {} to {} indexed",
+            config.id, progress, config.from_block, end_block
+        );
+    }
+
+    metadata.block = provider
+        .block_hash(metadata.to_block)
+        .await?
+        .map(|hash| SourceBlock { number: metadata.to_block, hash });
+    payload.toAdd.push(checkpoint_event(&config, &chain_config.chain_id, &metadata)?);
+    Ok(Some(payload))
+}
+
+/// Fetch the logs of the configured contracts in chunks of `eth_getLogs` requests
+async fn get_logs(
+    provider: &RootProvider<Ethereum>,
+    config: &BatchConfig,
+    from_block: u64,
+    to_block: u64,
+) -> Result<Vec<Log>> {
+    let mut logs = Vec::new();
+    for start in (from_block..=to_block).step_by(config.logs_chunk_size as usize) {
+        let end = to_block.min(start + config.logs_chunk_size - 1);
+        let filter =
+            Filter::new().address(config.contracts.clone()).from_block(start).to_block(end);
+        let chunk = provider
+            .get_logs(&filter)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get logs: {}", e))?;
+
+        println!("Batch {}: found {} logs from block {} to {}", config.id, chunk.len(), start, end);
+        logs.extend(chunk);
+    }
+
+    Ok(logs)
+}
+
+/// Transform the supported logs of a block. Returns `None` if an event could not be indexed and
+/// the block should be retried, unless no earlier block of the batch has events: then an
+/// `indexing_error` event records the failure, so the checkpoint never passes a log silently and
+/// the batch can't get stuck on it.
+///
+/// Transformers see the events of the earlier logs of the batch, so revisions of events that
+/// aren't on chain yet are resolved in memory.
+///
+/// Logs that are already indexed are left out, so a block range can be indexed again safely.
+/// With `skip_indexed`, they are recognized by their `tx:<hash>/log:<index>` tag before being
+/// transformed, which saves the transformers' calls on ranges that are mostly indexed, and
+/// attestations also by their `uid:` tag, since older events lack the log tag.
+async fn transform_block(
+    registry: &TransformerRegistry,
+    indexed: &IndexedEvents,
+    provider: &RootProvider<Ethereum>,
+    skip_indexed: Option<&WavsIndexerQuerier>,
+    chain: &str,
+    block_number: u64,
+    logs: &[Log],
+    batch_empty: bool,
+) -> Result<Option<BlockBatch>> {
+    let mut batch = BlockBatch {
+        block_number,
+        payload: IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() },
+        logs: 0,
+        skipped: 0,
+        failed: 0,
+    };
+    let mut cached_timestamp = None;
+
+    for log in logs {
+        let Some(event_signature) = log.topic0().filter(|sig| registry.supports_event(sig)) else {
+            continue;
+        };
+        batch.logs += 1;
+
+        let block_timestamp = match (cached_timestamp, log.block_timestamp) {
+            (Some(timestamp), _) | (None, Some(timestamp)) => timestamp,
+            (None, None) => get_block_timestamp(provider, block_number).await?,
+        };
+        cached_timestamp = Some(block_timestamp);
+        let event_data = EventData {
+            contract_address: log.address(),
+            log: EvmEventLogData {
+                topics: log.topics().iter().map(|topic| topic.to_vec()).collect(),
+                data: log.data().data.to_vec(),
+            },
+            block_number,
+            block_timestamp,
+            chain: chain.to_string(),
+            tx_hash: log.transaction_hash.unwrap_or_default(),
+            log_index: log.log_index.unwrap_or_default(),
+            block_hash: log.block_hash.unwrap_or_default(),
+            pending: indexed.pending(),
+        };
+
+        if let Some(querier) = skip_indexed {
+            let count = querier.getEventCountByTag(event_data.source_tag()).call().await?;
+            if !count.is_zero() {
+                batch.skipped += 1;
+                continue;
+            }
+        }
+
+        // A block that fails after others is retried by the next batch. A block failing first
+        // would fail every batch, so a failure that isn't the node's is handled by the error
+        // policy instead, while a node failure fails the trigger so it is retried.
+        let p = match registry.transform_event(event_signature, event_data.clone()).await {
+            Ok(p) => p,
+            Err(e) if !batch_empty => {
+                println!("Retrying block {} in the next batch: {}", block_number, e);
+                return Ok(None);
+            }
+            Err(e)
+                if is_transport_error(&e) || registry.error_policy() == ErrorPolicy::FailFast =>
+            {
+                return Err(anyhow::anyhow!("Failed to index block {}: {}", block_number, e));
+            }
+            Err(e) if registry.error_policy() == ErrorPolicy::BestEffort => {
+                println!(
+                    "Skipping log {} of block {}: {}",
+                    event_data.source_tag(),
+                    block_number,
+                    e
+                );
+                batch.failed += 1;
+                continue;
+            }
+            Err(e) => {
+                println!(
+                    "Recording the failure of log {} of block {}: {}",
+                    event_data.source_tag(),
+                    block_number,
+                    e
+                );
+                batch.failed += 1;
+                failure_payload(&e, event_signature, &event_data)?
+            }
+        };
+
+        if let Some(querier) = skip_indexed {
+            if let Some(uid_tag) = indexed_attestation(querier, &p).await? {
+                println!("Attestation {} is already indexed, skipping its log", uid_tag);
+                batch.skipped += 1;
+                continue;
+            }
+        }
+
+        let p = indexed.skip_indexed(p, &event_data).await?;
+        if p.toAdd.is_empty() && p.toDelete.is_empty() {
+            batch.skipped += 1;
+        }
+        batch.payload.toAdd.extend(p.toAdd);
+        batch.payload.toDelete.extend(p.toDelete);
+    }
+
+    Ok(Some(batch))
+}
+
+/// `uid:` tag of an attestation the payload adds that is already indexed and not deleted, under
+/// any id. Attestations indexed before events were tagged with their log and got UID-derived ids
+/// are only recognized this way.
+async fn indexed_attestation(
+    querier: &WavsIndexerQuerier,
+    payload: &IndexingPayload,
+) -> Result<Option<String>> {
+    let attestations =
+        payload.toAdd.iter().filter(|event| event.eventType == ATTESTATION_EVENT_TYPE);
+    for event in attestations {
+        let Some(uid_tag) = event.tags.iter().find(|tag| tag.starts_with("uid:")) else {
+            continue;
+        };
+        let indexed = querier
+            .getEventsByTypeAndTag(
+                ATTESTATION_EVENT_TYPE.to_string(),
+                uid_tag.clone(),
+                U256::ZERO,
+                U256::ONE,
+                true,
+            )
+            .call()
+            .await?;
+        if indexed.first().is_some_and(|event| !event.deleted) {
+            return Ok(Some(uid_tag.clone()));
+        }
+    }
+
+    Ok(None)
+}
+
+/// `indexing_error` event recording a log that failed to index, with the source block so a reorg
+/// of it is detected. It isn't tagged with the log, so a backfill doesn't skip the log.
+fn failure_payload(
+    error: &anyhow::Error,
+    event_signature: &FixedBytes<32>,
+    event_data: &EventData,
+) -> Result<IndexingPayload> {
+    let chain_id = log_chain_id(&event_data.chain);
+    let mut events =
+        vec![error_event(&chain_id, FAILED_LOG_TRANSFORMER, error, event_signature, event_data)?];
+    add_source(&mut events, event_data)?;
+
+    Ok(IndexingPayload { toAdd: events, toDelete: Vec::new() })
+}
+
+/// Last block indexed by the batch, from its latest checkpoint that wasn't deleted by a reorg
+async fn last_checkpoint(
+    querier: &WavsIndexerQuerier,
+    config: &BatchConfig,
+    chain_id: &str,
+) -> Result<Option<u64>> {
+    let checkpoints = querier
+        .getEventsByTypeAndTag(
+            CHECKPOINT_EVENT_TYPE.to_string(),
+            config.checkpoint_tag(chain_id),
+            U256::ZERO,
+            U256::from(CHECKPOINT_PAGE_SIZE),
+            true,
+        )
+        .call()
+        .await?;
+
+    Ok(checkpoints
+        .iter()
+        .find(|checkpoint| !checkpoint.deleted)
+        .map(|checkpoint| checkpoint.blockNumber.to::<u64>()))
+}
+
+fn checkpoint_event(
+    config: &BatchConfig,
+    chain_id: &str,
+    metadata: &CheckpointMetadata,
+) -> Result<IndexedEvent> {
+    Ok(IndexedEvent {
+        eventId: FixedBytes::ZERO,
+        chainId: chain_id.to_string(),
+        relevantContract: Address::ZERO,
+        blockNumber: U256::from(metadata.to_block),
+        timestamp: 0,
+        eventType: CHECKPOINT_EVENT_TYPE.to_string(),
+        tags: vec![
+            format!("batch:{}", config.id),
+            format!("chain:{}", chain_id),
+            config.checkpoint_tag(chain_id),
+        ],
+        relevantAddresses: config.contracts.clone(),
+        data: (U256::from(metadata.from_block), U256::from(metadata.to_block))
+            .abi_encode_params()
+            .into(),
+        metadata: serde_json::to_vec(metadata)?.into(),
+        deleted: false,
+    })
+}
+
+async fn get_block_timestamp(provider: &RootProvider<Ethereum>, block_number: u64) -> Result<u64> {
+    let block = provider
+        .get_block(BlockId::Number(BlockNumberOrTag::Number(block_number)))
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to get block {}: {}", block_number, e))?
+        .ok_or_else(|| anyhow::anyhow!("Block {} not found", block_number))?;
+
+    Ok(block.header.timestamp)
+}
+
+fn parse_config_var<T: FromStr>(key: &str) -> Result<Option<T>>
+where
+    T::Err: std::fmt::Display,
+{
+    host::config_var(key)
+        .map(|value| value.parse().map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", key, e)))
+        .transpose()
+}
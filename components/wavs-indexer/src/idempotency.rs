@@ -0,0 +1,279 @@
+use crate::bindings::host::get_evm_chain_config;
+use crate::reorg::SourceBlock;
+use crate::solidity::{IWavsIndexerEvents, IndexedEvent, IndexingPayload};
+use crate::transformers::{indexer_address, PendingEvents};
+use crate::trigger::EventData;
+use alloy_network::Ethereum;
+use alloy_primitives::{keccak256, Bytes, FixedBytes, U256};
+use alloy_provider::RootProvider;
+use alloy_sol_types::SolValue;
+use anyhow::Result;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wavs_indexer_api::query::WavsIndexerQuerier;
+use wavs_wasi_utils::evm::new_evm_provider;
+
+/// Event type of the markers of deletions whose event wasn't indexed yet
+pub const DEFERRED_DELETION_EVENT_TYPE: &str = "deferred_deletion";
+
+/// Number of deferred deletion markers fetched per indexer call
+const MARKER_PAGE_SIZE: u64 = 100;
+
+/// Existence checks against the WAVS indexer, so indexing the same log twice is a no-op
+pub struct IndexedEvents {
+    indexer: IWavsIndexerEvents::IWavsIndexerEventsInstance<RootProvider<Ethereum>>,
+    chain_id: String,
+    /// Events added and deleted by the payloads checked so far. Later payloads of a batch may
+    /// delete the added events, and must not delete the deleted ones twice.
+    pending: RefCell<PendingEvents>,
+}
+
+impl IndexedEvents {
+    pub fn new(chain: &str) -> Result<Self> {
+        let chain_config = get_evm_chain_config(chain)
+            .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", chain))?;
+        let provider = new_evm_provider::<Ethereum>(
+            chain_config
+                .http_endpoint
+                .ok_or_else(|| anyhow::anyhow!("No HTTP endpoint configured for {}", chain))?,
+        );
+
+        Ok(Self {
+            indexer: IWavsIndexerEvents::new(indexer_address()?, provider),
+            chain_id: chain_config.chain_id,
+            pending: RefCell::new(PendingEvents::default()),
+        })
+    }
+
+    /// Drop the updates of a log that are already on chain. If any event the log adds exists,
+    /// the log was indexed before and its whole payload is dropped, since its deletions were
+    /// applied too. Otherwise deletions of events that are already deleted, on chain or earlier in
+    /// the batch, are dropped, and deletions of events that aren't indexed yet are deferred: they
+    /// are replaced by a `deferred_deletion` marker. Deferred deletions of the events the log adds
+    /// are applied with them, and [`IndexedEvents::retry_deferred_deletions`] applies the others.
+    pub async fn skip_indexed(
+        &self,
+        payload: IndexingPayload,
+        event_data: &EventData,
+    ) -> Result<IndexingPayload> {
+        for event in &payload.toAdd {
+            if !event.eventId.is_zero() && self.indexer.eventExists(event.eventId).call().await? {
+                println!("Event {} is already indexed, skipping its log", event.eventId);
+                return Ok(IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() });
+            }
+        }
+
+        let mut to_add = payload.toAdd;
+        let mut to_delete = Vec::new();
+        for event_id in payload.toDelete {
+            if to_delete.contains(&event_id) || self.pending.borrow().is_deleted(&event_id) {
+                println!("Event {} is already deleted in this batch", event_id);
+                continue;
+            }
+
+            // The contract adds a payload's events before deleting, so events added earlier in
+            // the payload can be deleted
+            let pending = to_add.iter().any(|event| event.eventId == event_id)
+                || self.pending.borrow().is_added(&event_id);
+            if pending || self.indexer.eventExists(event_id).call().await? {
+                if !pending && self.indexer.eventExistsAndDeleted(event_id).call().await? {
+                    println!("Event {} is already deleted", event_id);
+                } else {
+                    to_delete.push(event_id);
+                }
+                continue;
+            }
+
+            println!("Event {} is not indexed yet, deferring its deletion", event_id);
+            let marker = deferred_deletion_event(&self.chain_id, event_id, event_data)?;
+            let deferred = self.pending.borrow().is_added(&marker.eventId);
+            if !deferred && !self.indexer.eventExists(marker.eventId).call().await? {
+                to_add.push(marker);
+            }
+        }
+
+        // The contract adds a payload's events before deleting, so deletions deferred until an
+        // event the payload adds is indexed can be applied right away
+        let added: Vec<FixedBytes<32>> = to_add
+            .iter()
+            .filter(|event| event.eventType != DEFERRED_DELETION_EVENT_TYPE)
+            .map(|event| event.eventId)
+            .filter(|event_id| !event_id.is_zero())
+            .collect();
+        for event_id in added {
+            let marker_id = deferred_deletion_id(&self.chain_id, event_id);
+            if to_delete.contains(&marker_id) || !self.is_live(marker_id).await? {
+                continue;
+            }
+
+            println!("Applying deferred deletion of event {}", event_id);
+            if !to_delete.contains(&event_id) {
+                to_delete.push(event_id);
+            }
+            to_delete.push(marker_id);
+        }
+
+        let mut pending = self.pending.borrow_mut();
+        pending.added.extend(to_add.iter().cloned());
+        pending.deleted.extend(to_delete.iter().copied());
+        Ok(IndexingPayload { toAdd: to_add, toDelete: to_delete })
+    }
+
+    /// Whether an event is indexed, on chain or earlier in the batch, and not deleted
+    pub async fn is_live(&self, event_id: FixedBytes<32>) -> Result<bool> {
+        if self.pending.borrow().is_deleted(&event_id) {
+            return Ok(false);
+        }
+        if self.pending.borrow().is_added(&event_id) {
+            return Ok(true);
+        }
+
+        Ok(self.indexer.eventExists(event_id).call().await?
+            && !self.indexer.eventExistsAndDeleted(event_id).call().await?)
+    }
+
+    /// Snapshot of the events added and deleted by the payloads checked so far, for the
+    /// transformers of the next log of a batch
+    pub fn pending(&self) -> Rc<PendingEvents> {
+        Rc::new(self.pending.borrow().clone())
+    }
+
+    /// Deferred deletions whose event is indexed by now, followed by their markers, for the
+    /// latest `max_markers` markers of the chain that aren't deleted yet. Markers from blocks
+    /// before `min_block` are expired and deleted without checking their event. Markers are
+    /// walked from the newest, up to the first expired one that is deleted already, since the
+    /// older ones were expired by an earlier run.
+    pub async fn retry_deferred_deletions(
+        &self,
+        querier: &WavsIndexerQuerier,
+        max_markers: u64,
+        min_block: u64,
+    ) -> Result<Vec<FixedBytes<32>>> {
+        let tag = format!("chain:{}", self.chain_id);
+        let count = querier
+            .getEventCountByTypeAndTag(DEFERRED_DELETION_EVENT_TYPE.to_string(), tag.clone())
+            .call()
+            .await?
+            .to::<u64>();
+
+        // Applied markers are deleted, so they are skipped rather than counted
+        let (mut to_delete, mut checked) = (Vec::new(), 0);
+        for start in (0..count).step_by(MARKER_PAGE_SIZE as usize) {
+            let markers = querier
+                .getEventsByTypeAndTag(
+                    DEFERRED_DELETION_EVENT_TYPE.to_string(),
+                    tag.clone(),
+                    U256::from(start),
+                    U256::from(MARKER_PAGE_SIZE.min(count - start)),
+                    true,
+                )
+                .call()
+                .await?;
+
+            for marker in markers {
+                let block_number = marker.blockNumber.saturating_to::<u64>();
+                match marker_action(block_number, marker.deleted, min_block) {
+                    MarkerAction::Stop => return Ok(to_delete),
+                    MarkerAction::Skip => continue,
+                    MarkerAction::Expire => {
+                        println!("Deferred deletion marker {} expired", marker.eventId);
+                        to_delete.push(marker.eventId);
+                        continue;
+                    }
+                    // The expired markers are still deleted once enough markers are checked
+                    MarkerAction::Check if checked == max_markers => continue,
+                    MarkerAction::Check => checked += 1,
+                }
+
+                let event_id = FixedBytes::<32>::try_from(marker.data.as_ref())
+                    .map_err(|_| anyhow::anyhow!("Invalid deferred deletion {}", marker.eventId))?;
+                if !self.indexer.eventExists(event_id).call().await? {
+                    continue;
+                }
+
+                if self.indexer.eventExistsAndDeleted(event_id).call().await? {
+                    println!("Deferred deletion of event {} is obsolete", event_id);
+                } else {
+                    println!("Applying deferred deletion of event {}", event_id);
+                    to_delete.push(event_id);
+                }
+                to_delete.push(marker.eventId);
+            }
+        }
+
+        Ok(to_delete)
+    }
+}
+
+/// What [`IndexedEvents::retry_deferred_deletions`] does with a marker
+#[derive(Debug, PartialEq)]
+enum MarkerAction {
+    /// Check whether the marker's event is indexed by now
+    Check,
+    /// Delete the marker, which is too old for its event to be indexed
+    Expire,
+    /// Skip the marker, which is deleted already
+    Skip,
+    /// Stop at the marker, since the older markers were expired by an earlier run
+    Stop,
+}
+
+fn marker_action(block_number: u64, deleted: bool, min_block: u64) -> MarkerAction {
+    match (block_number < min_block, deleted) {
+        (false, false) => MarkerAction::Check,
+        (false, true) => MarkerAction::Skip,
+        (true, false) => MarkerAction::Expire,
+        (true, true) => MarkerAction::Stop,
+    }
+}
+
+/// Id of the marker of a deferred deletion, derived from the deleted event so deferring the
+/// deletion again is detected
+fn deferred_deletion_id(chain_id: &str, event_id: FixedBytes<32>) -> FixedBytes<32> {
+    keccak256(
+        (DEFERRED_DELETION_EVENT_TYPE.to_string(), chain_id.to_string(), event_id)
+            .abi_encode_params(),
+    )
+}
+
+/// Marker of the deletion of an event that isn't indexed yet, tagged `event:<id>`
+fn deferred_deletion_event(
+    chain_id: &str,
+    event_id: FixedBytes<32>,
+    event_data: &EventData,
+) -> Result<IndexedEvent> {
+    let source_block = SourceBlock { number: event_data.block_number, hash: event_data.block_hash };
+
+    Ok(IndexedEvent {
+        eventId: deferred_deletion_id(chain_id, event_id),
+        chainId: chain_id.to_string(),
+        relevantContract: event_data.contract_address,
+        blockNumber: U256::from(event_data.block_number),
+        timestamp: event_data.block_timestamp as u128,
+        eventType: DEFERRED_DELETION_EVENT_TYPE.to_string(),
+        tags: vec![
+            format!("chain:{}", chain_id),
+            format!("event:{}", event_id),
+            event_data.source_tag(),
+        ],
+        relevantAddresses: Vec::new(),
+        data: Bytes::copy_from_slice(event_id.as_slice()),
+        metadata: source_block.add_to_metadata(&Bytes::new())?,
+        deleted: false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markers_expire_by_block_age() {
+        assert_eq!(marker_action(100, false, 50), MarkerAction::Check);
+        assert_eq!(marker_action(50, true, 50), MarkerAction::Skip);
+        assert_eq!(marker_action(49, false, 50), MarkerAction::Expire);
+        assert_eq!(marker_action(49, true, 50), MarkerAction::Stop);
+        // Nothing expires before the chain is `max_age` blocks long
+        assert_eq!(marker_action(0, true, 0), MarkerAction::Skip);
+    }
+}
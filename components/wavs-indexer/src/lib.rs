@@ -1,13 +1,19 @@
 #[allow(warnings)]
 #[rustfmt::skip]
 mod bindings;
+mod batch;
+mod idempotency;
+mod reorg;
+mod schema;
 mod solidity;
 mod transformers;
 mod trigger;
 
+use crate::batch::BatchMode;
 use crate::bindings::{export, Guest, TriggerAction, WasmResponse};
+use crate::idempotency::IndexedEvents;
 use crate::transformers::TransformerRegistry;
-use crate::trigger::{decode_trigger_event, encode_indexing_output, Destination};
+use crate::trigger::{decode_trigger_event, encode_indexing_output, Destination, TriggerInput};
 
 use anyhow::Result;
 use serde_json;
@@ -21,17 +27,49 @@ impl Guest for Component {
         println!("WAVS Indexer: Processing trigger action");
 
         // Decode the trigger event
-        let (event_data, dest) = block_on(async move { decode_trigger_event(action).await })
+        let (input, dest) = block_on(async move { decode_trigger_event(action).await })
             .map_err(|e| format!("Failed to decode trigger event: {}", e))?;
 
-        println!(
-            "WAVS Indexer: Event from contract {}: {:?}",
-            event_data.contract_address, event_data
-        );
+        let payload = match input {
+            TriggerInput::Event(event_data) => {
+                println!(
+                    "WAVS Indexer: Event from contract {}: {:?}",
+                    event_data.contract_address, event_data
+                );
+
+                // Transform the event into a payload using appropriate transformers
+                let source = event_data.clone();
+                let payload = block_on(async move { process_event(event_data).await })
+                    .map_err(|e| format!("Failed to process event: {}", e))?;
 
-        // Transform the event into a payload using appropriate transformers
-        let payload = block_on(async move { process_event(event_data).await })
-            .map_err(|e| format!("Failed to process event: {}", e))?;
+                // Re-delivery of an already indexed log is a no-op
+                let payload = match &dest {
+                    Destination::Ethereum => block_on(skip_indexed(&source, payload))?,
+                    Destination::CliOutput => payload,
+                };
+                if payload.toAdd.is_empty() && payload.toDelete.is_empty() {
+                    println!("WAVS Indexer: Event already indexed, nothing to submit");
+                    return Ok(None);
+                }
+                payload
+            }
+            TriggerInput::Batch { chain, block_height } => {
+                println!("WAVS Indexer: Batch indexing on {} up to block {}", chain, block_height);
+
+                // Index every supported log since the last checkpoint, or check for reorgs
+                let payload = block_on(async move {
+                    match BatchMode::load()? {
+                        BatchMode::Reconcile => reorg::reconcile(&chain, block_height).await,
+                        _ => batch::index_batch(&chain, block_height).await,
+                    }
+                })
+                .map_err(|e| format!("Failed to index batch: {}", e))?;
+                match payload {
+                    Some(payload) => payload,
+                    None => return Ok(None),
+                }
+            }
+        };
 
         println!(
             "WAVS Indexer: Transformed event, adding {} and deleting {}",
@@ -70,7 +108,21 @@ async fn process_event(
     };
 
     TransformerRegistry::all()
+        .map_err(|e| format!("Failed to load transformers: {}", e))?
         .transform_event(&event_signature, event_data)
         .await
         .map_err(|e| format!("Failed to transform event: {}", e))
 }
+
+/// Drop the updates of an event that is already indexed, and defer deletions of events that
+/// aren't indexed yet
+async fn skip_indexed(
+    event_data: &crate::trigger::EventData,
+    payload: crate::solidity::IndexingPayload,
+) -> Result<crate::solidity::IndexingPayload, String> {
+    IndexedEvents::new(&event_data.chain)
+        .map_err(|e| format!("Failed to check indexed events: {}", e))?
+        .skip_indexed(payload, event_data)
+        .await
+        .map_err(|e| format!("Failed to check indexed events: {}", e))
+}
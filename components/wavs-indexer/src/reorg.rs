@@ -0,0 +1,333 @@
+use crate::bindings::host::{self, get_evm_chain_config};
+use crate::idempotency::IndexedEvents;
+use crate::solidity::{IndexedEvent, IndexingPayload};
+use crate::transformers::indexer_querier;
+use alloy_network::Ethereum;
+use alloy_primitives::{Bytes, FixedBytes, U256};
+use alloy_provider::{Provider, RootProvider};
+use alloy_rpc_types::{BlockId, BlockNumberOrTag};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use wavs_wasi_utils::evm::new_evm_provider;
+
+/// Key of the source block in the JSON metadata of indexed events
+pub const BLOCK_METADATA_KEY: &str = "block";
+
+/// Block an event was indexed from, recorded in its metadata so reorgs can be detected
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceBlock {
+    pub number: u64,
+    pub hash: FixedBytes<32>,
+}
+
+impl SourceBlock {
+    /// Read the source block from the metadata of an indexed event, if it has one
+    pub fn from_metadata(metadata: &[u8]) -> Option<Self> {
+        let metadata: Value = serde_json::from_slice(metadata).ok()?;
+        serde_json::from_value(metadata.get(BLOCK_METADATA_KEY)?.clone()).ok()
+    }
+
+    /// Add the source block to JSON object metadata. Empty metadata becomes an object with just
+    /// the block, and metadata that isn't a JSON object is left as is.
+    pub fn add_to_metadata(&self, metadata: &Bytes) -> Result<Bytes> {
+        let mut object = if metadata.is_empty() {
+            Map::new()
+        } else {
+            match serde_json::from_slice(metadata) {
+                Ok(Value::Object(object)) => object,
+                _ => return Ok(metadata.clone()),
+            }
+        };
+        object.insert(BLOCK_METADATA_KEY.to_string(), serde_json::to_value(self)?);
+
+        Ok(serde_json::to_vec(&object)?.into())
+    }
+}
+
+/// Source of canonical block hashes
+pub trait BlockHashes {
+    /// Hash of the canonical block at a height, or `None` if the chain doesn't reach it
+    async fn block_hash(&self, block_number: u64) -> Result<Option<FixedBytes<32>>>;
+}
+
+impl BlockHashes for RootProvider<Ethereum> {
+    async fn block_hash(&self, block_number: u64) -> Result<Option<FixedBytes<32>>> {
+        let block = self
+            .get_block(BlockId::Number(BlockNumberOrTag::Number(block_number)))
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to get block {}: {}", block_number, e))?;
+
+        Ok(block.map(|block| block.header.hash))
+    }
+}
+
+/// Ids of the indexed events whose source block is no longer canonical. Deleted events, events
+/// without a recorded block and events from blocks before `min_block` are not checked. Neither are
+/// events from blocks the chain doesn't reach, which are unknown rather than reorged: the node may
+/// lag behind, or a shorter fork may not have caught up yet. A later run checks them again.
+pub async fn find_reorged_events(
+    chain: &impl BlockHashes,
+    events: &[IndexedEvent],
+    min_block: u64,
+) -> Result<Vec<FixedBytes<32>>> {
+    let mut canonical: HashMap<u64, Option<FixedBytes<32>>> = HashMap::new();
+    let mut reorged = Vec::new();
+
+    for event in events.iter().filter(|event| !event.deleted) {
+        let Some(block) = SourceBlock::from_metadata(&event.metadata) else {
+            continue;
+        };
+        if block.number < min_block || block.hash.is_zero() {
+            continue;
+        }
+
+        let hash = match canonical.get(&block.number) {
+            Some(hash) => *hash,
+            None => {
+                let hash = chain.block_hash(block.number).await?;
+                canonical.insert(block.number, hash);
+                hash
+            }
+        };
+        match hash {
+            Some(hash) if hash == block.hash => {}
+            Some(_) => {
+                println!(
+                    "Event {} was indexed from block {} ({}), which is no longer canonical",
+                    event.eventId, block.number, block.hash
+                );
+                reorged.push(event.eventId);
+            }
+            None => println!(
+                "Event {} was indexed from block {}, which the node doesn't have yet",
+                event.eventId, block.number
+            ),
+        }
+    }
+
+    Ok(reorged)
+}
+
+/// Reconciliation configuration, read from the `reconcile_*` config variables
+pub struct ReconcileConfig {
+    /// Blocks behind the trigger block that are checked for reorgs
+    pub depth: u64,
+    /// Most recently indexed events of the chain that are checked
+    pub max_events: u64,
+    /// Blocks after which a deferred deletion whose event still isn't indexed is dropped
+    pub marker_max_age: u64,
+}
+
+impl ReconcileConfig {
+    pub fn load() -> Result<Self> {
+        let parse = |key: &str, default: u64| -> Result<u64> {
+            host::config_var(key)
+                .map(|value| {
+                    value.parse().map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", key, e))
+                })
+                .transpose()
+                .map(|value| value.unwrap_or(default))
+        };
+
+        Ok(Self {
+            depth: parse("reconcile_depth", 64)?,
+            max_events: parse("reconcile_max_events", 100)?,
+            marker_max_age: parse("reconcile_marker_max_age", 100_000)?,
+        })
+    }
+}
+
+/// Delete the recently indexed events of the chain whose source block was reorged out, and apply
+/// the deferred deletions whose event is indexed by now. Returns `None` if there is nothing to
+/// delete.
+pub async fn reconcile(chain: &str, block_height: u64) -> Result<Option<IndexingPayload>> {
+    let config = ReconcileConfig::load()?;
+    let chain_config = get_evm_chain_config(chain)
+        .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", chain))?;
+    let provider = new_evm_provider::<Ethereum>(
+        chain_config
+            .http_endpoint
+            .ok_or_else(|| anyhow::anyhow!("No HTTP endpoint configured for {}", chain))?,
+    );
+
+    let querier = indexer_querier(chain).await?;
+    let events: Vec<IndexedEvent> = querier
+        .getEventsByChainId(
+            chain_config.chain_id.clone(),
+            U256::ZERO,
+            U256::from(config.max_events),
+            true,
+        )
+        .call()
+        .await?
+        .into_iter()
+        .map(IndexedEvent::from)
+        .collect();
+    let min_block = block_height.saturating_sub(config.depth);
+    let mut to_delete = find_reorged_events(&provider, &events, min_block).await?;
+
+    println!(
+        "Reconciled {} events of chain {} from block {}: {} reorged",
+        events.len(),
+        chain_config.chain_id,
+        min_block,
+        to_delete.len()
+    );
+
+    // A reorged event may also be a deferred deletion or its marker, and is deleted only once
+    let deferred = IndexedEvents::new(chain)?
+        .retry_deferred_deletions(
+            &querier,
+            config.max_events,
+            block_height.saturating_sub(config.marker_max_age),
+        )
+        .await?;
+    for event_id in deferred {
+        if !to_delete.contains(&event_id) {
+            to_delete.push(event_id);
+        }
+    }
+    if to_delete.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(IndexingPayload { toAdd: Vec::new(), toDelete: to_delete }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    /// Mocked provider serving the block hashes of one fork of a chain
+    struct MockChain {
+        hashes: HashMap<u64, FixedBytes<32>>,
+    }
+
+    impl MockChain {
+        /// Blocks `0..length`, with hashes distinguished by a fork byte from `fork_block` on
+        fn new(length: u64, fork_block: u64, fork: u8) -> Self {
+            let hashes = (0..length)
+                .map(|number| {
+                    let mut hash = FixedBytes::<32>::ZERO;
+                    hash[..8].copy_from_slice(&number.to_be_bytes());
+                    if number >= fork_block {
+                        hash[31] = fork;
+                    }
+                    (number, hash)
+                })
+                .collect();
+            Self { hashes }
+        }
+    }
+
+    impl BlockHashes for MockChain {
+        async fn block_hash(&self, block_number: u64) -> Result<Option<FixedBytes<32>>> {
+            Ok(self.hashes.get(&block_number).copied())
+        }
+    }
+
+    /// The mocked provider never suspends, so its futures complete on the first poll
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        match future.as_mut().poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("mocked provider future suspended"),
+        }
+    }
+
+    /// An event indexed from a block of the given chain
+    fn indexed_event(id: u8, chain: &MockChain, block_number: u64) -> IndexedEvent {
+        let block = SourceBlock { number: block_number, hash: chain.hashes[&block_number] };
+        IndexedEvent {
+            eventId: FixedBytes::repeat_byte(id),
+            chainId: "1".to_string(),
+            relevantContract: Default::default(),
+            blockNumber: U256::from(block_number),
+            timestamp: 0,
+            eventType: "attestation".to_string(),
+            tags: Vec::new(),
+            relevantAddresses: Vec::new(),
+            data: Bytes::new(),
+            metadata: block.add_to_metadata(&Bytes::new()).unwrap(),
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn test_source_block_metadata() {
+        let block = SourceBlock { number: 7, hash: FixedBytes::repeat_byte(7) };
+
+        let metadata = block.add_to_metadata(&Bytes::from(br#"{"data":{"weight":"50"}}"#)).unwrap();
+        let value: Value = serde_json::from_slice(&metadata).unwrap();
+        assert_eq!(value["data"]["weight"], "50");
+        assert_eq!(SourceBlock::from_metadata(&metadata), Some(block));
+
+        // Metadata that isn't a JSON object is kept as is
+        let binary = Bytes::from(vec![0xde, 0xad]);
+        assert_eq!(block.add_to_metadata(&binary).unwrap(), binary);
+        assert_eq!(SourceBlock::from_metadata(&binary), None);
+    }
+
+    #[test]
+    fn test_no_reorg() {
+        let chain = MockChain::new(20, 20, 0);
+        let events: Vec<_> = (0..20).map(|n| indexed_event(n as u8, &chain, n)).collect();
+
+        assert!(block_on(find_reorged_events(&chain, &events, 0)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reorged_events_are_found() {
+        // Events were indexed from fork A, but the canonical chain is now fork B from block 15
+        let indexed = MockChain::new(20, 15, 0xa);
+        let canonical = MockChain::new(20, 15, 0xb);
+        let events: Vec<_> = [10, 14, 15, 17, 19]
+            .into_iter()
+            .enumerate()
+            .map(|(i, n)| indexed_event(i as u8, &indexed, n))
+            .collect();
+
+        let reorged = block_on(find_reorged_events(&canonical, &events, 0)).unwrap();
+        assert_eq!(reorged, vec![events[2].eventId, events[3].eventId, events[4].eventId]);
+    }
+
+    #[test]
+    fn test_blocks_the_node_lacks_are_not_reorged() {
+        // The node is at block 15 of fork B, behind the blocks 16 to 19 events were indexed from
+        let indexed = MockChain::new(20, 15, 0xa);
+        let canonical = MockChain::new(16, 15, 0xb);
+        let events = vec![
+            indexed_event(1, &indexed, 14),
+            indexed_event(2, &indexed, 15),
+            indexed_event(3, &indexed, 18),
+        ];
+
+        let reorged = block_on(find_reorged_events(&canonical, &events, 0)).unwrap();
+        assert_eq!(reorged, vec![events[1].eventId]);
+
+        // Once the fork reaches them, they are checked again
+        let canonical = MockChain::new(20, 15, 0xb);
+        let reorged = block_on(find_reorged_events(&canonical, &events, 0)).unwrap();
+        assert_eq!(reorged, vec![events[1].eventId, events[2].eventId]);
+    }
+
+    #[test]
+    fn test_deleted_old_and_unrecorded_events_are_skipped() {
+        let indexed = MockChain::new(20, 5, 0xa);
+        let canonical = MockChain::new(20, 5, 0xb);
+        let mut deleted = indexed_event(1, &indexed, 12);
+        deleted.deleted = true;
+        let old = indexed_event(2, &indexed, 6);
+        let mut unrecorded = indexed_event(3, &indexed, 12);
+        unrecorded.metadata = Bytes::new();
+        let reorged_event = indexed_event(4, &indexed, 12);
+
+        let events = vec![deleted, old, unrecorded, reorged_event.clone()];
+        let reorged = block_on(find_reorged_events(&canonical, &events, 10)).unwrap();
+        assert_eq!(reorged, vec![reorged_event.eventId]);
+    }
+}
@@ -0,0 +1,272 @@
+use alloy_dyn_abi::{DynSolType, DynSolValue};
+use alloy_primitives::hex;
+use serde_json::{Map, Value};
+
+/// Longest string value emitted as a field tag. Longer strings are only in the metadata.
+pub const MAX_TAG_VALUE_LENGTH: usize = 64;
+
+/// Field of an EAS schema
+#[derive(Debug, Clone)]
+pub struct SchemaField {
+    pub name: String,
+    pub ty: DynSolType,
+}
+
+/// Attestation data decoded with its EAS schema
+#[derive(Debug, Clone)]
+pub struct DecodedData {
+    pub fields: Vec<(String, DynSolValue)>,
+}
+
+/// Parse an EAS schema string such as `uint256 weight, string comment` into its fields. Unnamed
+/// fields are named by their index, and names of tuple components are dropped.
+pub fn parse_schema(schema: &str) -> Result<Vec<SchemaField>, String> {
+    split_top_level(schema)
+        .into_iter()
+        .enumerate()
+        .map(|(index, declaration)| {
+            let (ty, name) = split_declaration(declaration);
+            let ty = DynSolType::parse(&normalize_type(ty))
+                .map_err(|e| format!("Invalid type of schema field {}: {}", declaration, e))?;
+            Ok(SchemaField { name: name.unwrap_or(&index.to_string()).to_string(), ty })
+        })
+        .collect()
+}
+
+/// Decode attestation data with the fields of its schema.
+pub fn decode_data(fields: &[SchemaField], data: &[u8]) -> Result<DecodedData, String> {
+    let ty = DynSolType::Tuple(fields.iter().map(|field| field.ty.clone()).collect());
+    let values = match ty.abi_decode_params(data) {
+        Ok(DynSolValue::Tuple(values)) => values,
+        Ok(_) => return Err("Decoded attestation data is not a tuple".to_string()),
+        Err(e) => return Err(format!("Failed to decode attestation data: {}", e)),
+    };
+
+    Ok(DecodedData { fields: fields.iter().map(|field| field.name.clone()).zip(values).collect() })
+}
+
+impl DecodedData {
+    /// `schema:<uid>/field:<name>:<value>` tags of the scalar fields. Arrays, tuples, dynamic
+    /// bytes and strings longer than [`MAX_TAG_VALUE_LENGTH`] once escaped are left out.
+    pub fn field_tags(&self, schema_tag: &str) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter_map(|(name, value)| {
+                let value = tag_value(value)?;
+                Some(format!("{}/field:{}:{}", schema_tag, name, value))
+            })
+            .collect()
+    }
+
+    /// The decoded fields as a JSON object. Integers are written as decimal strings and bytes as
+    /// hex, so no precision is lost.
+    pub fn to_json(&self) -> Value {
+        Value::Object(
+            self.fields
+                .iter()
+                .map(|(name, value)| (name.clone(), json_value(value)))
+                .collect::<Map<_, _>>(),
+        )
+    }
+}
+
+/// Value of a scalar field as written in tags, or `None` if the field can't be a tag. Strings are
+/// escaped with [`escape_tag_value`].
+pub fn tag_value(value: &DynSolValue) -> Option<String> {
+    match value {
+        DynSolValue::Bool(value) => Some(value.to_string()),
+        DynSolValue::Int(value, _) => Some(value.to_string()),
+        DynSolValue::Uint(value, _) => Some(value.to_string()),
+        DynSolValue::Address(value) => Some(value.to_string()),
+        DynSolValue::FixedBytes(word, size) => Some(hex::encode_prefixed(&word[..*size])),
+        DynSolValue::String(value) => {
+            Some(escape_tag_value(value)).filter(|value| value.len() <= MAX_TAG_VALUE_LENGTH)
+        }
+        _ => None,
+    }
+}
+
+/// Percent-encode the characters that separate the parts of tags, `/` and `:`, and `%` itself, so
+/// a string value can't forge another tag, e.g. `a/field:weight:100`.
+pub fn escape_tag_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '/' => escaped.push_str("%2F"),
+            ':' => escaped.push_str("%3A"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn json_value(value: &DynSolValue) -> Value {
+    match value {
+        DynSolValue::Bool(value) => Value::Bool(*value),
+        DynSolValue::Int(value, _) => Value::String(value.to_string()),
+        DynSolValue::Uint(value, _) => Value::String(value.to_string()),
+        DynSolValue::Address(value) => Value::String(value.to_string()),
+        DynSolValue::FixedBytes(word, size) => Value::String(hex::encode_prefixed(&word[..*size])),
+        DynSolValue::Bytes(value) => Value::String(hex::encode_prefixed(value)),
+        DynSolValue::String(value) => Value::String(value.clone()),
+        other => match other.as_fixed_seq().or(other.as_array()).or(other.as_tuple()) {
+            Some(values) => Value::Array(values.iter().map(json_value).collect()),
+            None => Value::String(hex::encode_prefixed(other.abi_encode())),
+        },
+    }
+}
+
+/// Split a comma-separated list, ignoring commas inside parentheses.
+fn split_top_level(list: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let (mut depth, mut start) = (0usize, 0);
+    for (i, c) in list.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                parts.push(list[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(list[start..].trim());
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+/// Split a declaration like `uint256[] values` into its type and name, if named.
+fn split_declaration(declaration: &str) -> (&str, Option<&str>) {
+    let declaration = declaration.trim();
+    if declaration.ends_with(')') || declaration.ends_with(']') {
+        return (declaration, None);
+    }
+    match declaration.rsplit_once(' ') {
+        Some((ty, name)) => (ty.trim(), Some(name)),
+        None => (declaration, None),
+    }
+}
+
+/// Drop the component names of tuple types, e.g. `(string key,uint256 value)[]` to
+/// `(string,uint256)[]`.
+fn normalize_type(ty: &str) -> String {
+    let ty = ty.trim();
+    if !ty.starts_with('(') {
+        return ty.to_string();
+    }
+
+    let mut depth = 0;
+    let close = ty
+        .char_indices()
+        .find(|(_, c)| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })
+        .map(|(i, _)| i)
+        .unwrap_or(ty.len() - 1);
+
+    let components: Vec<String> = split_top_level(&ty[1..close])
+        .into_iter()
+        .map(|component| normalize_type(split_declaration(component).0))
+        .collect();
+    format!("({}){}", components.join(","), &ty[close + 1..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{Address, U256};
+
+    fn types(schema: &str) -> Vec<(String, String)> {
+        parse_schema(schema)
+            .unwrap()
+            .into_iter()
+            .map(|field| (field.name, field.ty.sol_type_name().to_string()))
+            .collect()
+    }
+
+    fn field(name: &str, ty: &str) -> (String, String) {
+        (name.to_string(), ty.to_string())
+    }
+
+    #[test]
+    fn test_parse_schema() {
+        assert_eq!(
+            types("uint256 weight, string comment,bool  endorsed "),
+            vec![field("weight", "uint256"), field("comment", "string"), field("endorsed", "bool")]
+        );
+        assert_eq!(
+            types("address recipient, (string key, uint256 value)[] entries, (bool,uint8) flags"),
+            vec![
+                field("recipient", "address"),
+                field("entries", "(string,uint256)[]"),
+                field("flags", "(bool,uint8)"),
+            ]
+        );
+        // Unnamed fields are named by their index
+        assert_eq!(
+            types("uint256, (address a, bytes32 b), string[2] names, bytes"),
+            vec![
+                field("0", "uint256"),
+                field("1", "(address,bytes32)"),
+                field("names", "string[2]"),
+                field("3", "bytes"),
+            ]
+        );
+        assert!(parse_schema("uint257 weight").is_err());
+        assert!(parse_schema("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_split_declaration() {
+        assert_eq!(split_declaration("uint256 weight"), ("uint256", Some("weight")));
+        assert_eq!(split_declaration("  uint256[]   values  "), ("uint256[]", Some("values")));
+        assert_eq!(split_declaration("uint256[3]"), ("uint256[3]", None));
+        assert_eq!(split_declaration("(string key, bool ok)"), ("(string key, bool ok)", None));
+        assert_eq!(
+            split_declaration("(string key, bool ok)[] pairs"),
+            ("(string key, bool ok)[]", Some("pairs"))
+        );
+        assert_eq!(split_declaration("bytes"), ("bytes", None));
+    }
+
+    #[test]
+    fn test_normalize_type() {
+        assert_eq!(normalize_type(" uint256 "), "uint256");
+        assert_eq!(normalize_type("(string key,uint256 value)[]"), "(string,uint256)[]");
+        assert_eq!(
+            normalize_type("(address who, (uint8 kind, bytes32 id)[2] refs) "),
+            "(address,(uint8,bytes32)[2])"
+        );
+        assert_eq!(normalize_type("(bool, uint8)"), "(bool,uint8)");
+    }
+
+    #[test]
+    fn test_field_tags_escape_separators() {
+        let data = DecodedData {
+            fields: vec![
+                ("weight".to_string(), DynSolValue::Uint(U256::from(50), 256)),
+                ("url".to_string(), DynSolValue::String("https://a.b/c%d".to_string())),
+                ("forged".to_string(), DynSolValue::String("x/field:weight:100".to_string())),
+                ("who".to_string(), DynSolValue::Address(Address::ZERO)),
+                ("long".to_string(), DynSolValue::String("/".repeat(30))),
+                ("list".to_string(), DynSolValue::Array(vec![DynSolValue::Bool(true)])),
+            ],
+        };
+
+        assert_eq!(
+            data.field_tags("schema:0x01"),
+            vec![
+                "schema:0x01/field:weight:50".to_string(),
+                "schema:0x01/field:url:https%3A%2F%2Fa.b%2Fc%25d".to_string(),
+                "schema:0x01/field:forged:x%2Ffield%3Aweight%3A100".to_string(),
+                format!("schema:0x01/field:who:{}", Address::ZERO),
+            ]
+        );
+    }
+}
@@ -11,6 +11,34 @@ pub use IWavsIndexer::*;
 
 sol!("../../src/interfaces/IIndexedEvents.sol");
 
+/// Events read back from the indexer, e.g. to revise them
+impl From<wavs_indexer_api::solidity::IndexedEvent> for IndexedEvent {
+    fn from(event: wavs_indexer_api::solidity::IndexedEvent) -> Self {
+        Self {
+            eventId: event.eventId,
+            chainId: event.chainId,
+            relevantContract: event.relevantContract,
+            blockNumber: event.blockNumber,
+            timestamp: event.timestamp,
+            eventType: event.eventType,
+            tags: event.tags,
+            relevantAddresses: event.relevantAddresses,
+            data: event.data,
+            metadata: event.metadata,
+            deleted: event.deleted,
+        }
+    }
+}
+
+// `eventExists` is a public mapping of the contract, not part of the interface
+sol!(
+    #[sol(rpc)]
+    interface IWavsIndexerEvents {
+        function eventExists(bytes32 eventId) external view returns (bool);
+        function eventExistsAndDeleted(bytes32 eventId) external view returns (bool);
+    }
+);
+
 // sol!("../../src/interfaces/IPredictionMarketController.sol");
 
 // pub use IPredictionMarketController::MarketResolved;
@@ -1,21 +1,80 @@
-use super::EventTransformer;
-use crate::bindings::host;
-use crate::bindings::wavs::types::service::{AggregatorSubmit, Submit};
+use super::{indexer_querier, EventTransformer};
+use crate::bindings::host::{self, get_evm_chain_config};
+use crate::idempotency::IndexedEvents;
 use crate::register_transformer;
+use crate::reorg::{BlockHashes, SourceBlock};
+use crate::schema::{self, DecodedData};
+use crate::solidity::IndexingPayload;
 use crate::trigger::EventData;
-use crate::{bindings::host::get_evm_chain_config, solidity::IndexingPayload};
 use alloy_network::Ethereum;
-use alloy_primitives::{Address, FixedBytes, U256};
-use alloy_sol_types::SolEvent;
+use alloy_primitives::{keccak256, Address, FixedBytes, U256};
+use alloy_provider::RootProvider;
+use alloy_sol_types::{SolEvent, SolValue};
 use anyhow::Result;
-use wavs_indexer_api::query::WavsIndexerQuerier;
+use serde_json::json;
 use wavs_wasi_utils::{decode_event_log_data, evm::new_evm_provider};
 
 use crate::solidity::{AttestationAttested, AttestationRevoked, IndexedEvent};
 
+/// Event type of indexed attestations
+pub const ATTESTATION_EVENT_TYPE: &str = "attestation";
+
+/// Indexes EAS attestations, and deletes them when they are revoked.
+///
+/// Attestations are indexed with an id derived from their UID, so a revocation usually deletes its
+/// attestation without looking it up in the indexer. Attestations indexed before, or again after
+/// a reorg, are found by their `uid:` tag.
 pub struct AttestationTransformer;
 register_transformer!(AttestationTransformer);
 
+/// Deterministic id of the indexed attestation with a UID
+pub fn attestation_event_id(chain_id: &str, eas: Address, uid: FixedBytes<32>) -> FixedBytes<32> {
+    keccak256(
+        (ATTESTATION_EVENT_TYPE.to_string(), chain_id.to_string(), eas, uid).abi_encode_params(),
+    )
+}
+
+/// Ids of an attestation indexed from a block and of an indexed copy to delete, given the newest
+/// indexed event with its UID and whether that event's block is still canonical. The id is the one
+/// derived from its UID, unless the newest event is from another block that was reorged out: then
+/// the attestation was included again, and since an id can't be indexed twice, the block hash
+/// becomes part of the id. The reorged copy is deleted unless reconciliation already did.
+fn attestation_event_ids_in_block(
+    event_id: FixedBytes<32>,
+    block_hash: FixedBytes<32>,
+    newest: Option<&IndexedEvent>,
+    newest_canonical: bool,
+) -> (FixedBytes<32>, Option<FixedBytes<32>>) {
+    let Some(newest) = newest else {
+        return (event_id, None);
+    };
+    let other_block =
+        SourceBlock::from_metadata(&newest.metadata).is_some_and(|block| block.hash != block_hash);
+    if !other_block || (!newest.deleted && newest_canonical) {
+        return (event_id, None);
+    }
+
+    let reorged_copy = (!newest.deleted).then_some(newest.eventId);
+    (keccak256((event_id, block_hash).abi_encode_params()), reorged_copy)
+}
+
+/// Newest indexed attestation with a `uid:` tag, deleted or not
+async fn newest_attestation(chain: &str, uid_tag: &str) -> Result<Option<IndexedEvent>> {
+    let events = indexer_querier(chain)
+        .await?
+        .getEventsByTypeAndTag(
+            ATTESTATION_EVENT_TYPE.to_string(),
+            uid_tag.to_string(),
+            U256::ZERO,
+            U256::ONE,
+            true,
+        )
+        .call()
+        .await?;
+
+    Ok(events.into_iter().next().map(IndexedEvent::from))
+}
+
 impl EventTransformer for AttestationTransformer {
     fn name() -> &'static str {
         "AttestationTransformer"
@@ -40,6 +99,19 @@ impl EventTransformer for AttestationTransformer {
     }
 }
 
+/// Whether attestation data is decoded with its schema, set by the `decode_schema_data` config
+/// variable. Off by default, since it costs two extra calls per attestation.
+fn decode_schema_data() -> Result<bool> {
+    host::config_var("decode_schema_data")
+        .map(|value| {
+            value
+                .parse::<bool>()
+                .map_err(|_| anyhow::anyhow!("Invalid decode_schema_data: {}", value))
+        })
+        .transpose()
+        .map(|value| value.unwrap_or(false))
+}
+
 impl AttestationTransformer {
     async fn transform_attestation(event_data: EventData) -> Result<IndexingPayload> {
         // Decode the Attested event
@@ -56,80 +128,122 @@ impl AttestationTransformer {
         let eas = solidity::EAS::new(attested.eas, &provider);
         let attestation = eas.getAttestation(attested.uid).call().await?;
 
-        // Create IndexedEvent
+        let mut tags = vec![
+            format!("eas:{}", attested.eas),
+            format!("uid:{}", attested.uid),
+            format!("schema:{}", attestation.schema),
+            format!("attester:{}", attestation.attester),
+            format!("recipient:{}", attestation.recipient),
+            format!("schema:{}/attester:{}", attestation.schema, attestation.attester),
+            format!("schema:{}/recipient:{}", attestation.schema, attestation.recipient),
+            format!(
+                "schema:{}/attester:{}/recipient:{}",
+                attestation.schema, attestation.attester, attestation.recipient
+            ),
+        ];
+        let mut metadata = Vec::new();
+
+        // Decoding is best effort: an attestation whose data doesn't match its schema is still
+        // indexed, just without field tags.
+        if decode_schema_data()? {
+            match Self::decode_attestation_data(&provider, attested.eas, &attestation).await {
+                Ok(decoded) => {
+                    tags.extend(decoded.field_tags(&format!("schema:{}", attestation.schema)));
+                    metadata = serde_json::to_vec(&json!({ "data": decoded.to_json() }))?;
+                }
+                Err(e) => println!(
+                    "Failed to decode data of attestation {} with its schema: {}",
+                    attested.uid, e
+                ),
+            }
+        }
+
+        // Create IndexedEvent, with a new id if the attestation is included again after a reorg
+        let newest =
+            newest_attestation(&event_data.chain, &format!("uid:{}", attested.uid)).await?;
+        let newest_canonical = match newest.as_ref().and_then(|event| {
+            SourceBlock::from_metadata(&event.metadata)
+                .filter(|block| block.hash != event_data.block_hash)
+        }) {
+            Some(block) => provider.block_hash(block.number).await? == Some(block.hash),
+            None => true,
+        };
+        let (event_id, reorged_copy) = attestation_event_ids_in_block(
+            attestation_event_id(&chain.chain_id, attested.eas, attested.uid),
+            event_data.block_hash,
+            newest.as_ref(),
+            newest_canonical,
+        );
         let indexed_event = IndexedEvent {
-            eventId: FixedBytes::ZERO,
+            eventId: event_id,
             chainId: chain.chain_id,
             relevantContract: event_data.contract_address,
             blockNumber: U256::from(event_data.block_number),
             timestamp: attestation.time as u128,
-            eventType: "attestation".to_string(),
-            tags: vec![
-                format!("eas:{}", attested.eas),
-                format!("uid:{}", attested.uid),
-                format!("schema:{}", attestation.schema),
-                format!("attester:{}", attestation.attester),
-                format!("recipient:{}", attestation.recipient),
-                format!("schema:{}/attester:{}", attestation.schema, attestation.attester),
-                format!("schema:{}/recipient:{}", attestation.schema, attestation.recipient),
-                format!(
-                    "schema:{}/attester:{}/recipient:{}",
-                    attestation.schema, attestation.attester, attestation.recipient
-                ),
-            ],
+            eventType: ATTESTATION_EVENT_TYPE.to_string(),
+            tags,
             relevantAddresses: vec![attestation.attester, attestation.recipient],
             data: attestation.data,
-            metadata: Vec::new().into(),
+            metadata: metadata.into(),
             deleted: false,
         };
 
-        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: Vec::new() })
+        // A copy indexed from a reorged block is replaced. An attestation revoked before it was
+        // indexed is deleted right away, whether or not its revocation was deferred.
+        let mut to_delete = Vec::new();
+        if let Some(reorged_copy) = reorged_copy {
+            println!("Attestation {} replaces its reorged copy {}", attested.uid, reorged_copy);
+            to_delete.push(reorged_copy);
+        }
+        if attestation.revocationTime != 0 {
+            println!("Attestation {} is already revoked", attested.uid);
+            to_delete.push(event_id);
+        }
+
+        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: to_delete })
+    }
+
+    /// Look up the schema of an attestation in the EAS schema registry and decode its data.
+    async fn decode_attestation_data(
+        provider: &RootProvider<Ethereum>,
+        eas: Address,
+        attestation: &solidity::Attestation,
+    ) -> Result<DecodedData> {
+        let registry = solidity::EAS::new(eas, provider).getSchemaRegistry().call().await?;
+        let record = solidity::SchemaRegistry::new(registry, provider)
+            .getSchema(attestation.schema)
+            .call()
+            .await?;
+
+        let fields = schema::parse_schema(&record.schema).map_err(|e| anyhow::anyhow!(e))?;
+        schema::decode_data(&fields, &attestation.data).map_err(|e| anyhow::anyhow!(e))
     }
 
     async fn transform_revocation(event_data: EventData) -> Result<IndexingPayload> {
-        // Decode the Attested event
+        // Decode the Revoked event
         let revoked: AttestationRevoked = decode_event_log_data!(event_data.log)?;
 
         println!("Transforming AttestationRevoked event: eas={}, uid={}", revoked.eas, revoked.uid);
 
-        let wavs_indexer_address: Address = match host::get_workflow().workflow.submit {
-            Submit::Aggregator(AggregatorSubmit { component, .. }) => component
-                .config
-                .first()
-                .ok_or(anyhow::anyhow!("Aggregator config not found"))?
-                .1
-                .parse()?,
-            _ => host::config_var("indexer_address")
-                .ok_or(anyhow::anyhow!(
-                    "Failed to get indexer address from workflow submit config and config_var"
-                ))?
-                .parse()?,
-        };
+        let chain = get_evm_chain_config(&event_data.chain)
+            .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", event_data.chain))?;
+        let event_id = attestation_event_id(&chain.chain_id, revoked.eas, revoked.uid);
+        let uid_tag = format!("uid:{}", revoked.uid);
 
-        let chain: crate::bindings::wavs::types::chain::EvmChainConfig =
-            get_evm_chain_config(&event_data.chain).unwrap();
-        let indexer_querier =
-            WavsIndexerQuerier::new(wavs_indexer_address, chain.http_endpoint.unwrap())
-                .await
-                .map_err(|e| anyhow::anyhow!("Failed to create indexer querier: {}", e))?;
-
-        let events = indexer_querier
-            .getEventsByTypeAndTag(
-                "attestation".to_string(),
-                format!("uid:{}", revoked.uid),
-                U256::ZERO,
-                U256::ONE,
-                false,
-            )
-            .call()
-            .await?;
-        let event = events.first().ok_or(anyhow::anyhow!("Indexed attestation event not found"))?;
-
-        if event.deleted {
-            return Err(anyhow::anyhow!("Indexed attestation already deleted"));
+        // The attestation may be indexed earlier in the same batch, and not on chain yet
+        if let Some(event) = event_data.pending.find(ATTESTATION_EVENT_TYPE, &uid_tag).next() {
+            return Ok(IndexingPayload { toAdd: Vec::new(), toDelete: vec![event.eventId] });
+        }
+        if IndexedEvents::new(&event_data.chain)?.is_live(event_id).await? {
+            return Ok(IndexingPayload { toAdd: Vec::new(), toDelete: vec![event_id] });
         }
 
-        Ok(IndexingPayload { toAdd: Vec::new(), toDelete: vec![event.eventId] })
+        // Otherwise it's found by its tag, or its deletion is deferred until it's indexed
+        let to_delete = match newest_attestation(&event_data.chain, &uid_tag).await? {
+            Some(event) => event.eventId,
+            None => event_id,
+        };
+        Ok(IndexingPayload { toAdd: Vec::new(), toDelete: vec![to_delete] })
     }
 }
 
@@ -155,6 +269,81 @@ mod solidity {
         #[sol(rpc)]
         contract EAS {
             function getAttestation(bytes32 uid) external view returns (Attestation memory);
+            function getSchemaRegistry() external view returns (address);
+        }
+
+        /// @notice A struct representing a record for a submitted schema.
+        struct SchemaRecord {
+            bytes32 uid; // The unique identifier of the schema.
+            address resolver; // Optional schema resolver.
+            bool revocable; // Whether the schema allows revocations explicitly.
+            string schema; // Custom specification of the schema (e.g., an ABI).
+        }
+
+        #[sol(rpc)]
+        contract SchemaRegistry {
+            function getSchema(bytes32 uid) external view returns (SchemaRecord memory);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::Bytes;
+
+    fn indexed(deleted: bool, block_hash: Option<FixedBytes<32>>) -> IndexedEvent {
+        let metadata = match block_hash {
+            Some(hash) => SourceBlock { number: 1, hash }.add_to_metadata(&Bytes::new()).unwrap(),
+            None => Bytes::new(),
+        };
+        IndexedEvent {
+            eventId: FixedBytes::repeat_byte(1),
+            chainId: "31337".to_string(),
+            relevantContract: Address::ZERO,
+            blockNumber: U256::from(1),
+            timestamp: 0,
+            eventType: ATTESTATION_EVENT_TYPE.to_string(),
+            tags: Vec::new(),
+            relevantAddresses: Vec::new(),
+            data: Bytes::new(),
+            metadata,
+            deleted,
         }
     }
+
+    #[test]
+    fn test_attestation_included_again_after_a_reorg_gets_a_new_id() {
+        let event_id = attestation_event_id("31337", Address::ZERO, FixedBytes::repeat_byte(7));
+        let (block, reorged_block) = (FixedBytes::repeat_byte(0xb1), FixedBytes::repeat_byte(0xb0));
+        let ids = |newest: Option<IndexedEvent>, newest_canonical| {
+            attestation_event_ids_in_block(event_id, block, newest.as_ref(), newest_canonical)
+        };
+
+        // New, re-delivered, revoked or legacy attestations keep the id derived from their UID
+        assert_eq!(ids(None, true), (event_id, None));
+        assert_eq!(ids(Some(indexed(false, Some(block))), true), (event_id, None));
+        assert_eq!(ids(Some(indexed(true, Some(block))), true), (event_id, None));
+        assert_eq!(ids(Some(indexed(true, None)), true), (event_id, None));
+        // A log of a block that isn't canonical doesn't replace the canonical copy
+        assert_eq!(ids(Some(indexed(false, Some(reorged_block))), true), (event_id, None));
+
+        // A reconciled copy is left alone, one that isn't reconciled yet is deleted
+        let (reincluded, reorged_copy) = ids(Some(indexed(true, Some(reorged_block))), false);
+        assert_ne!(reincluded, event_id);
+        assert_eq!(reorged_copy, None);
+        assert_eq!(
+            ids(Some(indexed(false, Some(reorged_block))), false),
+            (reincluded, Some(FixedBytes::repeat_byte(1)))
+        );
+
+        // Each inclusion gets its own id
+        let (included_again, _) = attestation_event_ids_in_block(
+            event_id,
+            FixedBytes::repeat_byte(0xb2),
+            Some(&indexed(true, Some(reorged_block))),
+            false,
+        );
+        assert_ne!(included_again, reincluded);
+    }
 }
@@ -0,0 +1,307 @@
+use crate::bindings::host::{self, get_evm_chain_config};
+use crate::schema::{tag_value, DecodedData};
+use crate::solidity::{IndexedEvent, IndexingPayload};
+use crate::trigger::EventData;
+use alloy_dyn_abi::{DynSolValue, EventExt};
+use alloy_json_abi::Event;
+use alloy_primitives::{Address, Bytes, FixedBytes, U256};
+use anyhow::Result;
+use serde::Deserialize;
+use serde_json::json;
+
+/// Config variable holding the JSON array of declarative transformer specs
+pub const DECLARATIVE_TRANSFORMERS_CONFIG: &str = "declarative_transformers";
+
+/// Placeholder of tag templates replaced by the address of the contract emitting the event,
+/// unless the event has a field of the same name
+const CONTRACT_PLACEHOLDER: &str = "contract";
+
+/// Configuration of a declarative transformer
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct DeclarativeSpec {
+    /// Event signature, e.g. `Voted(address indexed voter, uint256 proposalId, bool support)`
+    pub event: String,
+    /// Event type of the indexed events
+    pub event_type: String,
+    /// Address fields of the event added to `relevantAddresses`
+    #[serde(default)]
+    pub relevant_addresses: Vec<String>,
+    /// Tag templates, e.g. `voter:{voter}`, with `{field}` replaced by the field's value
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Contracts whose events are indexed. All contracts if empty.
+    #[serde(default)]
+    pub contracts: Vec<Address>,
+}
+
+/// Indexes the events of an ABI signature given in configuration rather than code, so new event
+/// types don't need a new transformer.
+#[derive(Debug, Clone)]
+pub struct DeclarativeTransformer {
+    event: Event,
+    names: Vec<String>,
+    spec: DeclarativeSpec,
+}
+
+impl DeclarativeTransformer {
+    /// Parse the event of a spec and check that the fields it refers to exist.
+    pub fn new(spec: DeclarativeSpec) -> Result<Self> {
+        let signature = spec.event.trim();
+        let event = Event::parse(signature.strip_prefix("event ").unwrap_or(signature))
+            .map_err(|e| anyhow::anyhow!("Invalid event signature {}: {}", spec.event, e))?;
+        if event.anonymous {
+            return Err(anyhow::anyhow!("Anonymous event {} can't be indexed", spec.event));
+        }
+
+        // Unnamed fields are named by their index, like schema fields
+        let names: Vec<String> = event
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(index, input)| match input.name.as_str() {
+                "" => index.to_string(),
+                name => name.to_string(),
+            })
+            .collect();
+
+        for name in &spec.relevant_addresses {
+            if !names.contains(name) {
+                return Err(anyhow::anyhow!("Event {} has no field {}", event.name, name));
+            }
+        }
+        for template in &spec.tags {
+            for placeholder in placeholders(template)? {
+                if placeholder != CONTRACT_PLACEHOLDER && !names.iter().any(|n| n == placeholder) {
+                    return Err(anyhow::anyhow!(
+                        "Tag {} refers to missing field {} of event {}",
+                        template,
+                        placeholder,
+                        event.name
+                    ));
+                }
+            }
+        }
+
+        Ok(Self { event, names, spec })
+    }
+
+    /// Load the transformers configured in the `declarative_transformers` config variable
+    pub fn load() -> Result<Vec<Self>> {
+        let Some(config) = host::config_var(DECLARATIVE_TRANSFORMERS_CONFIG) else {
+            return Ok(Vec::new());
+        };
+        let specs: Vec<DeclarativeSpec> = serde_json::from_str(&config).map_err(|e| {
+            anyhow::anyhow!("Failed to parse {}: {}", DECLARATIVE_TRANSFORMERS_CONFIG, e)
+        })?;
+
+        specs.into_iter().map(Self::new).collect()
+    }
+
+    /// Name for debugging, after the event type
+    pub fn name(&self) -> String {
+        format!("DeclarativeTransformer({})", self.spec.event_type)
+    }
+
+    pub fn supports_event(&self, event_signature: &FixedBytes<32>) -> bool {
+        self.event.selector() == *event_signature
+    }
+
+    /// Transform an event of the configured signature into an indexed event.
+    pub fn transform(&self, event_data: &EventData) -> Result<IndexingPayload> {
+        let contract = event_data.contract_address;
+        if !self.spec.contracts.is_empty() && !self.spec.contracts.contains(&contract) {
+            return Ok(IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() });
+        }
+
+        let topics = event_data.log.topics.iter().map(|topic| FixedBytes::from_slice(topic));
+        let decoded = self.decode(topics, &event_data.log.data)?;
+
+        println!(
+            "Transforming {} event: contract={}, eventType={}",
+            self.event.name, contract, self.spec.event_type
+        );
+
+        let chain = get_evm_chain_config(&event_data.chain)
+            .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", event_data.chain))?;
+
+        let indexed_event = IndexedEvent {
+            eventId: FixedBytes::ZERO,
+            chainId: chain.chain_id,
+            relevantContract: contract,
+            blockNumber: U256::from(event_data.block_number),
+            timestamp: event_data.block_timestamp as u128,
+            eventType: self.spec.event_type.clone(),
+            tags: self.tags(&decoded, contract)?,
+            relevantAddresses: self.relevant_addresses(&decoded)?,
+            data: Bytes::from(event_data.log.data.clone()),
+            metadata: serde_json::to_vec(&json!({ "data": decoded.to_json() }))?.into(),
+            deleted: false,
+        };
+
+        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: Vec::new() })
+    }
+
+    /// Decode the topics and data of a log into the named fields of the event. Indexed fields of
+    /// dynamic types are only available as the hash in their topic.
+    fn decode(
+        &self,
+        topics: impl IntoIterator<Item = FixedBytes<32>>,
+        data: &[u8],
+    ) -> Result<DecodedData> {
+        let decoded = self
+            .event
+            .decode_log_parts(topics, data)
+            .map_err(|e| anyhow::anyhow!("Failed to decode {} event: {}", self.event.name, e))?;
+
+        let (mut indexed, mut body) = (decoded.indexed.into_iter(), decoded.body.into_iter());
+        let fields = self
+            .event
+            .inputs
+            .iter()
+            .zip(&self.names)
+            .map(|(input, name)| {
+                let value = if input.indexed { indexed.next() } else { body.next() };
+                value
+                    .map(|value| (name.clone(), value))
+                    .ok_or_else(|| anyhow::anyhow!("Missing field {} of event", name))
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(DecodedData { fields })
+    }
+
+    /// Render the tag templates with the decoded fields
+    fn tags(&self, decoded: &DecodedData, contract: Address) -> Result<Vec<String>> {
+        self.spec
+            .tags
+            .iter()
+            .map(|template| {
+                let mut tag = template.clone();
+                for placeholder in placeholders(template)? {
+                    let value = match decoded.fields.iter().find(|(name, _)| name == placeholder) {
+                        Some((_, value)) => tag_value(value).ok_or_else(|| {
+                            anyhow::anyhow!("Field {} can't be used in a tag", placeholder)
+                        })?,
+                        None => contract.to_string(),
+                    };
+                    tag = tag.replace(&format!("{{{}}}", placeholder), &value);
+                }
+                Ok(tag)
+            })
+            .collect()
+    }
+
+    /// Addresses of the configured fields, which must be addresses or arrays of addresses
+    fn relevant_addresses(&self, decoded: &DecodedData) -> Result<Vec<Address>> {
+        let mut addresses = Vec::new();
+        for name in &self.spec.relevant_addresses {
+            let (_, value) = decoded
+                .fields
+                .iter()
+                .find(|(field, _)| field == name)
+                .ok_or_else(|| anyhow::anyhow!("Missing field {} of event", name))?;
+            let values = match value.as_array().or(value.as_fixed_seq()) {
+                Some(values) => values,
+                None => std::slice::from_ref(value),
+            };
+            for value in values {
+                match value {
+                    DynSolValue::Address(address) => addresses.push(*address),
+                    _ => return Err(anyhow::anyhow!("Field {} is not an address", name)),
+                }
+            }
+        }
+        Ok(addresses)
+    }
+}
+
+/// Names of the `{field}` placeholders of a tag template
+fn placeholders(template: &str) -> Result<Vec<&str>> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow::anyhow!("Unclosed placeholder in tag {}", template))?;
+        names.push(&rest[start + 1..start + end]);
+        rest = &rest[start + end + 1..];
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, keccak256};
+    use alloy_sol_types::SolValue;
+
+    fn spec(tags: &[&str], relevant_addresses: &[&str]) -> DeclarativeSpec {
+        DeclarativeSpec {
+            event: "event Voted(address indexed voter, uint256 indexed proposalId, bool support, \
+                    address[] delegates)"
+                .to_string(),
+            event_type: "vote".to_string(),
+            relevant_addresses: relevant_addresses.iter().map(|s| s.to_string()).collect(),
+            tags: tags.iter().map(|s| s.to_string()).collect(),
+            contracts: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_decode_and_render_tags() {
+        let transformer = DeclarativeTransformer::new(spec(
+            &["voter:{voter}", "contract:{contract}/proposal:{proposalId}", "support:{support}"],
+            &["voter", "delegates"],
+        ))
+        .unwrap();
+
+        let voter = address!("0x1111111111111111111111111111111111111111");
+        let delegate = address!("0x2222222222222222222222222222222222222222");
+        let contract = address!("0x3333333333333333333333333333333333333333");
+        let selector = keccak256("Voted(address,uint256,bool,address[])");
+        assert!(transformer.supports_event(&selector));
+
+        let topics = vec![selector, voter.into_word(), U256::from(7).into()];
+        let data = (true, vec![delegate]).abi_encode_params();
+        let decoded = transformer.decode(topics, &data).unwrap();
+
+        assert_eq!(
+            transformer.tags(&decoded, contract).unwrap(),
+            vec![
+                format!("voter:{}", voter),
+                format!("contract:{}/proposal:7", contract),
+                "support:true".to_string(),
+            ]
+        );
+        assert_eq!(transformer.relevant_addresses(&decoded).unwrap(), vec![voter, delegate]);
+        assert_eq!(decoded.to_json()["proposalId"], "7");
+    }
+
+    #[test]
+    fn test_invalid_specs_are_rejected() {
+        assert!(DeclarativeTransformer::new(spec(&["voter:{voters}"], &[])).is_err());
+        assert!(DeclarativeTransformer::new(spec(&["voter:{voter"], &[])).is_err());
+        assert!(DeclarativeTransformer::new(spec(&[], &["proposer"])).is_err());
+
+        let mut invalid = spec(&[], &[]);
+        invalid.event = "Voted(address indexed voter,".to_string();
+        assert!(DeclarativeTransformer::new(invalid).is_err());
+    }
+
+    #[test]
+    fn test_non_address_fields_are_not_relevant_addresses() {
+        let transformer =
+            DeclarativeTransformer::new(spec(&["all:{delegates}"], &["support"])).unwrap();
+        let topics = vec![
+            keccak256("Voted(address,uint256,bool,address[])"),
+            Address::ZERO.into_word(),
+            U256::from(1).into(),
+        ];
+        let data = (false, Vec::<Address>::new()).abi_encode_params();
+        let decoded = transformer.decode(topics, &data).unwrap();
+
+        assert!(transformer.relevant_addresses(&decoded).is_err());
+        assert!(transformer.tags(&decoded, Address::ZERO).is_err());
+    }
+}
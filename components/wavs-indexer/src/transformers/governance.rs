@@ -0,0 +1,418 @@
+use super::{indexer_querier, EventTransformer, PendingEvents};
+use crate::bindings::host::get_evm_chain_config;
+use crate::register_transformer;
+use crate::solidity::{IndexedEvent, IndexingPayload};
+use crate::trigger::EventData;
+use alloy_primitives::{Address, FixedBytes, U256};
+use alloy_sol_types::{SolEvent, SolValue};
+use anyhow::Result;
+use wavs_indexer_api::query::WavsIndexerQuerier;
+use wavs_wasi_utils::decode_event_log_data;
+
+use self::solidity::IMerkleGovModule::{
+    ProposalCancelled, ProposalCreated, ProposalExecuted, VoteCast, VoteType,
+};
+
+/// Event type of indexed proposals
+pub const PROPOSAL_EVENT_TYPE: &str = "proposal";
+/// Event type of indexed votes
+pub const VOTE_EVENT_TYPE: &str = "proposal_vote";
+
+/// Number of votes fetched per indexer call when deleting the votes of a cancelled proposal
+const VOTE_PAGE_SIZE: u64 = 100;
+
+/// Indexes the proposals and votes of a `MerkleGovModule`.
+///
+/// Execution and cancellation revise the indexed proposal: it is deleted and indexed again with
+/// its new `status:` tag. Cancellation also deletes the proposal's votes, since they no longer
+/// count as participation. Proposals and votes added earlier in a batch are revised in memory.
+pub struct GovernanceTransformer;
+register_transformer!(GovernanceTransformer);
+
+impl EventTransformer for GovernanceTransformer {
+    fn name() -> &'static str {
+        "GovernanceTransformer"
+    }
+
+    fn supports_event(event_signature: &FixedBytes<32>) -> bool {
+        *event_signature == ProposalCreated::SIGNATURE_HASH
+            || *event_signature == VoteCast::SIGNATURE_HASH
+            || *event_signature == ProposalExecuted::SIGNATURE_HASH
+            || *event_signature == ProposalCancelled::SIGNATURE_HASH
+    }
+
+    async fn transform(
+        event_signature: FixedBytes<32>,
+        event_data: EventData,
+    ) -> Result<IndexingPayload> {
+        if event_signature == ProposalCreated::SIGNATURE_HASH {
+            Self::transform_proposal(event_data).await
+        } else if event_signature == VoteCast::SIGNATURE_HASH {
+            Self::transform_vote(event_data).await
+        } else if event_signature == ProposalExecuted::SIGNATURE_HASH {
+            let executed: ProposalExecuted = decode_event_log_data!(event_data.log)?;
+            Self::transform_revision(event_data, executed.proposalId, ProposalStatus::Executed)
+                .await
+        } else if event_signature == ProposalCancelled::SIGNATURE_HASH {
+            let cancelled: ProposalCancelled = decode_event_log_data!(event_data.log)?;
+            Self::transform_revision(event_data, cancelled.proposalId, ProposalStatus::Cancelled)
+                .await
+        } else {
+            Err(anyhow::anyhow!("Unsupported governance event"))
+        }
+    }
+}
+
+/// Latest lifecycle event of an indexed proposal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProposalStatus {
+    Created,
+    Executed,
+    Cancelled,
+}
+
+impl ProposalStatus {
+    fn tag(&self) -> String {
+        let status = match self {
+            Self::Created => "created",
+            Self::Executed => "executed",
+            Self::Cancelled => "cancelled",
+        };
+        format!("status:{}", status)
+    }
+}
+
+impl GovernanceTransformer {
+    async fn transform_proposal(event_data: EventData) -> Result<IndexingPayload> {
+        let created: ProposalCreated = decode_event_log_data!(event_data.log)?;
+        let module = event_data.contract_address;
+
+        println!(
+            "Transforming ProposalCreated event: module={}, proposalId={}, proposer={}",
+            module, created.proposalId, created.proposer
+        );
+
+        let indexed_event = IndexedEvent {
+            eventId: FixedBytes::ZERO,
+            chainId: chain_id(&event_data)?,
+            relevantContract: module,
+            blockNumber: U256::from(event_data.block_number),
+            timestamp: event_data.block_timestamp as u128,
+            eventType: PROPOSAL_EVENT_TYPE.to_string(),
+            tags: proposal_tags(
+                module,
+                created.proposalId,
+                created.proposer,
+                ProposalStatus::Created,
+            ),
+            relevantAddresses: vec![created.proposer],
+            data: (
+                created.title,
+                created.description,
+                created.startBlock,
+                created.endBlock,
+                created.merkleRoot,
+                created.totalVotingPower,
+            )
+                .abi_encode_params()
+                .into(),
+            metadata: Vec::new().into(),
+            deleted: false,
+        };
+
+        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: Vec::new() })
+    }
+
+    async fn transform_vote(event_data: EventData) -> Result<IndexingPayload> {
+        let vote: VoteCast = decode_event_log_data!(event_data.log)?;
+        let module = event_data.contract_address;
+        let support = support(vote.voteType)?;
+
+        println!(
+            "Transforming VoteCast event: module={}, proposalId={}, voter={}, support={}",
+            module, vote.proposalId, vote.voter, support
+        );
+
+        let indexed_event = IndexedEvent {
+            eventId: FixedBytes::ZERO,
+            chainId: chain_id(&event_data)?,
+            relevantContract: module,
+            blockNumber: U256::from(event_data.block_number),
+            timestamp: event_data.block_timestamp as u128,
+            eventType: VOTE_EVENT_TYPE.to_string(),
+            tags: vote_tags(module, vote.proposalId, vote.voter, support),
+            relevantAddresses: vec![vote.voter],
+            data: (U256::from(u8::from(vote.voteType)), vote.votingPower)
+                .abi_encode_params()
+                .into(),
+            metadata: Vec::new().into(),
+            deleted: false,
+        };
+
+        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: Vec::new() })
+    }
+
+    /// Replace the indexed proposal with one carrying its new status, deleting its votes if it
+    /// was cancelled.
+    async fn transform_revision(
+        event_data: EventData,
+        proposal_id: U256,
+        status: ProposalStatus,
+    ) -> Result<IndexingPayload> {
+        let module = event_data.contract_address;
+        let proposal_tag = format!("gov:{}/proposal:{}", module, proposal_id);
+
+        println!(
+            "Transforming proposal revision: module={}, proposalId={}, {}",
+            module,
+            proposal_id,
+            status.tag()
+        );
+
+        // The proposal may be created or revised earlier in the same batch, and not on chain yet
+        let pending = &event_data.pending;
+        let querier = indexer_querier(&event_data.chain).await?;
+        let event = match pending.find(PROPOSAL_EVENT_TYPE, &proposal_tag).next() {
+            Some(event) => event.clone(),
+            None => {
+                let events = querier
+                    .getEventsByTypeAndTag(
+                        PROPOSAL_EVENT_TYPE.to_string(),
+                        proposal_tag.clone(),
+                        U256::ZERO,
+                        U256::ONE,
+                        true,
+                    )
+                    .call()
+                    .await?;
+                events
+                    .into_iter()
+                    .next()
+                    .map(IndexedEvent::from)
+                    .ok_or(anyhow::anyhow!("Indexed proposal event not found"))?
+            }
+        };
+
+        if event.deleted || pending.is_deleted(&event.eventId) {
+            return Err(anyhow::anyhow!("Indexed proposal already deleted"));
+        }
+
+        let revised = revised_proposal(&event, module, proposal_id, status)?;
+
+        let mut to_delete = vec![event.eventId];
+        if status == ProposalStatus::Cancelled {
+            let indexed_votes = Self::vote_event_ids(&querier, &proposal_tag).await?;
+            to_delete.extend(cancelled_vote_ids(indexed_votes, pending, &proposal_tag));
+        }
+
+        Ok(IndexingPayload { toAdd: vec![revised], toDelete: to_delete })
+    }
+
+    /// Ids of the indexed votes on a proposal that aren't deleted yet.
+    async fn vote_event_ids(
+        querier: &WavsIndexerQuerier,
+        proposal_tag: &str,
+    ) -> Result<Vec<FixedBytes<32>>> {
+        let count = querier
+            .getEventCountByTypeAndTag(VOTE_EVENT_TYPE.to_string(), proposal_tag.to_string())
+            .call()
+            .await?
+            .to::<u64>();
+
+        let mut event_ids = Vec::new();
+        for start in (0..count).step_by(VOTE_PAGE_SIZE as usize) {
+            let votes = querier
+                .getEventsByTypeAndTag(
+                    VOTE_EVENT_TYPE.to_string(),
+                    proposal_tag.to_string(),
+                    U256::from(start),
+                    U256::from(VOTE_PAGE_SIZE.min(count - start)),
+                    false,
+                )
+                .call()
+                .await?;
+            event_ids.extend(votes.iter().filter(|vote| !vote.deleted).map(|vote| vote.eventId));
+        }
+
+        Ok(event_ids)
+    }
+}
+
+/// Support recorded in the tags of a vote
+fn support(vote_type: VoteType) -> Result<&'static str> {
+    match vote_type {
+        VoteType::No => Ok("no"),
+        VoteType::Yes => Ok("yes"),
+        VoteType::Abstain => Ok("abstain"),
+        other => Err(anyhow::anyhow!("Unknown vote type {}", u8::from(other))),
+    }
+}
+
+fn vote_tags(module: Address, proposal_id: U256, voter: Address, support: &str) -> Vec<String> {
+    let proposal = format!("gov:{}/proposal:{}", module, proposal_id);
+    vec![
+        format!("gov:{}", module),
+        format!("proposal:{}", proposal_id),
+        format!("voter:{}", voter),
+        format!("support:{}", support),
+        proposal.clone(),
+        format!("gov:{}/voter:{}", module, voter),
+        format!("{}/voter:{}", proposal, voter),
+        format!("{}/support:{}", proposal, support),
+    ]
+}
+
+/// Copy of an indexed proposal tagged with its new status, to replace it
+fn revised_proposal(
+    event: &IndexedEvent,
+    module: Address,
+    proposal_id: U256,
+    status: ProposalStatus,
+) -> Result<IndexedEvent> {
+    let proposer = *event
+        .relevantAddresses
+        .first()
+        .ok_or(anyhow::anyhow!("Indexed proposal has no proposer"))?;
+
+    Ok(IndexedEvent {
+        eventId: FixedBytes::ZERO,
+        chainId: event.chainId.clone(),
+        relevantContract: event.relevantContract,
+        blockNumber: event.blockNumber,
+        timestamp: event.timestamp,
+        eventType: event.eventType.clone(),
+        tags: proposal_tags(module, proposal_id, proposer, status),
+        relevantAddresses: event.relevantAddresses.clone(),
+        data: event.data.clone(),
+        metadata: event.metadata.clone(),
+        deleted: false,
+    })
+}
+
+/// Ids of the votes to delete with a cancelled proposal: its live indexed votes, minus those
+/// already deleted earlier in the batch, plus its votes added earlier in the batch.
+fn cancelled_vote_ids(
+    indexed_votes: Vec<FixedBytes<32>>,
+    pending: &PendingEvents,
+    proposal_tag: &str,
+) -> Vec<FixedBytes<32>> {
+    indexed_votes
+        .into_iter()
+        .filter(|id| !pending.is_deleted(id))
+        .chain(pending.find(VOTE_EVENT_TYPE, proposal_tag).map(|vote| vote.eventId))
+        .collect()
+}
+
+fn proposal_tags(
+    module: Address,
+    proposal_id: U256,
+    proposer: Address,
+    status: ProposalStatus,
+) -> Vec<String> {
+    let proposal = format!("gov:{}/proposal:{}", module, proposal_id);
+    vec![
+        format!("gov:{}", module),
+        format!("proposal:{}", proposal_id),
+        format!("proposer:{}", proposer),
+        status.tag(),
+        format!("gov:{}/proposer:{}", module, proposer),
+        format!("gov:{}/{}", module, status.tag()),
+        proposal,
+    ]
+}
+
+fn chain_id(event_data: &EventData) -> Result<String> {
+    get_evm_chain_config(&event_data.chain)
+        .map(|chain| chain.chain_id)
+        .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", event_data.chain))
+}
+
+mod solidity {
+    use alloy_sol_macro::sol;
+
+    sol!("../../src/interfaces/IMerkleGovModule.sol");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::{address, Bytes};
+    use std::collections::HashSet;
+
+    const MODULE: Address = address!("0x1111111111111111111111111111111111111111");
+    const PROPOSER: Address = address!("0x2222222222222222222222222222222222222222");
+
+    fn event(id: u8, event_type: &str, tags: Vec<String>) -> IndexedEvent {
+        IndexedEvent {
+            eventId: FixedBytes::repeat_byte(id),
+            chainId: "31337".to_string(),
+            relevantContract: MODULE,
+            blockNumber: U256::from(7),
+            timestamp: 1_700_000_000,
+            eventType: event_type.to_string(),
+            tags,
+            relevantAddresses: vec![PROPOSER],
+            data: Bytes::from(vec![1, 2, 3]),
+            metadata: Bytes::new(),
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn test_vote_type_support() {
+        assert_eq!(support(VoteType::No).unwrap(), "no");
+        assert_eq!(support(VoteType::Yes).unwrap(), "yes");
+        assert_eq!(support(VoteType::Abstain).unwrap(), "abstain");
+        assert!(support(VoteType::__Invalid).is_err());
+
+        let tags = vote_tags(MODULE, U256::from(3), PROPOSER, "yes");
+        assert!(tags.contains(&format!("gov:{}/proposal:3/support:yes", MODULE)));
+        assert!(tags.contains(&format!("gov:{}/proposal:3/voter:{}", MODULE, PROPOSER)));
+    }
+
+    #[test]
+    fn test_revised_proposal_tags() {
+        let created = event(
+            1,
+            PROPOSAL_EVENT_TYPE,
+            proposal_tags(MODULE, U256::from(3), PROPOSER, ProposalStatus::Created),
+        );
+        let revised =
+            revised_proposal(&created, MODULE, U256::from(3), ProposalStatus::Executed).unwrap();
+
+        assert_eq!(revised.eventId, FixedBytes::ZERO);
+        assert_eq!(revised.data, created.data);
+        assert_eq!(revised.timestamp, created.timestamp);
+        assert!(revised.tags.contains(&"status:executed".to_string()));
+        assert!(revised.tags.contains(&format!("gov:{}/status:executed", MODULE)));
+        assert!(!revised.tags.contains(&"status:created".to_string()));
+        // The proposal can still be found by the tag revisions look it up by
+        assert!(revised.tags.contains(&format!("gov:{}/proposal:3", MODULE)));
+
+        let mut no_proposer = created.clone();
+        no_proposer.relevantAddresses.clear();
+        assert!(revised_proposal(&no_proposer, MODULE, U256::from(3), ProposalStatus::Cancelled)
+            .is_err());
+    }
+
+    #[test]
+    fn test_cancelled_proposal_deletes_its_votes() {
+        let proposal_tag = format!("gov:{}/proposal:3", MODULE);
+        let vote = |id| event(id, VOTE_EVENT_TYPE, vec![proposal_tag.clone()]);
+        let pending = PendingEvents {
+            added: vec![
+                vote(4),
+                vote(5),
+                event(6, VOTE_EVENT_TYPE, vec![format!("gov:{}/proposal:9", MODULE)]),
+            ],
+            // Vote 2 was indexed and vote 5 added earlier in the batch, both already deleted
+            deleted: HashSet::from([FixedBytes::repeat_byte(2), FixedBytes::repeat_byte(5)]),
+        };
+
+        let indexed_votes = vec![FixedBytes::repeat_byte(1), FixedBytes::repeat_byte(2)];
+        assert_eq!(
+            cancelled_vote_ids(indexed_votes, &pending, &proposal_tag),
+            vec![FixedBytes::repeat_byte(1), FixedBytes::repeat_byte(4)]
+        );
+    }
+}
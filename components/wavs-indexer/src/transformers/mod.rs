@@ -1,9 +1,21 @@
 pub mod attestation;
+pub mod declarative;
+pub mod governance;
+pub mod transfer;
 
-use crate::solidity::IndexingPayload;
+use self::declarative::DeclarativeTransformer;
+use crate::bindings::host;
+use crate::bindings::wavs::types::service::{AggregatorSubmit, Submit};
+use crate::reorg::SourceBlock;
+use crate::solidity::{IndexedEvent, IndexingPayload};
 use crate::trigger::EventData;
-use alloy_primitives::FixedBytes;
+use alloy_primitives::{keccak256, Address, Bytes, FixedBytes, U256};
+use alloy_sol_types::SolValue;
+use alloy_transport::TransportError;
 use anyhow::Result;
+use serde_json::json;
+use std::collections::HashSet;
+use wavs_indexer_api::query::WavsIndexerQuerier;
 
 /// Static transformer function type
 pub type TransformerFn = fn(&FixedBytes<32>) -> bool;
@@ -39,20 +51,239 @@ pub trait EventTransformer {
     fn name() -> &'static str;
 }
 
-/// Registry of static transformers
+/// Address of the WAVS indexer the workflow submits to
+pub fn indexer_address() -> Result<Address> {
+    Ok(match host::get_workflow().workflow.submit {
+        Submit::Aggregator(AggregatorSubmit { component, .. }) => component
+            .config
+            .first()
+            .ok_or(anyhow::anyhow!("Aggregator config not found"))?
+            .1
+            .parse()?,
+        _ => host::config_var("indexer_address")
+            .ok_or(anyhow::anyhow!(
+                "Failed to get indexer address from workflow submit config and config_var"
+            ))?
+            .parse()?,
+    })
+}
+
+/// Create a querier for the WAVS indexer the workflow submits to, for transformers that revise
+/// already indexed events.
+pub async fn indexer_querier(chain: &str) -> Result<WavsIndexerQuerier> {
+    let wavs_indexer_address = indexer_address()?;
+
+    let chain = host::get_evm_chain_config(chain)
+        .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", chain))?;
+    let http_endpoint = chain
+        .http_endpoint
+        .ok_or_else(|| anyhow::anyhow!("No HTTP endpoint configured for {}", chain.chain_id))?;
+
+    WavsIndexerQuerier::new(wavs_indexer_address, http_endpoint)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to create indexer querier: {}", e))
+}
+
+/// Deterministic id of an event indexed from a log, so indexing the log again is detected. The
+/// block hash is included so a transaction included again after a reorg gets new ids.
+/// Transformers may set their own id instead.
+pub fn event_id(
+    chain_id: &str,
+    block_hash: FixedBytes<32>,
+    tx_hash: FixedBytes<32>,
+    log_index: u64,
+    event_type: &str,
+) -> FixedBytes<32> {
+    keccak256(
+        (chain_id.to_string(), block_hash, tx_hash, U256::from(log_index), event_type.to_string())
+            .abi_encode_params(),
+    )
+}
+
+/// Events added and deleted by the logs transformed earlier in a batch, which aren't on chain
+/// yet. Transformers revising indexed events look them up here before the indexer.
+#[derive(Debug, Clone, Default)]
+pub struct PendingEvents {
+    pub added: Vec<IndexedEvent>,
+    pub deleted: HashSet<FixedBytes<32>>,
+}
+
+impl PendingEvents {
+    pub fn is_added(&self, event_id: &FixedBytes<32>) -> bool {
+        self.added.iter().any(|event| event.eventId == *event_id)
+    }
+
+    pub fn is_deleted(&self, event_id: &FixedBytes<32>) -> bool {
+        self.deleted.contains(event_id)
+    }
+
+    /// Pending events of a type with a tag that aren't deleted, newest first
+    pub fn find<'a>(
+        &'a self,
+        event_type: &'a str,
+        tag: &'a str,
+    ) -> impl Iterator<Item = &'a IndexedEvent> + 'a {
+        self.added.iter().rev().filter(move |event| {
+            event.eventType == event_type
+                && event.tags.iter().any(|t| t == tag)
+                && !self.is_deleted(&event.eventId)
+        })
+    }
+}
+
+/// What the registry does when a transformer fails
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Fail the whole event, so nothing of it is indexed
+    FailFast,
+    /// Log the failure and index what the other transformers produced
+    BestEffort,
+    /// Index an `indexing_error` event with the failure instead, so it can be found and replayed
+    Quarantine,
+}
+
+impl ErrorPolicy {
+    pub fn load() -> Result<Self> {
+        Self::parse(
+            host::config_var("transformer_error_policy").as_deref().unwrap_or("best_effort"),
+        )
+    }
+
+    fn parse(policy: &str) -> Result<Self> {
+        match policy {
+            "fail_fast" => Ok(Self::FailFast),
+            "best_effort" => Ok(Self::BestEffort),
+            "quarantine" => Ok(Self::Quarantine),
+            other => Err(anyhow::anyhow!("Invalid transformer_error_policy: {}", other)),
+        }
+    }
+}
+
+/// Whether an error is a failed request to a node, which may succeed when retried, rather than a
+/// failure that repeats on every attempt, like a log that doesn't decode or a reverted call.
+pub fn is_transport_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let rpc_error = match cause.downcast_ref::<alloy_contract::Error>() {
+            Some(alloy_contract::Error::TransportError(e)) => Some(e),
+            _ => cause.downcast_ref::<TransportError>(),
+        };
+        rpc_error.is_some_and(|e| {
+            e.is_transport_error()
+                || e.is_null_resp()
+                || e.as_error_resp().is_some_and(|resp| !resp.message.contains("revert"))
+        })
+    })
+}
+
+/// Event type of the events quarantining a failed transformation
+pub const INDEXING_ERROR_EVENT_TYPE: &str = "indexing_error";
+
+/// Chain id of a chain. Chains without a config, like the one of CLI test triggers, keep their
+/// name.
+pub fn log_chain_id(chain: &str) -> String {
+    host::get_evm_chain_config(chain)
+        .map(|chain| chain.chain_id)
+        .unwrap_or_else(|| chain.to_string())
+}
+
+/// Event recording that a transformer failed on a log. It keeps the ABI-encoded topics and data
+/// of the log in `data`, so the log can be replayed once the transformer is fixed.
+pub fn error_event(
+    chain_id: &str,
+    transformer: &str,
+    error: &anyhow::Error,
+    event_signature: &FixedBytes<32>,
+    event_data: &EventData,
+) -> Result<IndexedEvent> {
+    let topics: Vec<FixedBytes<32>> =
+        event_data.log.topics.iter().map(|topic| FixedBytes::from_slice(topic)).collect();
+    let metadata = json!({
+        "transformer": transformer,
+        "error": error.to_string(),
+    });
+
+    Ok(IndexedEvent {
+        // One event per failed transformer, so several failing on a log don't collide
+        eventId: event_id(
+            chain_id,
+            event_data.block_hash,
+            event_data.tx_hash,
+            event_data.log_index,
+            &format!("{}:{}", INDEXING_ERROR_EVENT_TYPE, transformer),
+        ),
+        chainId: chain_id.to_string(),
+        relevantContract: event_data.contract_address,
+        blockNumber: U256::from(event_data.block_number),
+        timestamp: event_data.block_timestamp as u128,
+        eventType: INDEXING_ERROR_EVENT_TYPE.to_string(),
+        tags: vec![
+            format!("transformer:{}", transformer),
+            format!("signature:{}", event_signature),
+            format!("contract:{}", event_data.contract_address),
+        ],
+        relevantAddresses: Vec::new(),
+        data: (topics, Bytes::from(event_data.log.data.clone())).abi_encode_params().into(),
+        metadata: serde_json::to_vec(&metadata)?.into(),
+        deleted: false,
+    })
+}
+
+/// Record the log the events were indexed from: every event gets its block in its metadata and,
+/// unless set by its transformer, a deterministic id. Events other than `indexing_error` are also
+/// tagged with the log, so a backfill doesn't skip a log that only failed.
+pub fn add_source(events: &mut [IndexedEvent], event_data: &EventData) -> Result<()> {
+    let source_block = SourceBlock { number: event_data.block_number, hash: event_data.block_hash };
+    for event in events {
+        if event.eventType != INDEXING_ERROR_EVENT_TYPE {
+            event.tags.push(event_data.source_tag());
+        }
+        event.metadata = source_block.add_to_metadata(&event.metadata)?;
+        if event.eventId.is_zero() {
+            event.eventId = event_id(
+                &event.chainId,
+                source_block.hash,
+                event_data.tx_hash,
+                event_data.log_index,
+                &event.eventType,
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Registry of static and declarative transformers
 pub struct TransformerRegistry {
     transformers: Vec<StaticTransformer>,
+    declarative: Vec<DeclarativeTransformer>,
+    error_policy: ErrorPolicy,
 }
 
 impl TransformerRegistry {
     pub fn new() -> Self {
-        Self { transformers: Vec::new() }
+        Self {
+            transformers: Vec::new(),
+            declarative: Vec::new(),
+            error_policy: ErrorPolicy::BestEffort,
+        }
+    }
+
+    pub fn error_policy(&self) -> ErrorPolicy {
+        self.error_policy
     }
 
     fn register(&mut self, transformer: StaticTransformer) {
         self.transformers.push(transformer);
     }
 
+    /// Whether any registered transformer supports the event
+    pub fn supports_event(&self, event_signature: &FixedBytes<32>) -> bool {
+        self.transformers.iter().any(|transformer| (transformer.supports_event)(event_signature))
+            || self
+                .declarative
+                .iter()
+                .any(|transformer| transformer.supports_event(event_signature))
+    }
+
     pub async fn transform_event(
         &self,
         event_signature: &FixedBytes<32>,
@@ -60,17 +291,46 @@ impl TransformerRegistry {
     ) -> Result<IndexingPayload> {
         let mut payload = IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() };
 
-        let mut not_found = true;
+        let mut results = Vec::new();
         for transformer in &self.transformers {
             if (transformer.supports_event)(event_signature) {
-                not_found = false;
-                match (transformer.transform)(event_signature.clone(), event_data.clone()).await {
-                    Ok(p) => {
-                        payload.toAdd.extend(p.toAdd);
-                        payload.toDelete.extend(p.toDelete);
-                    }
-                    Err(e) => println!("Transformer {} failed: {}", transformer.name, e),
+                let result =
+                    (transformer.transform)(event_signature.clone(), event_data.clone()).await;
+                results.push((transformer.name.to_string(), result));
+            }
+        }
+        for transformer in &self.declarative {
+            if transformer.supports_event(event_signature) {
+                results.push((transformer.name(), transformer.transform(&event_data)));
+            }
+        }
+
+        let not_found = results.is_empty();
+        for (name, result) in results {
+            match result {
+                Ok(p) => {
+                    payload.toAdd.extend(p.toAdd);
+                    payload.toDelete.extend(p.toDelete);
                 }
+                Err(e) => match self.error_policy {
+                    ErrorPolicy::FailFast => {
+                        // Keep the cause, so transport errors can be told apart
+                        let message = format!("Transformer {} failed: {}", name, e);
+                        return Err(e.context(message));
+                    }
+                    ErrorPolicy::BestEffort => println!("Transformer {} failed: {}", name, e),
+                    ErrorPolicy::Quarantine => {
+                        println!("Transformer {} failed, quarantining the event: {}", name, e);
+                        let chain_id = log_chain_id(&event_data.chain);
+                        payload.toAdd.push(error_event(
+                            &chain_id,
+                            &name,
+                            &e,
+                            event_signature,
+                            &event_data,
+                        )?);
+                    }
+                },
             }
         }
 
@@ -79,19 +339,23 @@ impl TransformerRegistry {
         } else if payload.toAdd.is_empty() && payload.toDelete.is_empty() {
             Err(anyhow::anyhow!("No index updates were applied for event: {}", event_signature))
         } else {
+            add_source(&mut payload.toAdd, &event_data)?;
             Ok(payload)
         }
     }
 
-    /// Register all available transformers automatically.
-    pub fn all() -> Self {
+    /// Register all available transformers automatically, and the declarative transformers of
+    /// the `declarative_transformers` config variable, with the `transformer_error_policy`.
+    pub fn all() -> Result<Self> {
         let mut registry = Self::new();
 
         for transformer_factory in inventory::iter::<TransformerFactory> {
             registry.register(transformer_factory.0());
         }
+        registry.declarative = DeclarativeTransformer::load()?;
+        registry.error_policy = ErrorPolicy::load()?;
 
-        registry
+        Ok(registry)
     }
 }
 
@@ -110,3 +374,153 @@ macro_rules! register_transformer {
         }));
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bindings::wavs::types::chain::EvmEventLogData;
+    use alloy_primitives::address;
+    use std::rc::Rc;
+
+    fn event_data() -> EventData {
+        EventData {
+            contract_address: address!("0x3333333333333333333333333333333333333333"),
+            log: EvmEventLogData {
+                topics: vec![vec![0xaa; 32], vec![0xbb; 32]],
+                data: vec![1, 2, 3],
+            },
+            block_number: 7,
+            block_timestamp: 1_700_000_000,
+            chain: "local".to_string(),
+            tx_hash: FixedBytes::repeat_byte(0x11),
+            log_index: 2,
+            block_hash: FixedBytes::repeat_byte(0x22),
+            pending: Rc::default(),
+        }
+    }
+
+    #[test]
+    fn test_transport_errors() {
+        use alloy_transport::TransportErrorKind;
+
+        assert!(is_transport_error(&TransportErrorKind::custom_str("connection reset").into()));
+        assert!(is_transport_error(&TransportError::NullResp.into()));
+        let call_error = alloy_contract::Error::TransportError(TransportErrorKind::backend_gone());
+        assert!(is_transport_error(&anyhow::Error::new(call_error).context("Transformer failed")));
+
+        let deser_error = serde_json::from_str::<u64>("x").unwrap_err();
+        assert!(!is_transport_error(&TransportError::deser_err(deser_error, "x").into()));
+        assert!(!is_transport_error(&anyhow::anyhow!("Failed to decode Transfer")));
+    }
+
+    #[test]
+    fn test_error_policy_parse() {
+        assert_eq!(ErrorPolicy::parse("fail_fast").unwrap(), ErrorPolicy::FailFast);
+        assert_eq!(ErrorPolicy::parse("best_effort").unwrap(), ErrorPolicy::BestEffort);
+        assert_eq!(ErrorPolicy::parse("quarantine").unwrap(), ErrorPolicy::Quarantine);
+        assert!(ErrorPolicy::parse("Quarantine").is_err());
+        assert!(ErrorPolicy::parse("").is_err());
+    }
+
+    #[test]
+    fn test_error_event_keeps_the_log() {
+        let data = event_data();
+        let signature = FixedBytes::repeat_byte(0xaa);
+        let error = anyhow::anyhow!("decoding failed");
+        let event = error_event("31337", "VoteTransformer", &error, &signature, &data).unwrap();
+
+        assert_eq!(event.eventType, INDEXING_ERROR_EVENT_TYPE);
+        assert_eq!(event.chainId, "31337");
+        assert_eq!(event.blockNumber, U256::from(7));
+        assert_eq!(
+            event.tags,
+            vec![
+                "transformer:VoteTransformer".to_string(),
+                format!("signature:{}", signature),
+                format!("contract:{}", data.contract_address),
+            ]
+        );
+
+        let (topics, log_data) =
+            <(Vec<FixedBytes<32>>, Bytes)>::abi_decode_params(&event.data).unwrap();
+        assert_eq!(topics, vec![FixedBytes::repeat_byte(0xaa), FixedBytes::repeat_byte(0xbb)]);
+        assert_eq!(log_data, Bytes::from(vec![1, 2, 3]));
+
+        let metadata: serde_json::Value = serde_json::from_slice(&event.metadata).unwrap();
+        assert_eq!(metadata["transformer"], "VoteTransformer");
+        assert_eq!(metadata["error"], "decoding failed");
+
+        // Failures of different transformers on the same log get different ids
+        let other = error_event("31337", "Other", &error, &signature, &data).unwrap();
+        assert_ne!(event.eventId, other.eventId);
+        assert_eq!(
+            event.eventId,
+            error_event("31337", "VoteTransformer", &error, &signature, &data).unwrap().eventId
+        );
+    }
+
+    #[test]
+    fn test_add_source_leaves_error_events_untagged() {
+        let data = event_data();
+        let error = anyhow::anyhow!("failed");
+        let mut events = vec![
+            event(0, "proposal_vote", &["voter:a"]),
+            error_event("31337", "Other", &error, &FixedBytes::ZERO, &data).unwrap(),
+        ];
+        let error_id = events[1].eventId;
+        add_source(&mut events, &data).unwrap();
+
+        assert_eq!(events[0].tags, vec!["voter:a".to_string(), data.source_tag()]);
+        assert_eq!(
+            events[0].eventId,
+            event_id("31337", data.block_hash, data.tx_hash, 2, "proposal_vote")
+        );
+        assert!(!events[1].tags.contains(&data.source_tag()));
+        assert_eq!(events[1].eventId, error_id);
+
+        let block = SourceBlock { number: 7, hash: data.block_hash };
+        for event in &events {
+            assert_eq!(SourceBlock::from_metadata(&event.metadata), Some(block));
+        }
+    }
+
+    fn event(id: u8, event_type: &str, tags: &[&str]) -> IndexedEvent {
+        IndexedEvent {
+            eventId: FixedBytes::repeat_byte(id),
+            chainId: "31337".to_string(),
+            relevantContract: Address::ZERO,
+            blockNumber: U256::from(1),
+            timestamp: 0,
+            eventType: event_type.to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            relevantAddresses: Vec::new(),
+            data: Bytes::new(),
+            metadata: Bytes::new(),
+            deleted: false,
+        }
+    }
+
+    #[test]
+    fn test_pending_events_find_newest_live_events() {
+        let pending = PendingEvents {
+            added: vec![
+                event(1, "proposal", &["gov:a/proposal:1", "status:created"]),
+                event(2, "proposal_vote", &["gov:a/proposal:1"]),
+                event(3, "proposal", &["gov:a/proposal:1", "status:executed"]),
+                event(4, "proposal", &["gov:a/proposal:2"]),
+            ],
+            deleted: HashSet::from([FixedBytes::repeat_byte(1)]),
+        };
+
+        let ids = |event_type, tag| -> Vec<FixedBytes<32>> {
+            pending.find(event_type, tag).map(|event| event.eventId).collect()
+        };
+        assert_eq!(ids("proposal", "gov:a/proposal:1"), vec![FixedBytes::repeat_byte(3)]);
+        assert_eq!(ids("proposal_vote", "gov:a/proposal:1"), vec![FixedBytes::repeat_byte(2)]);
+        assert!(ids("proposal", "gov:a/proposal:3").is_empty());
+
+        assert!(pending.is_added(&FixedBytes::repeat_byte(1)));
+        assert!(pending.is_deleted(&FixedBytes::repeat_byte(1)));
+        assert!(!pending.is_added(&FixedBytes::repeat_byte(5)));
+    }
+}
@@ -0,0 +1,255 @@
+use super::EventTransformer;
+use crate::bindings::host::get_evm_chain_config;
+use crate::bindings::wavs::types::chain::EvmEventLogData;
+use crate::register_transformer;
+use crate::solidity::{IndexedEvent, IndexingPayload};
+use crate::trigger::EventData;
+use alloy_primitives::{Address, Bytes, FixedBytes, U256};
+use alloy_sol_types::{SolEvent, SolValue};
+use anyhow::Result;
+
+use self::solidity::{Erc20, Erc721};
+
+/// Indexes ERC-20 `Transfer` events, with the transferred value as the event data.
+pub struct Erc20TransferTransformer;
+register_transformer!(Erc20TransferTransformer);
+
+/// Indexes ERC-721 `Transfer` events, with the token id as the event data.
+pub struct Erc721TransferTransformer;
+register_transformer!(Erc721TransferTransformer);
+
+/// ERC-20 and ERC-721 `Transfer` share a signature. ERC-721 indexes the token id, so its logs have
+/// four topics and ERC-20 logs three.
+const ERC20_TRANSFER_TOPICS: usize = 3;
+const ERC721_TRANSFER_TOPICS: usize = 4;
+
+impl EventTransformer for Erc20TransferTransformer {
+    fn name() -> &'static str {
+        "Erc20TransferTransformer"
+    }
+
+    fn supports_event(event_signature: &FixedBytes<32>) -> bool {
+        *event_signature == Erc20::Transfer::SIGNATURE_HASH
+    }
+
+    async fn transform(
+        _event_signature: FixedBytes<32>,
+        event_data: EventData,
+    ) -> Result<IndexingPayload> {
+        // Leave ERC-721 transfers to the ERC-721 transformer
+        let Some(transfer) =
+            decode_transfer::<Erc20::Transfer>(&event_data.log, ERC20_TRANSFER_TOPICS)?
+        else {
+            return Ok(IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() });
+        };
+
+        println!(
+            "Transforming ERC-20 Transfer event: token={}, from={}, to={}, value={}",
+            event_data.contract_address, transfer.from, transfer.to, transfer.value
+        );
+
+        let indexed_event = transfer_event(
+            &event_data,
+            "erc20_transfer",
+            transfer.from,
+            transfer.to,
+            Vec::new(),
+            transfer.value.abi_encode().into(),
+        )?;
+
+        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: Vec::new() })
+    }
+}
+
+impl EventTransformer for Erc721TransferTransformer {
+    fn name() -> &'static str {
+        "Erc721TransferTransformer"
+    }
+
+    fn supports_event(event_signature: &FixedBytes<32>) -> bool {
+        *event_signature == Erc721::Transfer::SIGNATURE_HASH
+    }
+
+    async fn transform(
+        _event_signature: FixedBytes<32>,
+        event_data: EventData,
+    ) -> Result<IndexingPayload> {
+        // Leave ERC-20 transfers to the ERC-20 transformer
+        let Some(transfer) =
+            decode_transfer::<Erc721::Transfer>(&event_data.log, ERC721_TRANSFER_TOPICS)?
+        else {
+            return Ok(IndexingPayload { toAdd: Vec::new(), toDelete: Vec::new() });
+        };
+
+        println!(
+            "Transforming ERC-721 Transfer event: token={}, from={}, to={}, tokenId={}",
+            event_data.contract_address, transfer.from, transfer.to, transfer.tokenId
+        );
+
+        let token = event_data.contract_address;
+        let indexed_event = transfer_event(
+            &event_data,
+            "erc721_transfer",
+            transfer.from,
+            transfer.to,
+            vec![
+                format!("tokenId:{}", transfer.tokenId),
+                format!("token:{}/tokenId:{}", token, transfer.tokenId),
+            ],
+            transfer.tokenId.abi_encode().into(),
+        )?;
+
+        Ok(IndexingPayload { toAdd: vec![indexed_event], toDelete: Vec::new() })
+    }
+}
+
+/// Decode a `Transfer` log with the topic count of one standard, or None if it has the topic count
+/// of the other.
+fn decode_transfer<E: SolEvent>(log: &EvmEventLogData, topics: usize) -> Result<Option<E>> {
+    if log.topics.len() != topics {
+        return Ok(None);
+    }
+
+    let topics = log
+        .topics
+        .iter()
+        .map(|topic| FixedBytes::<32>::try_from(topic.as_slice()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid {} topic: {}", E::SIGNATURE, e))?;
+    let transfer = E::decode_raw_log(topics, &log.data)
+        .map_err(|e| anyhow::anyhow!("Failed to decode {}: {}", E::SIGNATURE, e))?;
+
+    Ok(Some(transfer))
+}
+
+/// Build the indexed event of a transfer of the token emitting the event. Mints are transfers
+/// from, and burns transfers to, the zero address.
+fn transfer_event(
+    event_data: &EventData,
+    event_type: &str,
+    from: Address,
+    to: Address,
+    extra_tags: Vec<String>,
+    data: Bytes,
+) -> Result<IndexedEvent> {
+    let chain = get_evm_chain_config(&event_data.chain)
+        .ok_or_else(|| anyhow::anyhow!("Chain config not found for {}", event_data.chain))?;
+    let token = event_data.contract_address;
+
+    let mut tags = vec![
+        format!("token:{}", token),
+        format!("from:{}", from),
+        format!("to:{}", to),
+        format!("token:{}/from:{}", token, from),
+        format!("token:{}/to:{}", token, to),
+    ];
+    tags.extend(extra_tags);
+
+    Ok(IndexedEvent {
+        eventId: FixedBytes::ZERO,
+        chainId: chain.chain_id,
+        relevantContract: token,
+        blockNumber: U256::from(event_data.block_number),
+        timestamp: event_data.block_timestamp as u128,
+        eventType: event_type.to_string(),
+        tags,
+        relevantAddresses: vec![from, to],
+        data,
+        metadata: Vec::new().into(),
+        deleted: false,
+    })
+}
+
+mod solidity {
+    use alloy_sol_macro::sol;
+
+    sol! {
+        interface Erc20 {
+            event Transfer(address indexed from, address indexed to, uint256 value);
+        }
+
+        interface Erc721 {
+            event Transfer(address indexed from, address indexed to, uint256 indexed tokenId);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_primitives::address;
+
+    const FROM: Address = address!("0x1111111111111111111111111111111111111111");
+    const TO: Address = address!("0x2222222222222222222222222222222222222222");
+
+    fn log(topics: Vec<FixedBytes<32>>, data: Vec<u8>) -> EvmEventLogData {
+        EvmEventLogData { topics: topics.iter().map(|topic| topic.to_vec()).collect(), data }
+    }
+
+    fn erc20_log() -> EvmEventLogData {
+        log(
+            vec![Erc20::Transfer::SIGNATURE_HASH, FROM.into_word(), TO.into_word()],
+            U256::from(1_000).abi_encode(),
+        )
+    }
+
+    fn erc721_log() -> EvmEventLogData {
+        log(
+            vec![
+                Erc721::Transfer::SIGNATURE_HASH,
+                FROM.into_word(),
+                TO.into_word(),
+                U256::from(7).into(),
+            ],
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn test_decode_erc20_transfer() {
+        let transfer = decode_transfer::<Erc20::Transfer>(&erc20_log(), ERC20_TRANSFER_TOPICS)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(transfer.from, FROM);
+        assert_eq!(transfer.to, TO);
+        assert_eq!(transfer.value, U256::from(1_000));
+        // The ERC-721 transformer leaves it to the ERC-20 one
+        assert!(decode_transfer::<Erc721::Transfer>(&erc20_log(), ERC721_TRANSFER_TOPICS)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_decode_erc721_transfer() {
+        let transfer = decode_transfer::<Erc721::Transfer>(&erc721_log(), ERC721_TRANSFER_TOPICS)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(transfer.from, FROM);
+        assert_eq!(transfer.to, TO);
+        assert_eq!(transfer.tokenId, U256::from(7));
+        // The ERC-20 transformer leaves it to the ERC-721 one
+        assert!(decode_transfer::<Erc20::Transfer>(&erc721_log(), ERC20_TRANSFER_TOPICS)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_decode_malformed_transfer() {
+        // Value missing from the data
+        let mut truncated = erc20_log();
+        truncated.data.truncate(16);
+        assert!(decode_transfer::<Erc20::Transfer>(&truncated, ERC20_TRANSFER_TOPICS).is_err());
+
+        // Topic that isn't 32 bytes long
+        let mut short_topic = erc20_log();
+        short_topic.topics[1].truncate(20);
+        assert!(decode_transfer::<Erc20::Transfer>(&short_topic, ERC20_TRANSFER_TOPICS).is_err());
+
+        // Another event with the same topic count
+        let mut other_event = erc20_log();
+        other_event.topics[0] = vec![0xaa; 32];
+        assert!(decode_transfer::<Erc20::Transfer>(&other_event, ERC20_TRANSFER_TOPICS).is_err());
+    }
+}
@@ -1,12 +1,14 @@
 use crate::bindings::wavs::operator::input::TriggerData;
 use crate::bindings::wavs::types::chain::EvmEventLogData;
-use crate::bindings::wavs::types::events::TriggerDataEvmContractEvent;
+use crate::bindings::wavs::types::events::{TriggerDataBlockInterval, TriggerDataEvmContractEvent};
 use crate::bindings::{TriggerAction, WasmResponse};
 use crate::solidity::IndexingPayload;
-use alloy_primitives::Address as AlloyAddress;
+use crate::transformers::PendingEvents;
+use alloy_primitives::{Address as AlloyAddress, FixedBytes};
 use alloy_provider::Provider;
 use alloy_sol_types::SolValue;
 use anyhow::Result;
+use std::rc::Rc;
 
 /// Represents the destination where the trigger output should be sent
 pub enum Destination {
@@ -16,6 +18,14 @@ pub enum Destination {
     CliOutput,
 }
 
+/// What a trigger asks the indexer to do
+pub enum TriggerInput {
+    /// Index a single event
+    Event(EventData),
+    /// Index every supported log of the batch contracts up to a block
+    Batch { chain: String, block_height: u64 },
+}
+
 /// Simplified event data structure for transformers
 #[derive(Debug, Clone)]
 pub struct EventData {
@@ -24,10 +34,26 @@ pub struct EventData {
     pub block_number: u64,
     pub block_timestamp: u64,
     pub chain: String,
+    /// Transaction that emitted the log
+    pub tx_hash: FixedBytes<32>,
+    /// Index of the log in its block
+    pub log_index: u64,
+    /// Block the log was emitted in, recorded so reorgs can be detected
+    pub block_hash: FixedBytes<32>,
+    /// Events of the logs before this one in its batch, empty outside batches
+    pub pending: Rc<PendingEvents>,
+}
+
+impl EventData {
+    /// Tag added to every event indexed from this log, so the log can be recognized as already
+    /// indexed
+    pub fn source_tag(&self) -> String {
+        format!("tx:{}/log:{}", self.tx_hash, self.log_index)
+    }
 }
 
 /// Decodes incoming trigger event data
-pub async fn decode_trigger_event(action: TriggerAction) -> Result<(EventData, Destination)> {
+pub async fn decode_trigger_event(action: TriggerAction) -> Result<(TriggerInput, Destination)> {
     match &action.data {
         TriggerData::EvmContractEvent(TriggerDataEvmContractEvent { chain, log }) => {
             // Convert the EvmAddress to AlloyAddress
@@ -44,9 +70,19 @@ pub async fn decode_trigger_event(action: TriggerAction) -> Result<(EventData, D
                 block_number: log.block_number,
                 block_timestamp,
                 chain: chain.clone(),
+                tx_hash: FixedBytes::try_from(log.tx_hash.as_slice())
+                    .map_err(|e| anyhow::anyhow!("Invalid transaction hash: {}", e))?,
+                log_index: log.log_index,
+                block_hash: FixedBytes::try_from(log.block_hash.as_slice())
+                    .map_err(|e| anyhow::anyhow!("Invalid block hash: {}", e))?,
+                pending: Rc::default(),
             };
-            Ok((event_data, Destination::Ethereum))
+            Ok((TriggerInput::Event(event_data), Destination::Ethereum))
         }
+        TriggerData::BlockInterval(TriggerDataBlockInterval { chain, block_height }) => Ok((
+            TriggerInput::Batch { chain: chain.clone(), block_height: *block_height },
+            Destination::Ethereum,
+        )),
         TriggerData::Raw(data) => {
             // For CLI testing - create dummy event data
             let dummy_log = EvmEventLogData { topics: vec![], data: data.clone() };
@@ -57,8 +93,12 @@ pub async fn decode_trigger_event(action: TriggerAction) -> Result<(EventData, D
                 block_number: 0,
                 block_timestamp: 0,
                 chain: "test".to_string(),
+                tx_hash: FixedBytes::ZERO,
+                log_index: 0,
+                block_hash: FixedBytes::ZERO,
+                pending: Rc::default(),
             };
-            Ok((event_data, Destination::CliOutput))
+            Ok((TriggerInput::Event(event_data), Destination::CliOutput))
         }
         _ => Err(anyhow::anyhow!("Unsupported trigger data type")),
     }
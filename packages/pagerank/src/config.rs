@@ -1,5 +1,5 @@
 use alloy_primitives::Address;
-use std::collections::HashSet;
+use std::{collections::HashSet, fmt, str::FromStr};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 /// Trust configuration for Trust Aware PageRank
@@ -196,3 +196,85 @@ impl PageRankConfig {
         self.trust_config.clone()
     }
 }
+
+/// How the graph handles an attester vouching for itself
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelfEdgePolicy {
+    /// Drop the edge without adding its node, and count it as rejected
+    Reject,
+    /// Keep the edge in the graph but leave it out of the PageRank calculation
+    #[default]
+    Ignore,
+    /// Let the edge contribute to the node's own score
+    Count,
+}
+
+impl FromStr for SelfEdgePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "reject" => Ok(Self::Reject),
+            "ignore" => Ok(Self::Ignore),
+            "count" => Ok(Self::Count),
+            _ => Err(format!("Unknown self-edge policy: {} (expected reject, ignore or count)", s)),
+        }
+    }
+}
+
+impl fmt::Display for SelfEdgePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Reject => write!(f, "reject"),
+            Self::Ignore => write!(f, "ignore"),
+            Self::Count => write!(f, "count"),
+        }
+    }
+}
+
+/// How the graph combines several edges between the same attester and recipient
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DuplicatePolicy {
+    /// Keep every edge as a separate parallel edge
+    #[default]
+    KeepAll,
+    /// The last edge added replaces the previous ones
+    LatestWins,
+    /// Keep the highest weight
+    Max,
+    /// Average the weights of all edges
+    Average,
+    /// Sum the weights of all edges, up to a cap
+    SumWithCap(f64),
+}
+
+impl DuplicatePolicy {
+    /// Parse a policy by name. The cap is required by, and only used for, `sum`.
+    pub fn parse(name: &str, cap: Option<f64>) -> Result<Self, String> {
+        match name {
+            "keep_all" => Ok(Self::KeepAll),
+            "latest" => Ok(Self::LatestWins),
+            "max" => Ok(Self::Max),
+            "average" => Ok(Self::Average),
+            "sum" => cap
+                .map(Self::SumWithCap)
+                .ok_or_else(|| "The sum duplicate policy requires a cap".to_string()),
+            _ => Err(format!(
+                "Unknown duplicate policy: {} (expected keep_all, latest, max, average or sum)",
+                name
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DuplicatePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::KeepAll => write!(f, "keep_all"),
+            Self::LatestWins => write!(f, "latest"),
+            Self::Max => write!(f, "max"),
+            Self::Average => write!(f, "average"),
+            Self::SumWithCap(cap) => write!(f, "sum (cap {})", cap),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use alloy_primitives::{Address, U256};
 use std::{collections::HashMap, str::FromStr};
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::config::{PageRankConfig, TrustConfig};
+use crate::config::{DuplicatePolicy, PageRankConfig, SelfEdgePolicy, TrustConfig};
 
 /// A directed graph for Trust Aware PageRank computation
 #[wasm_bindgen]
@@ -14,8 +14,16 @@ pub struct PageRankGraphComputer {
     incoming: HashMap<Address, usize>,
     /// All nodes in the graph
     nodes: Vec<Address>,
-    /// Allow duplicates in edges
-    allow_duplicates: bool,
+    /// How self-edges are handled
+    self_edge_policy: SelfEdgePolicy,
+    /// How duplicate edges are combined
+    duplicate_policy: DuplicatePolicy,
+    /// Number of edges combined into each edge, for averaging
+    edge_counts: HashMap<(Address, Address), usize>,
+    /// Number of self-edges rejected
+    rejected_self_edges: usize,
+    /// Number of duplicate edges combined into an existing edge
+    merged_duplicates: usize,
 }
 
 #[wasm_bindgen]
@@ -84,19 +92,63 @@ impl PageRankGraphComputer {
             outgoing: HashMap::new(),
             incoming: HashMap::new(),
             nodes: Vec::new(),
-            allow_duplicates: true,
+            self_edge_policy: SelfEdgePolicy::default(),
+            duplicate_policy: DuplicatePolicy::default(),
+            edge_counts: HashMap::new(),
+            rejected_self_edges: 0,
+            merged_duplicates: 0,
         }
     }
 
-    /// Set allow duplicates in edges
-    pub fn with_allow_duplicates(mut self, allow_duplicates: bool) -> Self {
-        self.allow_duplicates = allow_duplicates;
+    /// Set allow duplicates in edges. Disallowing them lets the latest edge win.
+    pub fn with_allow_duplicates(self, allow_duplicates: bool) -> Self {
+        self.with_duplicate_policy(if allow_duplicates {
+            DuplicatePolicy::KeepAll
+        } else {
+            DuplicatePolicy::LatestWins
+        })
+    }
+
+    /// Set how self-edges are handled
+    pub fn with_self_edge_policy(mut self, self_edge_policy: SelfEdgePolicy) -> Self {
+        self.self_edge_policy = self_edge_policy;
         self
     }
 
+    /// Set how duplicate edges are combined
+    pub fn with_duplicate_policy(mut self, duplicate_policy: DuplicatePolicy) -> Self {
+        self.duplicate_policy = duplicate_policy;
+        self
+    }
+
+    /// How self-edges are handled
+    pub fn self_edge_policy(&self) -> SelfEdgePolicy {
+        self.self_edge_policy
+    }
+
+    /// How duplicate edges are combined
+    pub fn duplicate_policy(&self) -> DuplicatePolicy {
+        self.duplicate_policy
+    }
+
+    /// Number of self-edges rejected by the self-edge policy
+    pub fn rejected_self_edges(&self) -> usize {
+        self.rejected_self_edges
+    }
+
+    /// Number of duplicate edges combined into an existing edge by the duplicate policy
+    pub fn merged_duplicates(&self) -> usize {
+        self.merged_duplicates
+    }
+
     /// Add an edge from attester to recipient with base weight
     /// The actual weight will be adjusted based on trust configuration during PageRank calculation
     pub fn add_edge(&mut self, from: Address, to: Address, base_weight: f64) {
+        if from == to && self.self_edge_policy == SelfEdgePolicy::Reject {
+            self.rejected_self_edges += 1;
+            return;
+        }
+
         // Add nodes if they don't exist
         if !self.outgoing.contains_key(&from) {
             self.outgoing.insert(from, Vec::new());
@@ -113,17 +165,48 @@ impl PageRankGraphComputer {
 
         let outgoing = self.outgoing.get_mut(&from).unwrap();
 
-        // Override existing if allow_duplicates is false and the edge already exists, otherwise add the edge.
-        if let (false, Some(index)) =
-            (self.allow_duplicates, outgoing.iter().position(|(addr, _)| *addr == to))
-        {
-            outgoing[index] = (to, base_weight);
+        // Combine with the existing edge unless duplicates are kept, otherwise add the edge.
+        let existing = match self.duplicate_policy {
+            DuplicatePolicy::KeepAll => None,
+            _ => outgoing.iter().position(|(addr, _)| *addr == to),
+        };
+        if let Some(index) = existing {
+            let count = self.edge_counts.entry((from, to)).or_insert(1);
+            let current = outgoing[index].1;
+            outgoing[index].1 = match self.duplicate_policy {
+                DuplicatePolicy::KeepAll | DuplicatePolicy::LatestWins => base_weight,
+                DuplicatePolicy::Max => current.max(base_weight),
+                DuplicatePolicy::Average => {
+                    (current * *count as f64 + base_weight) / (*count + 1) as f64
+                }
+                DuplicatePolicy::SumWithCap(cap) => (current + base_weight).min(cap),
+            };
+            *count += 1;
+            self.merged_duplicates += 1;
         } else {
             outgoing.push((to, base_weight));
             *self.incoming.get_mut(&to).unwrap() += 1;
         }
     }
 
+    /// Remove the edges from attester to recipient, and what was combined into them. The nodes
+    /// stay in the graph. Returns the number of edges removed.
+    pub fn remove_edge(&mut self, from: Address, to: Address) -> usize {
+        let Some(outgoing) = self.outgoing.get_mut(&from) else {
+            return 0;
+        };
+
+        let before = outgoing.len();
+        outgoing.retain(|(addr, _)| *addr != to);
+        let removed = before - outgoing.len();
+        if let Some(incoming) = self.incoming.get_mut(&to) {
+            *incoming -= removed;
+        }
+        self.edge_counts.remove(&(from, to));
+
+        removed
+    }
+
     /// Sort the nodes and edges for deterministic iteration
     pub fn sort(&mut self) {
         self.nodes.sort();
@@ -135,6 +218,21 @@ impl PageRankGraphComputer {
         &self.nodes
     }
 
+    /// Get all edges as `(from, to, weight)`, sorted by `from` then `to`. Parallel edges kept by
+    /// the duplicate policy stay in the order they were added.
+    pub fn edges(&self) -> Vec<(Address, Address, f64)> {
+        let mut from_nodes: Vec<_> = self.outgoing.keys().copied().collect();
+        from_nodes.sort();
+        from_nodes
+            .into_iter()
+            .flat_map(|from| {
+                let mut edges = self.outgoing[&from].clone();
+                edges.sort_by_key(|(to, _)| *to);
+                edges.into_iter().map(move |(to, weight)| (from, to, weight))
+            })
+            .collect()
+    }
+
     /// Get outgoing edges from a node
     pub fn get_outgoing(&self, node: &Address) -> Option<&Vec<(Address, f64)>> {
         self.outgoing.get(node)
@@ -184,6 +282,8 @@ impl PageRankGraphComputer {
             None
         };
 
+        let count_self_edges = self.self_edge_policy == SelfEdgePolicy::Count;
+
         // Create sorted node list for deterministic iteration
         for iteration in 0..config.max_iterations {
             let mut max_delta = 0.0;
@@ -203,18 +303,19 @@ impl PageRankGraphComputer {
 
                 // Sum contributions from incoming edges with trust-aware weights
                 for attester_node in &self.nodes {
-                    // Skip the recipient node itself
-                    if attester_node == recipient_node {
+                    // Skip the recipient node itself, unless self-edges count
+                    if attester_node == recipient_node && !count_self_edges {
                         continue;
                     }
 
                     if let Some(outgoing_edges) = self.outgoing.get(attester_node) {
-                        // Filter out self-loops when calculating outgoing weights and zero-weight edges
+                        // Filter out self-loops (unless they count) and zero-weight edges when
+                        // calculating outgoing weights
                         let filtered_edges: Vec<_> = outgoing_edges
                             .iter()
                             .filter(|(target, base_weight)| {
-                                target != attester_node && *base_weight > 0.0
-                            }) // Exclude self-loops and zero-weight edges
+                                (count_self_edges || target != attester_node) && *base_weight > 0.0
+                            })
                             .collect();
 
                         // Node only has self-loops or zero-weight edges, skip it.
@@ -295,8 +396,9 @@ impl PageRankGraphComputer {
 
         println!("🎯 PageRank calculation completed");
 
-        // Normalize scores to ensure they sum to 1
-        let total_score: f64 = current_scores.values().sum();
+        // Normalize scores to ensure they sum to 1. Summed in node order, since the order of
+        // floating-point additions changes the result.
+        let total_score: f64 = self.nodes.iter().map(|node| current_scores[node]).sum();
         if total_score > 0.0 {
             current_scores.iter_mut().for_each(|(_, score)| *score /= total_score);
         }
@@ -342,9 +444,9 @@ impl PageRankGraphComputer {
             return (points_map, U256::ZERO);
         }
 
-        // Sort addresses by score (descending) for deterministic processing
+        // Sort addresses by score (descending), then address, for deterministic processing
         let mut sorted_scores = scaled_scores;
-        sorted_scores.sort_by(|a, b| b.1.cmp(&a.1));
+        sorted_scores.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
 
         let mut total_distributed = U256::ZERO;
         let mut remaining_pool = total_pool;
@@ -513,7 +615,10 @@ impl PageRankGraphComputer {
             if regular_count > 0 { regular_total_score / regular_count as f64 } else { 0.0 }
         );
         println!("  🚫 Isolated nodes: {} (unreachable from trusted seeds)", isolated_count);
-        println!("  🔄 Self-vouching nodes: {} (ignored in calculation)", self_vouching_count);
+        println!(
+            "  🔄 Self-vouching nodes: {} (self-edge policy: {})",
+            self_vouching_count, self.self_edge_policy
+        );
 
         if trusted_count > 0 && regular_count > 0 {
             let trust_advantage = (trusted_total_score / trusted_count as f64)
@@ -1129,4 +1234,201 @@ mod tests {
         let incoming = graph.get_incoming_count(&bob);
         assert_eq!(incoming, 1);
     }
+
+    #[test]
+    fn test_remove_edge() {
+        let (alice, bob, charlie) = policy_test_addresses();
+        let mut graph =
+            PageRankGraphComputer::new().with_duplicate_policy(DuplicatePolicy::Average);
+        graph.add_edge(alice, bob, 10.0);
+        graph.add_edge(alice, bob, 30.0);
+        graph.add_edge(alice, charlie, 20.0);
+
+        assert_eq!(graph.remove_edge(alice, bob), 1);
+        assert_eq!(graph.remove_edge(alice, bob), 0);
+        assert_eq!(graph.remove_edge(bob, alice), 0);
+        assert_eq!(graph.get_incoming_count(&bob), 0);
+        assert_eq!(graph.get_outgoing(&alice).unwrap(), &vec![(charlie, 20.0)]);
+        assert!(graph.nodes().contains(&bob));
+
+        // An edge added again starts over instead of averaging with the removed one
+        graph.add_edge(alice, bob, 50.0);
+        graph.add_edge(alice, bob, 70.0);
+        assert_eq!(graph.edges(), vec![(alice, bob, 60.0), (alice, charlie, 20.0)]);
+
+        let mut graph =
+            PageRankGraphComputer::new().with_duplicate_policy(DuplicatePolicy::KeepAll);
+        graph.add_edge(alice, bob, 10.0);
+        graph.add_edge(alice, bob, 30.0);
+        assert_eq!(graph.remove_edge(alice, bob), 2);
+        assert_eq!(graph.get_incoming_count(&bob), 0);
+    }
+
+    fn policy_test_addresses() -> (Address, Address, Address) {
+        (Address::from([0x01; 20]), Address::from([0x02; 20]), Address::from([0x03; 20]))
+    }
+
+    #[test]
+    fn test_self_edge_policy_reject() {
+        let (alice, bob, _) = policy_test_addresses();
+        let mut graph = PageRankGraphComputer::new().with_self_edge_policy(SelfEdgePolicy::Reject);
+
+        graph.add_edge(alice, bob, 1.0);
+        graph.add_edge(bob, bob, 100.0);
+
+        assert_eq!(graph.rejected_self_edges(), 1);
+        assert!(graph.get_outgoing(&bob).is_none());
+        assert_eq!(graph.get_incoming_count(&bob), 1);
+
+        // A node only seen vouching for itself is not added at all
+        let (_, _, charlie) = policy_test_addresses();
+        graph.add_edge(charlie, charlie, 100.0);
+        assert!(!graph.nodes().contains(&charlie));
+        assert_eq!(graph.rejected_self_edges(), 2);
+    }
+
+    #[test]
+    fn test_self_edge_policy_ignore() {
+        let (alice, bob, charlie) = policy_test_addresses();
+        let config = PageRankConfig::default();
+
+        let mut graph = PageRankGraphComputer::new();
+        graph.add_edge(alice, bob, 1.0);
+        graph.add_edge(bob, charlie, 1.0);
+        let scores = graph.calculate_pagerank(&config);
+
+        let mut with_self_edge =
+            PageRankGraphComputer::new().with_self_edge_policy(SelfEdgePolicy::Ignore);
+        with_self_edge.add_edge(alice, bob, 1.0);
+        with_self_edge.add_edge(bob, charlie, 1.0);
+        with_self_edge.add_edge(bob, bob, 100.0);
+        let ignored_scores = with_self_edge.calculate_pagerank(&config);
+
+        assert_eq!(with_self_edge.rejected_self_edges(), 0);
+        assert_eq!(with_self_edge.get_outgoing(&bob).unwrap().len(), 2);
+        for addr in [alice, bob, charlie] {
+            assert!(
+                (scores[&addr] - ignored_scores[&addr]).abs() < 1e-12,
+                "Ignored self-edge should not change the score of {}",
+                addr
+            );
+        }
+    }
+
+    #[test]
+    fn test_self_edge_policy_count() {
+        let (alice, bob, charlie) = policy_test_addresses();
+        let config = PageRankConfig::default();
+
+        let build = |policy| {
+            let mut graph = PageRankGraphComputer::new().with_self_edge_policy(policy);
+            graph.add_edge(alice, bob, 1.0);
+            graph.add_edge(bob, charlie, 1.0);
+            graph.add_edge(bob, bob, 1.0);
+            graph.calculate_pagerank(&config)
+        };
+        let ignored_scores = build(SelfEdgePolicy::Ignore);
+        let counted_scores = build(SelfEdgePolicy::Count);
+
+        assert!(
+            counted_scores[&bob] > ignored_scores[&bob],
+            "Counted self-edge should raise the score of the node: {} > {}",
+            counted_scores[&bob],
+            ignored_scores[&bob]
+        );
+        assert!(
+            counted_scores[&charlie] < ignored_scores[&charlie],
+            "Counted self-edge should split the outgoing weight of the node"
+        );
+    }
+
+    fn duplicate_edge_weight(policy: DuplicatePolicy, weights: &[f64]) -> (usize, Vec<f64>) {
+        let (alice, bob, _) = policy_test_addresses();
+        let mut graph = PageRankGraphComputer::new().with_duplicate_policy(policy);
+        for weight in weights {
+            graph.add_edge(alice, bob, *weight);
+        }
+        let edges = graph.get_outgoing(&alice).unwrap();
+        assert_eq!(graph.get_incoming_count(&bob), edges.len());
+        (graph.merged_duplicates(), edges.iter().map(|(_, weight)| *weight).collect())
+    }
+
+    #[test]
+    fn test_duplicate_policy_keep_all() {
+        assert_eq!(
+            duplicate_edge_weight(DuplicatePolicy::KeepAll, &[10.0, 30.0, 20.0]),
+            (0, vec![10.0, 30.0, 20.0])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policy_latest_wins() {
+        assert_eq!(
+            duplicate_edge_weight(DuplicatePolicy::LatestWins, &[10.0, 30.0, 20.0]),
+            (2, vec![20.0])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policy_max() {
+        assert_eq!(
+            duplicate_edge_weight(DuplicatePolicy::Max, &[10.0, 30.0, 20.0]),
+            (2, vec![30.0])
+        );
+    }
+
+    #[test]
+    fn test_duplicate_policy_average() {
+        let (merged, weights) =
+            duplicate_edge_weight(DuplicatePolicy::Average, &[10.0, 30.0, 20.0, 60.0]);
+        assert_eq!(merged, 3);
+        assert_eq!(weights.len(), 1);
+        assert!((weights[0] - 30.0).abs() < 1e-12, "Expected average 30, got {}", weights[0]);
+    }
+
+    #[test]
+    fn test_duplicate_policy_average_is_per_edge() {
+        let (alice, bob, charlie) = policy_test_addresses();
+        let mut graph =
+            PageRankGraphComputer::new().with_duplicate_policy(DuplicatePolicy::Average);
+        graph.add_edge(alice, bob, 10.0);
+        graph.add_edge(alice, bob, 20.0);
+        graph.add_edge(alice, bob, 30.0);
+        graph.add_edge(alice, charlie, 40.0);
+        graph.add_edge(alice, charlie, 60.0);
+
+        let edges = graph.get_outgoing(&alice).unwrap();
+        assert_eq!(edges, &vec![(bob, 20.0), (charlie, 50.0)]);
+    }
+
+    #[test]
+    fn test_duplicate_policy_sum_with_cap() {
+        assert_eq!(
+            duplicate_edge_weight(DuplicatePolicy::SumWithCap(100.0), &[10.0, 30.0]),
+            (1, vec![40.0])
+        );
+        assert_eq!(
+            duplicate_edge_weight(DuplicatePolicy::SumWithCap(100.0), &[60.0, 30.0, 20.0]),
+            (2, vec![100.0])
+        );
+    }
+
+    #[test]
+    fn test_policy_parsing() {
+        assert_eq!("reject".parse::<SelfEdgePolicy>(), Ok(SelfEdgePolicy::Reject));
+        assert_eq!("ignore".parse::<SelfEdgePolicy>(), Ok(SelfEdgePolicy::Ignore));
+        assert_eq!("count".parse::<SelfEdgePolicy>(), Ok(SelfEdgePolicy::Count));
+        assert!("allow".parse::<SelfEdgePolicy>().is_err());
+
+        assert_eq!(DuplicatePolicy::parse("keep_all", None), Ok(DuplicatePolicy::KeepAll));
+        assert_eq!(DuplicatePolicy::parse("latest", None), Ok(DuplicatePolicy::LatestWins));
+        assert_eq!(DuplicatePolicy::parse("max", None), Ok(DuplicatePolicy::Max));
+        assert_eq!(DuplicatePolicy::parse("average", Some(5.0)), Ok(DuplicatePolicy::Average));
+        assert_eq!(
+            DuplicatePolicy::parse("sum", Some(100.0)),
+            Ok(DuplicatePolicy::SumWithCap(100.0))
+        );
+        assert!(DuplicatePolicy::parse("sum", None).is_err());
+        assert!(DuplicatePolicy::parse("min", None).is_err());
+    }
 }
@@ -1,5 +1,9 @@
 pub mod config;
 pub mod graph_computer;
+pub mod manifest;
 
-pub use config::{PageRankConfig, TrustConfig};
+pub use config::{DuplicatePolicy, PageRankConfig, SelfEdgePolicy, TrustConfig};
 pub use graph_computer::PageRankGraphComputer;
+pub use manifest::{
+    edges_digest, GraphManifest, ManifestEdge, ALGORITHM_VERSION, MANIFEST_VERSION,
+};
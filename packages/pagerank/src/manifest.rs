@@ -0,0 +1,299 @@
+use alloy_primitives::{keccak256, Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{
+    config::{PageRankConfig, SelfEdgePolicy, TrustConfig},
+    graph_computer::PageRankGraphComputer,
+};
+
+/// Version of the manifest format
+pub const MANIFEST_VERSION: u32 = 1;
+
+/// Version of the graph, scoring and point distribution algorithm. Bump it with any change that
+/// computes different points from the same manifest.
+pub const ALGORITHM_VERSION: u32 = 1;
+
+/// Everything needed to recompute the points of a PageRank run bit-for-bit: the parameters and
+/// the final edge list. Floats are written as strings so they round-trip exactly through JSON.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphManifest {
+    pub manifest_version: u32,
+    pub algorithm_version: u32,
+    pub self_edge_policy: String,
+    /// Duplicate policy the edges were merged with, for reference. The edges are already merged.
+    pub duplicate_policy: String,
+    #[serde(with = "float_string")]
+    pub damping_factor: f64,
+    pub max_iterations: usize,
+    #[serde(with = "float_string")]
+    pub tolerance: f64,
+    #[serde(with = "float_string")]
+    pub min_weight: f64,
+    #[serde(with = "float_string")]
+    pub max_weight: f64,
+    /// Trusted seeds in ascending order
+    pub trusted_seeds: Vec<Address>,
+    #[serde(with = "float_string")]
+    pub trust_multiplier: f64,
+    #[serde(with = "float_string")]
+    pub trust_share: f64,
+    #[serde(with = "float_string")]
+    pub trust_decay: f64,
+    pub total_pool: U256,
+    pub num_edges: usize,
+    /// Digest of the edge list, see [`edges_digest`]
+    pub edges_digest: B256,
+    /// Edges of the graph, sorted by attester then recipient
+    pub edges: Vec<ManifestEdge>,
+}
+
+/// Weighted edge of the graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEdge {
+    pub attester: Address,
+    pub recipient: Address,
+    #[serde(with = "float_string")]
+    pub weight: f64,
+}
+
+/// keccak256 of the edges, each encoded as `attester (20 bytes) || recipient (20 bytes) ||
+/// weight (IEEE 754 bits, 8 bytes big-endian)`.
+pub fn edges_digest(edges: &[ManifestEdge]) -> B256 {
+    let mut encoded = Vec::with_capacity(edges.len() * 48);
+    for edge in edges {
+        encoded.extend_from_slice(edge.attester.as_slice());
+        encoded.extend_from_slice(edge.recipient.as_slice());
+        encoded.extend_from_slice(&edge.weight.to_bits().to_be_bytes());
+    }
+    keccak256(encoded)
+}
+
+impl GraphManifest {
+    /// Record the graph and parameters of a run. The graph must be final, i.e. built with all
+    /// edges its points are calculated from.
+    pub fn new(graph: &PageRankGraphComputer, config: &PageRankConfig, total_pool: U256) -> Self {
+        let edges: Vec<ManifestEdge> = graph
+            .edges()
+            .into_iter()
+            .map(|(attester, recipient, weight)| ManifestEdge { attester, recipient, weight })
+            .collect();
+        let mut trusted_seeds: Vec<Address> =
+            config.trust_config.trusted_seeds.iter().copied().collect();
+        trusted_seeds.sort();
+
+        Self {
+            manifest_version: MANIFEST_VERSION,
+            algorithm_version: ALGORITHM_VERSION,
+            self_edge_policy: graph.self_edge_policy().to_string(),
+            duplicate_policy: graph.duplicate_policy().to_string(),
+            damping_factor: config.damping_factor,
+            max_iterations: config.max_iterations,
+            tolerance: config.tolerance,
+            min_weight: config.min_weight,
+            max_weight: config.max_weight,
+            trusted_seeds,
+            trust_multiplier: config.trust_config.trust_multiplier,
+            trust_share: config.trust_config.trust_share,
+            trust_decay: config.trust_config.trust_decay,
+            total_pool,
+            num_edges: edges.len(),
+            edges_digest: edges_digest(&edges),
+            edges,
+        }
+    }
+
+    /// Parse a manifest from JSON.
+    pub fn from_json(json: &serde_json::Value) -> Result<Self, String> {
+        serde_json::from_value(json.clone()).map_err(|e| format!("Invalid graph manifest: {}", e))
+    }
+
+    /// Check that this build can reproduce the manifest and that the edges match their digest.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.manifest_version != MANIFEST_VERSION {
+            return Err(format!(
+                "Unsupported manifest version {} (expected {})",
+                self.manifest_version, MANIFEST_VERSION
+            ));
+        }
+        if self.algorithm_version != ALGORITHM_VERSION {
+            return Err(format!(
+                "Manifest was computed with algorithm version {}, this build implements {}",
+                self.algorithm_version, ALGORITHM_VERSION
+            ));
+        }
+        if self.num_edges != self.edges.len() {
+            return Err(format!(
+                "Manifest lists {} edges but contains {}",
+                self.num_edges,
+                self.edges.len()
+            ));
+        }
+        let digest = edges_digest(&self.edges);
+        if digest != self.edges_digest {
+            return Err(format!(
+                "Edges digest {} does not match recorded digest {}",
+                digest, self.edges_digest
+            ));
+        }
+        Ok(())
+    }
+
+    /// PageRank configuration of the run
+    pub fn pagerank_config(&self) -> PageRankConfig {
+        PageRankConfig {
+            damping_factor: self.damping_factor,
+            max_iterations: self.max_iterations,
+            tolerance: self.tolerance,
+            min_weight: self.min_weight,
+            max_weight: self.max_weight,
+            trust_config: TrustConfig {
+                trusted_seeds: self.trusted_seeds.iter().copied().collect(),
+                trust_multiplier: self.trust_multiplier,
+                trust_share: self.trust_share,
+                trust_decay: self.trust_decay,
+            },
+        }
+    }
+
+    /// Rebuild the graph from the edges. The edges are already merged, so parallel edges are kept
+    /// as they are.
+    pub fn graph(&self) -> Result<PageRankGraphComputer, String> {
+        self.validate()?;
+        let self_edge_policy: SelfEdgePolicy = self.self_edge_policy.parse()?;
+
+        let mut graph = PageRankGraphComputer::new().with_self_edge_policy(self_edge_policy);
+        for edge in &self.edges {
+            graph.add_edge(edge.attester, edge.recipient, edge.weight);
+        }
+        graph.sort();
+        Ok(graph)
+    }
+
+    /// Recompute the points of the run.
+    pub fn reproduce_points(&self) -> Result<HashMap<Address, U256>, String> {
+        let graph = self.graph()?;
+        let scores: HashMap<Address, f64> = graph
+            .calculate_pagerank(&self.pagerank_config())
+            .into_iter()
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        let (points, _) = graph.distribute_points(&scores, self.total_pool);
+        Ok(points)
+    }
+}
+
+/// Serialize floats as their shortest round-trip string
+mod float_string {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &f64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&value.to_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::DuplicatePolicy;
+
+    fn test_graph() -> (PageRankGraphComputer, PageRankConfig) {
+        let [alice, bob, charlie, dave] = [1u8, 2, 3, 4].map(|i| Address::from([i; 20]));
+        let mut graph =
+            PageRankGraphComputer::new().with_duplicate_policy(DuplicatePolicy::Average);
+        graph.add_edge(alice, bob, 10.0);
+        graph.add_edge(bob, charlie, 0.1);
+        graph.add_edge(charlie, alice, 7.3);
+        graph.add_edge(alice, bob, 3.3);
+        graph.add_edge(dave, alice, 1.0 / 3.0);
+        graph.add_edge(dave, dave, 50.0);
+        graph.sort();
+
+        let config = PageRankConfig::default()
+            .with_trust_config(TrustConfig::new(vec![alice]).with_trust_decay(0.7));
+        (graph, config)
+    }
+
+    fn points(graph: &PageRankGraphComputer, config: &PageRankConfig, pool: U256) -> Vec<String> {
+        let scores: HashMap<Address, f64> = graph
+            .calculate_pagerank(config)
+            .into_iter()
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        let mut points: Vec<_> = graph
+            .distribute_points(&scores, pool)
+            .0
+            .into_iter()
+            .map(|(account, points)| format!("{}:{}", account, points))
+            .collect();
+        points.sort();
+        points
+    }
+
+    #[test]
+    fn test_manifest_reproduces_points_through_json() {
+        let (graph, config) = test_graph();
+        let pool = U256::from(1_000_000_000u64);
+        let manifest = GraphManifest::new(&graph, &config, pool);
+        assert_eq!(manifest.num_edges, 5);
+
+        let json = serde_json::to_value(&manifest).unwrap();
+        let parsed = GraphManifest::from_json(&json).unwrap();
+        assert_eq!(parsed, manifest);
+
+        let mut reproduced: Vec<_> = parsed
+            .reproduce_points()
+            .unwrap()
+            .into_iter()
+            .map(|(account, points)| format!("{}:{}", account, points))
+            .collect();
+        reproduced.sort();
+        assert_eq!(reproduced, points(&graph, &config, pool));
+    }
+
+    #[test]
+    fn test_manifest_edges_are_sorted_and_merged() {
+        let (graph, config) = test_graph();
+        let manifest = GraphManifest::new(&graph, &config, U256::from(100));
+
+        let keys: Vec<_> = manifest.edges.iter().map(|e| (e.attester, e.recipient)).collect();
+        let mut sorted = keys.clone();
+        sorted.sort();
+        assert_eq!(keys, sorted);
+        assert_eq!(manifest.edges[0].weight, (10.0 + 3.3) / 2.0);
+        assert_eq!(manifest.duplicate_policy, "average");
+    }
+
+    #[test]
+    fn test_manifest_rejects_tampered_edges() {
+        let (graph, config) = test_graph();
+        let mut manifest = GraphManifest::new(&graph, &config, U256::from(100));
+        manifest.edges[1].weight += f64::EPSILON;
+        assert!(manifest.reproduce_points().unwrap_err().contains("digest"));
+    }
+
+    #[test]
+    fn test_manifest_rejects_other_algorithm_version() {
+        let (graph, config) = test_graph();
+        let mut manifest = GraphManifest::new(&graph, &config, U256::from(100));
+        manifest.algorithm_version += 1;
+        assert!(manifest.validate().unwrap_err().contains("algorithm version"));
+    }
+
+    #[test]
+    fn test_distribute_points_breaks_ties_by_address() {
+        let [alice, bob, charlie] = [1u8, 2, 3].map(|i| Address::from([i; 20]));
+        let scores = HashMap::from([(charlie, 1.0 / 3.0), (alice, 1.0 / 3.0), (bob, 1.0 / 3.0)]);
+        let (points, total) =
+            PageRankGraphComputer::new().distribute_points(&scores, U256::from(100));
+        assert_eq!(total, U256::from(100));
+        // The last account in order gets the remainder
+        assert_eq!(points[&alice], U256::from(33));
+        assert_eq!(points[&bob], U256::from(33));
+        assert_eq!(points[&charlie], U256::from(34));
+    }
+}
@@ -0,0 +1,64 @@
+use alloy_primitives::{Address, B256, U256};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Merkle tree JSON as uploaded to IPFS by the merkler components
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MerkleTreeIpfsData {
+    pub id: String,
+    pub metadata: serde_json::Value,
+    pub root: String,
+    pub tree: Vec<MerkleTreeEntry>,
+}
+
+/// Single account entry of a merkle tree, with its proof
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct MerkleTreeEntry {
+    pub account: String,
+    pub value: String,
+    pub proof: Vec<String>,
+}
+
+impl MerkleTreeIpfsData {
+    /// Parse the merkle tree JSON.
+    pub fn from_json(json: &[u8]) -> Result<Self, String> {
+        serde_json::from_slice(json).map_err(|e| format!("Failed to parse merkle tree JSON: {}", e))
+    }
+
+    /// Parse the claimed root.
+    pub fn parsed_root(&self) -> Result<B256, String> {
+        B256::from_str(&self.root).map_err(|e| format!("Invalid root {}: {}", self.root, e))
+    }
+
+    /// Find the entry of an account.
+    pub fn entry(&self, account: Address) -> Option<&MerkleTreeEntry> {
+        self.tree.iter().find(|entry| entry.parsed_account().is_ok_and(|a| a == account))
+    }
+}
+
+impl MerkleTreeEntry {
+    /// Parse the account address.
+    pub fn parsed_account(&self) -> Result<Address, String> {
+        Address::from_str(&self.account)
+            .map_err(|e| format!("Invalid account {}: {}", self.account, e))
+    }
+
+    /// Parse the value, given as a decimal or 0x-prefixed hex string.
+    pub fn parsed_value(&self) -> Result<U256, String> {
+        U256::from_str(&self.value)
+            .map_err(|e| format!("Invalid value {} for {}: {}", self.value, self.account, e))
+    }
+
+    /// Parse the proof hashes.
+    pub fn parsed_proof(&self) -> Result<Vec<B256>, String> {
+        self.proof
+            .iter()
+            .map(|hash| {
+                B256::from_str(hash)
+                    .map_err(|e| format!("Invalid proof hash {} for {}: {}", hash, self.account, e))
+            })
+            .collect()
+    }
+}
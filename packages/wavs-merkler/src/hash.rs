@@ -0,0 +1,30 @@
+//! Hashing that matches the OpenZeppelin `MerkleProof` library and the leaf encoding used by
+//! `MerkleSnapshot`, `MerkleGovModule` and `MerkleFundDistributor`.
+
+use alloy_primitives::{keccak256, Address, B256, U256};
+
+/// Hash a leaf: `keccak256(bytes.concat(keccak256(abi.encode(account, value))))`.
+pub fn leaf_hash(account: Address, value: U256) -> B256 {
+    // abi.encode(address, uint256): the address left-padded to 32 bytes, then the value
+    let mut encoded = [0u8; 64];
+    encoded[12..32].copy_from_slice(account.as_slice());
+    encoded[32..].copy_from_slice(&value.to_be_bytes::<32>());
+
+    keccak256(keccak256(encoded))
+}
+
+/// Hash a pair of nodes in sorted order, like `MerkleProof` does.
+pub fn hash_pair(a: B256, b: B256) -> B256 {
+    let (first, second) = if a < b { (a, b) } else { (b, a) };
+
+    let mut concatenated = [0u8; 64];
+    concatenated[..32].copy_from_slice(first.as_slice());
+    concatenated[32..].copy_from_slice(second.as_slice());
+
+    keccak256(concatenated)
+}
+
+/// Rebuild the root from a leaf and its proof, like `MerkleProof.processProof`.
+pub fn process_proof(leaf: B256, proof: &[B256]) -> B256 {
+    proof.iter().fold(leaf, |computed, sibling| hash_pair(computed, *sibling))
+}
@@ -0,0 +1,11 @@
+pub mod data;
+pub mod hash;
+pub mod reproduce;
+pub mod tree;
+pub mod verify;
+
+pub use data::{MerkleTreeEntry, MerkleTreeIpfsData};
+pub use hash::{hash_pair, leaf_hash, process_proof};
+pub use reproduce::{find_graph_manifests, reproduce_root, ReproductionReport};
+pub use tree::MerkleTree;
+pub use verify::{simulate_claim, verify_proof, verify_tree, Claim, VerificationReport};
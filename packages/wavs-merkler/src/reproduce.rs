@@ -0,0 +1,185 @@
+use alloy_primitives::{Address, U256};
+use pagerank::GraphManifest;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::{data::MerkleTreeIpfsData, tree::MerkleTree};
+
+/// Result of recomputing a merkle tree from the manifests in its metadata
+#[derive(Debug, Clone, Serialize)]
+pub struct ReproductionReport {
+    /// Root claimed by the JSON
+    pub root: String,
+    /// Root recomputed from the manifests
+    pub reproduced_root: String,
+    pub num_manifests: usize,
+    pub num_accounts: usize,
+    /// Accounts whose recomputed value differs from the published one, or that are missing from
+    /// either tree
+    pub mismatched_accounts: Vec<String>,
+}
+
+impl ReproductionReport {
+    pub fn root_matches(&self) -> bool {
+        self.root == self.reproduced_root
+    }
+
+    /// Turn a report whose root doesn't match into an error describing the differences.
+    pub fn into_result(self) -> Result<Self, String> {
+        if self.root_matches() {
+            return Ok(self);
+        }
+
+        Err(format!(
+            "Reproduced root {} does not match root {} ({} mismatched accounts: {})",
+            self.reproduced_root,
+            self.root,
+            self.mismatched_accounts.len(),
+            self.mismatched_accounts.join(", ")
+        ))
+    }
+}
+
+/// Find the graph manifests recorded in merkle metadata, wherever the sources nest them: the
+/// `graph` of every object under a `manifest` key.
+pub fn find_graph_manifests(metadata: &Value) -> Result<Vec<GraphManifest>, String> {
+    let mut manifests = Vec::new();
+    collect_graph_manifests(metadata, &mut manifests)?;
+    Ok(manifests)
+}
+
+fn collect_graph_manifests(
+    value: &Value,
+    manifests: &mut Vec<GraphManifest>,
+) -> Result<(), String> {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match value.get("graph") {
+                    Some(graph) if key == "manifest" => {
+                        manifests.push(GraphManifest::from_json(graph)?)
+                    }
+                    _ => collect_graph_manifests(value, manifests)?,
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                collect_graph_manifests(value, manifests)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Recompute the values of a merkle tree JSON from the graph manifests in its metadata and
+/// rebuild its root. Values are summed across manifests, like the merkler sums its sources.
+/// Returns an error if there is no manifest or a manifest can't be reproduced by this build.
+pub fn reproduce_root(data: &MerkleTreeIpfsData) -> Result<ReproductionReport, String> {
+    let manifests = find_graph_manifests(&data.metadata)?;
+    if manifests.is_empty() {
+        return Err("No graph manifest found in the merkle tree metadata".to_string());
+    }
+
+    let mut values: BTreeMap<Address, U256> = BTreeMap::new();
+    for manifest in &manifests {
+        for (account, points) in manifest.reproduce_points()? {
+            *values.entry(account).or_default() += points;
+        }
+    }
+    values.retain(|_, value| !value.is_zero());
+
+    let published = data
+        .tree
+        .iter()
+        .map(|entry| Ok((entry.parsed_account()?, entry.parsed_value()?)))
+        .collect::<Result<BTreeMap<Address, U256>, String>>()?;
+    let accounts: BTreeSet<&Address> = values.keys().chain(published.keys()).collect();
+    let mismatched_accounts = accounts
+        .into_iter()
+        .filter(|account| values.get(*account) != published.get(*account))
+        .map(|account| account.to_string())
+        .collect();
+
+    let num_accounts = values.len();
+    let reproduced_root = MerkleTree::new(values.into_iter().collect())?.root();
+
+    Ok(ReproductionReport {
+        root: data.parsed_root()?.to_string(),
+        reproduced_root: reproduced_root.to_string(),
+        num_manifests: manifests.len(),
+        num_accounts,
+        mismatched_accounts,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pagerank::{PageRankConfig, PageRankGraphComputer, TrustConfig};
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    /// Publish a tree the way the trust-graph component does, with its manifest in the metadata
+    fn published_tree() -> MerkleTreeIpfsData {
+        let [alice, bob, charlie] = [1u8, 2, 3].map(Address::repeat_byte);
+        let mut graph = PageRankGraphComputer::new();
+        graph.add_edge(alice, bob, 10.0);
+        graph.add_edge(bob, charlie, 2.5);
+        graph.add_edge(charlie, alice, 0.1);
+        graph.add_edge(alice, charlie, 1.0 / 3.0);
+        graph.sort();
+        let config = PageRankConfig::default().with_trust_config(TrustConfig::new(vec![alice]));
+        let pool = U256::from(1_000_000_000_000u64);
+
+        let scores: HashMap<Address, f64> = graph
+            .calculate_pagerank(&config)
+            .into_iter()
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        let (points, _) = graph.distribute_points(&scores, pool);
+
+        let manifest = GraphManifest::new(&graph, &config, pool);
+        let metadata = json!({
+            "sources": [{
+                "name": "Trust-Aware-EAS-PageRank",
+                "metadata": { "manifest": { "manifest_version": 1, "graph": manifest } },
+            }],
+        });
+        let metadata: Value = serde_json::from_str(&metadata.to_string()).unwrap();
+
+        MerkleTree::new(points.into_iter().collect())
+            .unwrap()
+            .to_ipfs_data("trust-graph".to_string(), metadata)
+    }
+
+    #[test]
+    fn test_reproduce_root_from_manifest() {
+        let data = published_tree();
+        let report = reproduce_root(&data).unwrap().into_result().unwrap();
+        assert_eq!(report.num_manifests, 1);
+        assert_eq!(report.num_accounts, 3);
+        assert!(report.mismatched_accounts.is_empty());
+    }
+
+    #[test]
+    fn test_reproduce_root_detects_changed_value() {
+        let mut data = published_tree();
+        data.tree[0].value = (data.tree[0].parsed_value().unwrap() + U256::from(1)).to_string();
+        data.root = MerkleTree::from_ipfs_data(&data).unwrap().root().to_string();
+
+        let report = reproduce_root(&data).unwrap();
+        assert!(!report.root_matches());
+        assert_eq!(report.mismatched_accounts, vec![data.tree[0].account.clone()]);
+        assert!(report.into_result().is_err());
+    }
+
+    #[test]
+    fn test_reproduce_root_requires_manifest() {
+        let mut data = published_tree();
+        data.metadata = json!({ "sources": [] });
+        assert!(reproduce_root(&data).unwrap_err().contains("No graph manifest"));
+    }
+}
@@ -0,0 +1,91 @@
+use alloy_primitives::{Address, B256, U256};
+use serde_json::Value;
+
+use crate::{
+    data::{MerkleTreeEntry, MerkleTreeIpfsData},
+    hash::{hash_pair, leaf_hash},
+};
+
+/// Merkle tree with the layout of OpenZeppelin's `StandardMerkleTree`: leaves are sorted by hash
+/// and stored at the end of a flat array, with each parent at `(i - 1) / 2`.
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    values: Vec<(Address, U256)>,
+    /// Position of each value's leaf in `nodes`
+    leaf_indices: Vec<usize>,
+    nodes: Vec<B256>,
+}
+
+impl MerkleTree {
+    /// Build the tree of the given account values.
+    pub fn new(values: Vec<(Address, U256)>) -> Result<Self, String> {
+        if values.is_empty() {
+            return Err("Cannot build a merkle tree without values".to_string());
+        }
+
+        let mut leaves: Vec<(usize, B256)> = values
+            .iter()
+            .enumerate()
+            .map(|(i, (account, value))| (i, leaf_hash(*account, *value)))
+            .collect();
+        leaves.sort_by_key(|(_, leaf)| *leaf);
+
+        let num_nodes = 2 * leaves.len() - 1;
+        let mut nodes = vec![B256::ZERO; num_nodes];
+        let mut leaf_indices = vec![0; values.len()];
+        for (i, (value_index, leaf)) in leaves.into_iter().enumerate() {
+            let node_index = num_nodes - 1 - i;
+            nodes[node_index] = leaf;
+            leaf_indices[value_index] = node_index;
+        }
+        for i in (0..num_nodes - values.len()).rev() {
+            nodes[i] = hash_pair(nodes[2 * i + 1], nodes[2 * i + 2]);
+        }
+
+        Ok(Self { values, leaf_indices, nodes })
+    }
+
+    /// Build the tree of the entries of a merkle tree JSON, ignoring their proofs.
+    pub fn from_ipfs_data(data: &MerkleTreeIpfsData) -> Result<Self, String> {
+        let values = data
+            .tree
+            .iter()
+            .map(|entry| Ok((entry.parsed_account()?, entry.parsed_value()?)))
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Self::new(values)
+    }
+
+    pub fn root(&self) -> B256 {
+        self.nodes[0]
+    }
+
+    /// Get the proof of the value at the given index.
+    pub fn proof(&self, index: usize) -> Option<Vec<B256>> {
+        let mut node_index = *self.leaf_indices.get(index)?;
+        let mut proof = Vec::new();
+        while node_index > 0 {
+            let sibling = if node_index % 2 == 1 { node_index + 1 } else { node_index - 1 };
+            proof.push(self.nodes[sibling]);
+            node_index = (node_index - 1) / 2;
+        }
+
+        Some(proof)
+    }
+
+    /// Convert the tree to the merkle tree JSON format, with a proof for every entry.
+    pub fn to_ipfs_data(&self, id: String, metadata: Value) -> MerkleTreeIpfsData {
+        let tree = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(i, (account, value))| MerkleTreeEntry {
+                account: account.to_string(),
+                value: value.to_string(),
+                proof: self.proof(i).unwrap_or_default().iter().map(|h| h.to_string()).collect(),
+            })
+            .collect();
+
+        MerkleTreeIpfsData { id, metadata, root: self.root().to_string(), tree }
+    }
+}
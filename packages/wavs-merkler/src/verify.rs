@@ -0,0 +1,254 @@
+use alloy_primitives::{Address, B256, U256};
+use serde::Serialize;
+
+use crate::{
+    data::MerkleTreeIpfsData,
+    hash::{leaf_hash, process_proof},
+    tree::MerkleTree,
+};
+
+/// Verify a proof of an account value against a root, like `MerkleSnapshot.verifyProof`.
+pub fn verify_proof(root: B256, account: Address, value: U256, proof: &[B256]) -> bool {
+    process_proof(leaf_hash(account, value), proof) == root
+}
+
+/// Result of verifying a merkle tree JSON
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    /// Root claimed by the JSON
+    pub root: String,
+    /// Root recomputed from the entries
+    pub computed_root: String,
+    pub num_entries: usize,
+    /// Accounts whose proof does not verify against the claimed root
+    pub invalid_proofs: Vec<String>,
+    /// Accounts that appear more than once
+    pub duplicate_accounts: Vec<String>,
+}
+
+impl VerificationReport {
+    pub fn root_matches(&self) -> bool {
+        self.root == self.computed_root
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.root_matches() && self.invalid_proofs.is_empty() && self.duplicate_accounts.is_empty()
+    }
+
+    /// Turn an invalid report into an error describing what failed.
+    pub fn into_result(self) -> Result<Self, String> {
+        if self.is_valid() {
+            return Ok(self);
+        }
+
+        let mut problems = Vec::new();
+        if !self.root_matches() {
+            problems.push(format!(
+                "root {} does not match recomputed root {}",
+                self.root, self.computed_root
+            ));
+        }
+        if !self.invalid_proofs.is_empty() {
+            problems.push(format!(
+                "{} invalid proofs ({})",
+                self.invalid_proofs.len(),
+                self.invalid_proofs.join(", ")
+            ));
+        }
+        if !self.duplicate_accounts.is_empty() {
+            problems.push(format!("duplicate accounts ({})", self.duplicate_accounts.join(", ")));
+        }
+
+        Err(format!("Invalid merkle tree: {}", problems.join("; ")))
+    }
+}
+
+/// Recompute the root of a merkle tree JSON and verify the proof of every entry against the
+/// claimed root. Returns an error only if the JSON cannot be parsed.
+pub fn verify_tree(data: &MerkleTreeIpfsData) -> Result<VerificationReport, String> {
+    let root = data.parsed_root()?;
+    let computed_root = MerkleTree::from_ipfs_data(data)?.root();
+
+    let mut seen = std::collections::HashSet::new();
+    let mut invalid_proofs = Vec::new();
+    let mut duplicate_accounts = Vec::new();
+    for entry in &data.tree {
+        let account = entry.parsed_account()?;
+        if !seen.insert(account) {
+            duplicate_accounts.push(account.to_string());
+        }
+        if !verify_proof(root, account, entry.parsed_value()?, &entry.parsed_proof()?) {
+            invalid_proofs.push(account.to_string());
+        }
+    }
+
+    Ok(VerificationReport {
+        root: root.to_string(),
+        computed_root: computed_root.to_string(),
+        num_entries: data.tree.len(),
+        invalid_proofs,
+        duplicate_accounts,
+    })
+}
+
+/// Arguments an account would submit to claim against the tree's root
+#[derive(Debug, Clone, Serialize)]
+pub struct Claim {
+    pub account: Address,
+    pub value: U256,
+    pub proof: Vec<B256>,
+}
+
+/// Simulate a claim: look up the account's entry and check that its proof would be accepted
+/// on-chain against the given root.
+pub fn simulate_claim(
+    data: &MerkleTreeIpfsData,
+    root: B256,
+    account: Address,
+) -> Result<Claim, String> {
+    let entry =
+        data.entry(account).ok_or_else(|| format!("Account {} is not in the tree", account))?;
+    let value = entry.parsed_value()?;
+    let proof = entry.parsed_proof()?;
+
+    if !verify_proof(root, account, value, &proof) {
+        return Err(format!("Proof for {} does not verify against root {}", account, root));
+    }
+
+    Ok(Claim { account, value, proof })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// Tree generated by this crate and verified by `MerkleSnapshot` in the Solidity tests. If the
+    /// encoding drifts on either side, one of the two test suites fails.
+    const FIXTURE: &str = include_str!("../fixtures/merkle_tree.json");
+
+    fn fixture() -> MerkleTreeIpfsData {
+        MerkleTreeIpfsData::from_json(FIXTURE.as_bytes()).unwrap()
+    }
+
+    fn values(n: u8) -> Vec<(Address, U256)> {
+        (1..=n).map(|i| (Address::repeat_byte(i), U256::from(i as u64 * 100))).collect()
+    }
+
+    #[test]
+    fn test_leaf_hash_matches_solidity_encoding() {
+        // keccak256(bytes.concat(keccak256(abi.encode(0x1111...1111, 100))))
+        let leaf = leaf_hash(Address::repeat_byte(0x11), U256::from(100));
+        assert_eq!(
+            leaf,
+            B256::from_str("0x922c8389ffeb7a618b1f9fe2e9a75c76d86291502713033e5951dbad45b3fc31")
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_fixture_verifies() {
+        let report = verify_tree(&fixture()).unwrap();
+
+        assert!(report.is_valid(), "{:?}", report);
+        assert_eq!(report.num_entries, 5);
+    }
+
+    #[test]
+    fn test_fixture_is_reproducible() {
+        let data = fixture();
+        let rebuilt = MerkleTree::from_ipfs_data(&data)
+            .unwrap()
+            .to_ipfs_data(data.id.clone(), data.metadata.clone());
+
+        assert_eq!(rebuilt.root, data.root);
+        for (rebuilt, entry) in rebuilt.tree.iter().zip(&data.tree) {
+            assert_eq!(rebuilt.proof, entry.proof);
+        }
+    }
+
+    #[test]
+    fn test_all_tree_sizes_verify() {
+        for n in 1..=17 {
+            let tree = MerkleTree::new(values(n)).unwrap();
+            let data = tree.to_ipfs_data(format!("tree-{n}"), serde_json::Value::Null);
+
+            let report = verify_tree(&data).unwrap();
+            assert!(report.is_valid(), "tree of {n} entries: {:?}", report);
+        }
+    }
+
+    #[test]
+    fn test_single_entry_root_is_leaf() {
+        let tree = MerkleTree::new(values(1)).unwrap();
+
+        assert_eq!(tree.root(), leaf_hash(Address::repeat_byte(1), U256::from(100)));
+        assert_eq!(tree.proof(0), Some(vec![]));
+    }
+
+    #[test]
+    fn test_empty_tree_is_rejected() {
+        assert!(MerkleTree::new(vec![]).is_err());
+    }
+
+    #[test]
+    fn test_tampered_value_is_detected() {
+        let mut data = fixture();
+        data.tree[2].value = "301".to_string();
+
+        let report = verify_tree(&data).unwrap();
+
+        assert!(!report.is_valid());
+        assert!(!report.root_matches());
+        assert_eq!(report.invalid_proofs, vec![data.tree[2].account.clone()]);
+        assert!(report.into_result().is_err());
+    }
+
+    #[test]
+    fn test_tampered_root_is_detected() {
+        let mut data = fixture();
+        data.root = B256::repeat_byte(0xab).to_string();
+
+        let report = verify_tree(&data).unwrap();
+
+        assert!(!report.root_matches());
+        assert_eq!(report.invalid_proofs.len(), data.tree.len());
+    }
+
+    #[test]
+    fn test_duplicate_accounts_are_detected() {
+        let mut values = values(3);
+        values.push(values[0]);
+        let data = MerkleTree::new(values).unwrap().to_ipfs_data("dup".into(), Default::default());
+
+        let report = verify_tree(&data).unwrap();
+
+        assert!(report.root_matches());
+        assert!(report.invalid_proofs.is_empty());
+        assert_eq!(report.duplicate_accounts, vec![Address::repeat_byte(1).to_string()]);
+    }
+
+    #[test]
+    fn test_simulate_claim() {
+        let data = fixture();
+        let root = data.parsed_root().unwrap();
+        let account = Address::repeat_byte(0x33);
+
+        let claim = simulate_claim(&data, root, account).unwrap();
+        assert_eq!(claim.value, U256::from(300));
+        assert!(verify_proof(root, account, claim.value, &claim.proof));
+
+        // Unknown account and stale root
+        assert!(simulate_claim(&data, root, Address::repeat_byte(0x99)).is_err());
+        assert!(simulate_claim(&data, B256::repeat_byte(1), account).is_err());
+    }
+
+    #[test]
+    fn test_malformed_json_is_an_error() {
+        let mut data = fixture();
+        data.tree[0].proof[0] = "not a hash".to_string();
+
+        assert!(verify_tree(&data).is_err());
+        assert!(MerkleTreeIpfsData::from_json(b"not json").is_err());
+    }
+}
@@ -10,7 +10,10 @@ import {uncheckedInc} from "@ethereum-attestation-service/eas-contracts/contract
 
 /// @title WavsIndexer
 /// @notice An indexing service for arbitrary blockchain events and data running on WAVS
-/// @dev Integrates with WAVS to receive indexed data from off-chain components
+/// @dev Integrates with WAVS to receive indexed data from off-chain components. Components may choose
+/// the ids of their events; an event whose id already exists reverts the whole envelope with
+/// EventAlreadyExists, so components must leave out events that are already indexed before
+/// submitting (the wavs-indexer component does it with `skip_indexed`).
 contract WavsIndexer is IWavsServiceHandler, IWavsIndexer, Semver {
     // Core storage mappings
     mapping(bytes32 => IndexedEvent) public events;
@@ -42,7 +45,7 @@ contract WavsIndexer is IWavsServiceHandler, IWavsIndexer, Semver {
 
     /// @notice Creates a new WavsIndexer instance
     /// @param serviceManager The WAVS service manager contract
-    constructor(IWavsServiceManager serviceManager) Semver(1, 0, 0) {
+    constructor(IWavsServiceManager serviceManager) Semver(1, 1, 0) {
         if (address(serviceManager) == address(0)) {
             revert InvalidServiceManager();
         }
@@ -85,16 +88,17 @@ contract WavsIndexer is IWavsServiceHandler, IWavsIndexer, Semver {
     }
 
     /// @notice Indexes a single event
+    /// @dev Reverts with EventAlreadyExists if the id is taken, which reverts the whole envelope
+    /// @param eventId The ID derived from the envelope, used if the event doesn't set its own
     /// @param event_ The event to index
     function _indexEvent(bytes32 eventId, IndexedEvent memory event_) internal {
-        // validate that the eventId is not already set, since it should only be set by this contract
-        if (event_.eventId != bytes32(0)) {
-            revert ExpectedEventIdZero();
+        // components may set a deterministic eventId, so indexing the same event twice is detected
+        if (event_.eventId == bytes32(0)) {
+            event_.eventId = eventId;
+        } else {
+            eventId = event_.eventId;
         }
 
-        // override eventId with the one provided
-        event_.eventId = eventId;
-
         if (eventExists[eventId]) {
             revert EventAlreadyExists();
         }
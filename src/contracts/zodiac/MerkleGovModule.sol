@@ -10,11 +10,12 @@ import {Math} from "@openzeppelin/contracts/utils/math/Math.sol";
 
 import {IMerkleSnapshot} from "interfaces/merkle/IMerkleSnapshot.sol";
 import {IMerkleSnapshotHook} from "interfaces/merkle/IMerkleSnapshotHook.sol";
+import {IMerkleGovModule} from "interfaces/IMerkleGovModule.sol";
 
 /// @title MerkleGovModule - Zodiac module for merkle-based governance
 /// @notice Combines merkle voting verification with Zodiac's execution capabilities
 /// TODO: should the onlyOwner modifier be onlyAvatar instead? voting config (quorum, delay, period) should be set by the DAO probably, not owner.
-contract MerkleGovModule is Module, IMerkleSnapshotHook {
+contract MerkleGovModule is Module, IMerkleSnapshotHook, IMerkleGovModule {
     /*///////////////////////////////////////////////////////////////
                                 ERRORS
     //////////////////////////////////////////////////////////////*/
@@ -49,12 +50,6 @@ contract MerkleGovModule is Module, IMerkleSnapshotHook {
         Cancelled
     }
 
-    enum VoteType {
-        No,
-        Yes,
-        Abstain
-    }
-
     struct ProposalAction {
         address target;
         uint256 value;
@@ -83,26 +78,7 @@ contract MerkleGovModule is Module, IMerkleSnapshotHook {
                                 EVENTS
     //////////////////////////////////////////////////////////////*/
 
-    event ProposalCreated(
-        uint256 indexed proposalId,
-        address indexed proposer,
-        string title,
-        string description,
-        uint256 startBlock,
-        uint256 endBlock,
-        bytes32 merkleRoot,
-        uint256 totalVotingPower
-    );
-
-    event VoteCast(
-        address indexed voter,
-        uint256 indexed proposalId,
-        VoteType voteType,
-        uint256 votingPower
-    );
-
-    event ProposalExecuted(uint256 indexed proposalId);
-    event ProposalCancelled(uint256 indexed proposalId);
+    // ProposalCreated, VoteCast, ProposalExecuted and ProposalCancelled are declared in IMerkleGovModule
     event QuorumUpdated(uint256 newQuorum);
     event VotingDelayUpdated(uint256 newDelay);
     event VotingPeriodUpdated(uint256 newPeriod);
@@ -0,0 +1,33 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.22;
+
+/// @title IMerkleGovModule
+/// @notice Vote types and events of the MerkleGovModule, shared with the indexer that indexes its proposals and votes.
+interface IMerkleGovModule {
+    enum VoteType {
+        No,
+        Yes,
+        Abstain
+    }
+
+    event ProposalCreated(
+        uint256 indexed proposalId,
+        address indexed proposer,
+        string title,
+        string description,
+        uint256 startBlock,
+        uint256 endBlock,
+        bytes32 merkleRoot,
+        uint256 totalVotingPower
+    );
+
+    event VoteCast(
+        address indexed voter,
+        uint256 indexed proposalId,
+        VoteType voteType,
+        uint256 votingPower
+    );
+
+    event ProposalExecuted(uint256 indexed proposalId);
+    event ProposalCancelled(uint256 indexed proposalId);
+}
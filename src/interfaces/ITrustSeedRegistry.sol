@@ -0,0 +1,9 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.22;
+
+/// @notice A governance-managed set of trusted seeds for the trust graph's Trust Aware PageRank.
+interface ITrustSeedRegistry {
+    /// @notice Get the current trusted seeds.
+    /// @return seeds The trusted seed addresses.
+    function getTrustedSeeds() external view returns (address[] memory seeds);
+}
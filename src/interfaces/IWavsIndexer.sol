@@ -34,7 +34,6 @@ interface IWavsIndexer {
     /// ================================================
 
     error InvalidServiceManager();
-    error ExpectedEventIdZero();
     error InvalidOffset();
     error PayloadDecodingFailed();
     error EventAlreadyExists();
@@ -49,7 +48,7 @@ interface IWavsIndexer {
 
     /// @notice Represents a universally indexed event
     struct IndexedEvent {
-        bytes32 eventId; // Unique identifier for this event
+        bytes32 eventId; // Unique identifier for this event, derived from the envelope if zero
         string chainId; // Chain ID of the event
         address relevantContract; // Relevant contract for the event
         uint256 blockNumber; // Block number when event was emitted
@@ -19,6 +19,7 @@ import {
 import { MerkleTreeHelper } from '../merkle-governance/MerkleTreeHelper.sol';
 import { IMerkleSnapshot } from 'interfaces/merkle/IMerkleSnapshot.sol';
 import { IMerkleSnapshotHook } from 'interfaces/merkle/IMerkleSnapshotHook.sol';
+import { IMerkleGovModule } from 'interfaces/IMerkleGovModule.sol';
 
 contract MerkleGovModuleTest is Test {
   // Core contracts
@@ -60,7 +61,7 @@ contract MerkleGovModuleTest is Test {
   event VoteCast(
     address indexed voter,
     uint256 indexed proposalId,
-    MerkleGovModule.VoteType voteType,
+    IMerkleGovModule.VoteType voteType,
     uint256 votingPower
   );
   event ProposalExecuted(uint256 indexed proposalId);
@@ -322,14 +323,14 @@ contract MerkleGovModuleTest is Test {
     emit VoteCast(
       alice,
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice]
     );
 
     vm.prank(alice);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     );
@@ -339,14 +340,14 @@ contract MerkleGovModuleTest is Test {
     emit VoteCast(
       bob,
       proposalId,
-      MerkleGovModule.VoteType.No,
+      IMerkleGovModule.VoteType.No,
       votingPowers[bob]
     );
 
     vm.prank(bob);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.No,
+      IMerkleGovModule.VoteType.No,
       votingPowers[bob],
       proofs[bob]
     );
@@ -356,14 +357,14 @@ contract MerkleGovModuleTest is Test {
     emit VoteCast(
       charlie,
       proposalId,
-      MerkleGovModule.VoteType.Abstain,
+      IMerkleGovModule.VoteType.Abstain,
       votingPowers[charlie]
     );
 
     vm.prank(charlie);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Abstain,
+      IMerkleGovModule.VoteType.Abstain,
       votingPowers[charlie],
       proofs[charlie]
     );
@@ -429,7 +430,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(alice);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     );
@@ -439,7 +440,7 @@ contract MerkleGovModuleTest is Test {
     vm.expectRevert(MerkleGovModule.AlreadyVoted.selector);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     );
@@ -483,7 +484,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(alice);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     ); // 100e18
@@ -491,7 +492,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(bob);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[bob],
       proofs[bob]
     ); // 200e18
@@ -707,7 +708,7 @@ contract MerkleGovModuleTest is Test {
     vm.expectRevert(MerkleGovModule.InvalidMerkleProof.selector);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       500e18,
       proofs[alice]
     ); // Wrong amount
@@ -720,7 +721,7 @@ contract MerkleGovModuleTest is Test {
     vm.expectRevert(MerkleGovModule.InvalidMerkleProof.selector);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       wrongProof
     );
@@ -774,7 +775,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(alice);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     );
@@ -782,7 +783,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(bob);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[bob],
       proofs[bob]
     );
@@ -863,7 +864,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(alice);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     );
@@ -876,7 +877,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(bob);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[bob],
       proofs[bob]
     );
@@ -986,7 +987,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(alice);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[alice],
       proofs[alice]
     );
@@ -1198,7 +1199,7 @@ contract MerkleGovModuleTest is Test {
     );
 
     vm.expectEmit(true, true, false, true);
-    emit VoteCast(alice, 1, MerkleGovModule.VoteType.Yes, votingPowers[alice]);
+    emit VoteCast(alice, 1, IMerkleGovModule.VoteType.Yes, votingPowers[alice]);
 
     // Create proposal and vote in one transaction
     vm.prank(alice);
@@ -1212,7 +1213,7 @@ contract MerkleGovModuleTest is Test {
       actionDescriptions,
       votingPowers[alice],
       proofs[alice],
-      MerkleGovModule.VoteType.Yes
+      IMerkleGovModule.VoteType.Yes
     );
 
     assertEq(proposalId, 1);
@@ -1250,7 +1251,7 @@ contract MerkleGovModuleTest is Test {
     assertTrue(govModule.hasVoted(proposalId, alice));
     assertEq(
       uint256(govModule.votes(proposalId, alice)),
-      uint256(MerkleGovModule.VoteType.Yes)
+      uint256(IMerkleGovModule.VoteType.Yes)
     );
 
     // Alice cannot vote again
@@ -1259,7 +1260,7 @@ contract MerkleGovModuleTest is Test {
     vm.expectRevert(MerkleGovModule.AlreadyVoted.selector);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.No,
+      IMerkleGovModule.VoteType.No,
       votingPowers[alice],
       proofs[alice]
     );
@@ -1268,7 +1269,7 @@ contract MerkleGovModuleTest is Test {
     vm.prank(bob);
     govModule.castVote(
       proposalId,
-      MerkleGovModule.VoteType.Yes,
+      IMerkleGovModule.VoteType.Yes,
       votingPowers[bob],
       proofs[bob]
     );
@@ -1307,7 +1308,7 @@ contract MerkleGovModuleTest is Test {
       actionDescriptions,
       votingPowers[bob],
       proofs[bob],
-      MerkleGovModule.VoteType.No
+      IMerkleGovModule.VoteType.No
     );
 
     // Check that No vote is recorded
@@ -1318,7 +1319,7 @@ contract MerkleGovModuleTest is Test {
 
     assertEq(
       uint256(govModule.votes(proposalId, bob)),
-      uint256(MerkleGovModule.VoteType.No)
+      uint256(IMerkleGovModule.VoteType.No)
     );
   }
 
@@ -1347,7 +1348,7 @@ contract MerkleGovModuleTest is Test {
       actionDescriptions,
       votingPowers[charlie],
       proofs[charlie],
-      MerkleGovModule.VoteType.Abstain
+      IMerkleGovModule.VoteType.Abstain
     );
 
     // Check that Abstain vote is recorded
@@ -1372,7 +1373,7 @@ contract MerkleGovModuleTest is Test {
 
     assertEq(
       uint256(govModule.votes(proposalId, charlie)),
-      uint256(MerkleGovModule.VoteType.Abstain)
+      uint256(IMerkleGovModule.VoteType.Abstain)
     );
   }
 }
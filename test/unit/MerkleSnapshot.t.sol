@@ -393,6 +393,43 @@ contract MerkleSnapshotTest is Test {
         assertFalse(merkleSnapshot.verifyProof(charlie, 100, proof)); // wrong account
     }
 
+    function testVerifyProof_ShouldAcceptRustMerklerFixture() public {
+        // Tree generated by the wavs-merkler Rust package. Keeps the leaf encoding and tree layout
+        // in sync between the components and the contracts.
+        string memory json = vm.readFile(
+            "packages/wavs-merkler/fixtures/merkle_tree.json"
+        );
+        bytes32 root = vm.parseJsonBytes32(json, ".root");
+
+        _createStateAtCurrentBlock(
+            0x1,
+            root,
+            TEST_IPFS_HASH_1,
+            TEST_IPFS_CID_1,
+            1500
+        );
+
+        uint256 numEntries = 5;
+        for (uint256 i = 0; i < numEntries; i++) {
+            string memory entry = string.concat(".tree[", vm.toString(i), "]");
+            address account = vm.parseJsonAddress(
+                json,
+                string.concat(entry, ".account")
+            );
+            uint256 value = vm.parseJsonUint(
+                json,
+                string.concat(entry, ".value")
+            );
+            bytes32[] memory proof = vm.parseJsonBytes32Array(
+                json,
+                string.concat(entry, ".proof")
+            );
+
+            assertTrue(merkleSnapshot.verifyProof(account, value, proof));
+            assertFalse(merkleSnapshot.verifyProof(account, value + 1, proof));
+        }
+    }
+
     function testVerifyMyProof_ShouldWorkForSender() public {
         // Create a simple merkle tree
         bytes32 leaf1 = _generateLeaf(alice, 100);
@@ -74,10 +74,11 @@ contract WavsIndexerTest is Test {
         wavsIndexer.handleSignedEnvelope(envelope, signatureData);
     }
 
-    function testHandleSignedEnvelope_ShouldRevertOnAddIfNonZeroEventId() public {
-        // Create event with non-zero eventId (should be zero for ADD operations)
-        IWavsIndexer.IndexedEvent[] memory events = new IWavsIndexer.IndexedEvent[](1);
+    function testHandleSignedEnvelope_ShouldUseProvidedEventId() public {
+        // Create event with a deterministic eventId set by the component
+        IWavsIndexer.IndexedEvent[] memory events = new IWavsIndexer.IndexedEvent[](2);
         events[0] = _createMockEvent(123, abi.encode(123), "attestation");
+        events[1] = _createMockEvent(0, abi.encode(456), "attestation");
 
         // Create payload
         IWavsIndexer.IndexingPayload memory payload = _createPayload(events, new bytes32[](0));
@@ -86,11 +87,30 @@ contract WavsIndexerTest is Test {
         (IWavsServiceHandler.Envelope memory envelope, IWavsServiceHandler.SignatureData memory signatureData) =
             _createEnvelopeAndSignature(payload);
 
-        // Expect revert for non-zero eventId in ADD operation
-        vm.expectRevert(IWavsIndexer.ExpectedEventIdZero.selector);
-
         // Call handleSignedEnvelope
         wavsIndexer.handleSignedEnvelope(envelope, signatureData);
+
+        // The provided eventId is kept, and events without one get the envelope-derived eventId
+        assertEq(wavsIndexer.eventExists(bytes32(uint256(123))), true);
+        assertEq(wavsIndexer.getEvent(bytes32(uint256(123))).eventId, bytes32(uint256(123)));
+        assertEq(wavsIndexer.eventExists(_generateEventId(1, 0)), false);
+        assertEq(wavsIndexer.eventExists(_generateEventId(1, 1)), true);
+    }
+
+    function testHandleSignedEnvelope_ShouldRevertOnAddIfProvidedEventIdExists() public {
+        // Create event with a deterministic eventId
+        IWavsIndexer.IndexedEvent[] memory events = new IWavsIndexer.IndexedEvent[](1);
+        events[0] = _createMockEvent(123, abi.encode(123), "attestation");
+
+        // First envelope indexes the event
+        (IWavsServiceHandler.Envelope memory envelope, IWavsServiceHandler.SignatureData memory signatureData) =
+            _createEnvelopeAndSignature(_createPayload(events, new bytes32[](0)));
+        wavsIndexer.handleSignedEnvelope(envelope, signatureData);
+
+        // A different envelope with the same event should revert
+        envelope = _createEnvelope(bytes20(uint160(0x2)), bytes12(uint96(0)), _createPayload(events, new bytes32[](0)));
+        vm.expectRevert(IWavsIndexer.EventAlreadyExists.selector);
+        wavsIndexer.handleSignedEnvelope(envelope, signatureData);
     }
 
     function testHandleSignedEnvelope_ShouldRevertOnAddIfEventExists() public {